@@ -8,11 +8,14 @@ CREATE TYPE accessibility AS ENUM ('Unknown', 'Forbidden', 'Allowed', 'Residenti
 CREATE TABLE nodes (
     id BIGINT PRIMARY KEY,
     longitude DOUBLE PRECISION,
-    latitude DOUBLE PRECISION
+    latitude DOUBLE PRECISION,
+    ele REAL,
+    component INTEGER
 );
 
 CREATE TABLE edges (
-    id TEXT,
+    id BIGINT PRIMARY KEY,
+    composite_id TEXT,
 	osm_id BIGINT,
     source BIGINT REFERENCES nodes(id),
     target BIGINT REFERENCES nodes(id),
@@ -23,6 +26,28 @@ CREATE TABLE edges (
     bike_forward accessibility,
     bike_backward accessibility,
 	train accessibility,
+    moped accessibility,
+    surface TEXT,
+    smoothness TEXT,
+    tracktype TEXT,
+    mtb_scale TEXT,
+    lts_forward TEXT,
+    lts_backward TEXT,
+    lanes_forward SMALLINT,
+    lanes_backward SMALLINT,
+    width REAL,
+    turn_lanes_forward TEXT,
+    turn_lanes_backward TEXT,
+    bridge BOOLEAN,
+    tunnel BOOLEAN,
+    covered BOOLEAN,
+    toll BOOLEAN,
+    layer SMALLINT,
+    ascent REAL,
+    descent REAL,
+    max_grade_forward REAL,
+    max_grade_backward REAL,
+    component INTEGER,
     wkt TEXT
 );
 
@@ -27,6 +27,20 @@
 //!     .unwrap();
 //! ```
 //!
+//! # Querying the Graph
+//!
+//! [`Reader::read_graph`] returns a [`Graph`] indexing nodes and edges,
+//! with outgoing and incoming arcs per mode:
+//!
+//! ```no_run
+//! use osm4routing::{Mode, NodeId};
+//!
+//! let graph = osm4routing::read_graph("map.osm.pbf").unwrap();
+//! if let Some(node) = graph.node_by_osm_id(NodeId(42)) {
+//!     println!("{} neighbors by bike", graph.neighbors(node, Mode::Bike).len());
+//! }
+//! ```
+//!
 //! # Preserving Tags
 //!
 //! By default, only computed accessibility properties are stored.
@@ -73,18 +87,63 @@
 //!
 //! - [`models`]: Core data structures ([`Node`], [`Edge`])
 //! - [`categorize`]: Transportation mode accessibility enums
+//! - [`components`]: Connected components and islands ([`Components`])
+//! - [`conditional`]: Time-limited restrictions ([`ConditionalRestriction`])
+//! - [`country`]: Country-specific default access ([`AccessRules`])
+//! - [`crs`]: Projected coordinate systems of the output ([`Crs`])
+//! - [`csr`]: Compact graph for large networks ([`CsrGraph`])
+//! - [`distance`]: Distance models, from haversine to geodesics on the WGS84 ellipsoid ([`DistanceModel`])
+//! - [`elevation`]: Elevation from local tiles ([`Dem`], [`Climb`])
+//! - [`lanes`]: Lanes per direction ([`Lanes`])
+//! - [`mapmatch`]: Matching GPS traces to edges ([`MapMatcher`])
+//! - [`matrix`]: Cost matrices between locations ([`Matrix`])
+//! - [`merge`]: Merging chains of edges ([`TagPolicy`])
+//! - [`order`]: Order of the output, by id or along a Hilbert curve ([`Order`])
+//! - [`isochrone`]: Everything reachable within a budget ([`Isochrone`])
+//! - [`profile`]: Surface-aware profiles ([`Profile`])
+//! - [`structure`]: Bridges, tunnels, layers and tolls ([`Structure`])
+//! - [`graph`]: Routing graph with adjacency per mode ([`Graph`])
 //! - [`reader`]: PBF file reading and graph construction
+//! - [`routing`]: Shortest paths with Dijkstra and A* ([`Router`])
+//! - [`ch`]: Contraction hierarchies for fast queries ([`ContractionHierarchy`])
+//! - [`simplify`]: Simplification of geometries ([`douglas_peucker`])
+//! - [`spatial`]: Nearest edge of a point ([`EdgeIndex`])
+//! - [`validate`]: Quality checks of the graph ([`Report`])
 //! - [`writers`]: Output formats (CSV)
 //! - [`error`]: Error types
 
 mod osm4routing;
 
 pub use crate::osm4routing::categorize::{
-    BikeAccessibility, CarAccessibility, FootAccessibility, TrainAccessibility,
+    BikeAccessibility, CarAccessibility, Direction, FootAccessibility, Lts, Mode,
+    MopedAccessibility, MtbScale, Smoothness, Surface, TrackType, TrainAccessibility,
 };
+pub use crate::osm4routing::ch::ContractionHierarchy;
+pub use crate::osm4routing::components::{Components, Connectivity};
+pub use crate::osm4routing::conditional::{ConditionalRestriction, LocalTime, TimeCondition};
+pub use crate::osm4routing::country::{AccessRules, DefaultAccess, access_rules};
+pub use crate::osm4routing::crs::{Crs, Reprojection};
+pub use crate::osm4routing::csr::{CsrAdjacency, CsrGraph};
+pub use crate::osm4routing::distance::DistanceModel;
+pub use crate::osm4routing::elevation::{Climb, Dem};
 pub use crate::osm4routing::error::Error;
+pub use crate::osm4routing::graph::{Arc, Graph};
+pub use crate::osm4routing::isochrone::{Isochrone, ReachedEdge};
+pub use crate::osm4routing::lanes::{Lanes, Turn};
+pub use crate::osm4routing::mapmatch::{
+    MapMatcher, MatchedEdge, MatchedTrace, TracePoint, read_gpx, read_trace,
+};
+pub use crate::osm4routing::matrix::{Location, Matrix, Snap, Snapper, read_locations};
+pub use crate::osm4routing::merge::TagPolicy;
 pub use crate::osm4routing::models::*;
-pub use crate::osm4routing::reader::{Reader, read};
+pub use crate::osm4routing::order::{Order, hilbert_index};
+pub use crate::osm4routing::profile::Profile;
+pub use crate::osm4routing::reader::{Reader, read, read_graph};
+pub use crate::osm4routing::routing::{Path, Router, Weight};
+pub use crate::osm4routing::simplify::douglas_peucker;
+pub use crate::osm4routing::spatial::{EdgeIndex, Projection, split_edge};
+pub use crate::osm4routing::structure::{Structure, StructureRange};
+pub use crate::osm4routing::validate::{Issue, IssueKind, Report};
 pub use crate::osm4routing::writers;
 
 // Reexpose crates that are part of the API
@@ -1,10 +1,21 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    convert: Convert,
+}
+
+/// Converts the network to nodes and edges CSV files
+#[derive(clap::Args)]
+struct Convert {
     /// Input OpenStreetMap in the .pbf format
-    source_pbf: String,
+    #[arg(required = true)]
+    source_pbf: Option<String>,
     /// Output path of the csv file that will contain the nodes
     #[arg(short, long, default_value = "nodes.csv")]
     nodes_file: String,
@@ -14,27 +25,250 @@ struct Cli {
     /// Merge two edges from different OSM ways into a single edge when there is no intersection
     #[arg(short, long)]
     merge_edges: bool,
+    /// Keep every OSM node as a vertex, with one edge per pair of consecutive nodes
+    #[arg(long, conflicts_with = "merge_edges")]
+    keep_all_nodes: bool,
+    /// SRTM .hgt or GeoTIFF tile, or directory of tiles, to sample elevations from
+    #[arg(long)]
+    elevation: Option<String>,
+    /// Distance model for the lengths: haversine, vincenty or geodesic
+    #[arg(long, default_value = "haversine")]
+    distance: osm4routing::DistanceModel,
+    /// Simplify the geometries of the edges within this tolerance, in meters
+    #[arg(long)]
+    simplify: Option<f64>,
+    /// Split the edges longer than this length, in meters
+    #[arg(long)]
+    max_edge_length: Option<f64>,
+    /// Coordinate system of the output: wgs84, utm, utm<zone><n|s>, webmercator, lambert93 or a PROJ string
+    #[arg(long, default_value = "wgs84")]
+    crs: osm4routing::Crs,
+    /// Compute the connected components of the network, written in the component column
+    #[arg(long)]
+    components: bool,
+    /// Mode linking the nodes of a component: foot, car, bike or train, every mode if omitted
+    #[arg(long)]
+    component_mode: Option<osm4routing::Mode>,
+    /// Connectivity of the components: weak (edges in any direction) or strong (reachable both ways)
+    #[arg(long, default_value = "weak")]
+    connectivity: osm4routing::Connectivity,
+    /// Keep only the largest connected component of the network
+    #[arg(long)]
+    keep_largest_component: bool,
+    /// Drop the connected components with fewer nodes
+    #[arg(long)]
+    min_component_nodes: Option<usize>,
+    /// Drop the connected components shorter than this length, in meters
+    #[arg(long)]
+    min_component_length: Option<f64>,
+    /// Order of the nodes and edges: id, or hilbert to keep close elements close
+    #[arg(long, default_value = "id")]
+    order: osm4routing::Order,
 }
-fn main() {
-    let cli = Cli::parse();
+
+#[derive(Subcommand)]
+enum Command {
+    /// Computes the cost from every origin to every destination
+    Matrix {
+        /// Input OpenStreetMap in the .pbf format
+        source_pbf: String,
+        /// CSV (id, lon, lat) or GeoJSON points of the origins
+        #[arg(short, long)]
+        origins: String,
+        /// CSV (id, lon, lat) or GeoJSON points of the destinations, the origins if omitted
+        #[arg(short, long)]
+        destinations: Option<String>,
+        /// Mode of transportation: foot, car, bike or train
+        #[arg(long, default_value = "car")]
+        mode: osm4routing::Mode,
+        /// Cost to minimize
+        #[arg(long, value_enum, default_value_t = WeightArg::Time)]
+        weight: WeightArg,
+        /// Output path of the csv file that will contain the matrix
+        #[arg(long, default_value = "matrix.csv")]
+        output: String,
+    },
+    /// Matches a GPS trace to the edges of the network
+    Match {
+        /// Input OpenStreetMap in the .pbf format
+        source_pbf: String,
+        /// GPX or CSV (lon, lat, time) trace
+        trace: String,
+        /// Mode of transportation: foot, car, bike or train
+        #[arg(long, default_value = "car")]
+        mode: osm4routing::Mode,
+        /// Standard deviation of the GPS noise, in meters
+        #[arg(long, default_value_t = 10.)]
+        sigma: f64,
+        /// Maximum distance between a point and its candidate edges, in meters
+        #[arg(long, default_value_t = 50.)]
+        radius: f64,
+        /// Output path of the csv file that will contain the matched edges
+        #[arg(long, default_value = "matched.csv")]
+        output: String,
+    },
+    /// Checks the network for loops, duplicates, dead-ends, crossings and overlaps without a node
+    Validate {
+        /// Input OpenStreetMap in the .pbf format
+        source_pbf: String,
+        /// Merge two edges from different OSM ways into a single edge when there is no intersection
+        #[arg(short, long)]
+        merge_edges: bool,
+        /// Output path of the report, GeoJSON if it ends with .geojson, JSON otherwise
+        #[arg(long, default_value = "validation.geojson")]
+        output: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum WeightArg {
+    /// Length in meters
+    Length,
+    /// Travel time in seconds
+    Time,
+}
+
+fn convert(cli: Convert) -> Result<(), osm4routing::Error> {
     let mut reader = if cli.merge_edges {
         osm4routing::Reader::new().merge_ways()
     } else {
         osm4routing::Reader::new()
     };
+    if cli.keep_all_nodes {
+        reader = reader.keep_all_nodes();
+    }
+    if let Some(elevation) = &cli.elevation {
+        reader = reader.elevation(elevation);
+    }
+    reader = reader.distance_model(cli.distance);
+    if let Some(max_length) = cli.max_edge_length {
+        reader = reader.max_edge_length(max_length);
+    }
+    if let Some(tolerance) = cli.simplify {
+        reader = reader.simplify(tolerance, false);
+    }
+    let small_components = cli.min_component_nodes.is_some() || cli.min_component_length.is_some();
+    if cli.components || cli.keep_largest_component || small_components {
+        reader = reader.components(cli.component_mode, cli.connectivity);
+    }
+    if cli.keep_largest_component {
+        reader = reader.keep_largest_component();
+    } else if small_components {
+        reader = reader.drop_small_components(
+            cli.min_component_nodes.unwrap_or(0),
+            cli.min_component_length.unwrap_or(0.),
+        );
+    }
+    reader = reader.order(cli.order);
+    let source_pbf = cli.source_pbf.unwrap_or_default();
+    let (nodes, edges) = reader.read(&source_pbf)?;
+    osm4routing::writers::csv_in_crs(nodes, edges, &cli.nodes_file, &cli.edges_file, &cli.crs)
+}
 
-    match reader.read(&cli.source_pbf) {
-        Ok((nodes, edges)) => {
-            if let Err(error) =
-                osm4routing::writers::csv(nodes, edges, &cli.nodes_file, &cli.edges_file)
-            {
-                eprintln!("Error: {}", error);
-                std::process::exit(1);
-            }
-        }
-        Err(error) => {
-            eprintln!("Error: {}", error);
-            std::process::exit(1);
+fn matrix(
+    source_pbf: &str,
+    origins: &str,
+    destinations: Option<&str>,
+    mode: osm4routing::Mode,
+    weight: WeightArg,
+    output: &str,
+) -> Result<(), osm4routing::Error> {
+    let origins = osm4routing::read_locations(origins)?;
+    let destinations = match destinations {
+        Some(path) => osm4routing::read_locations(path)?,
+        None => origins.clone(),
+    };
+    let graph = osm4routing::read_graph(source_pbf)?;
+    let weight = match weight {
+        WeightArg::Length => osm4routing::Weight::Length,
+        WeightArg::Time => osm4routing::Weight::TravelTime,
+    };
+    let router = osm4routing::Router::new(&graph, mode, weight);
+    let coords = |locations: &[osm4routing::Location]| -> Vec<_> {
+        locations.iter().map(|location| location.coord).collect()
+    };
+    let matrix = osm4routing::Matrix::new(&router, &coords(&origins), &coords(&destinations));
+    for (location, snap) in origins
+        .iter()
+        .zip(&matrix.origins)
+        .chain(destinations.iter().zip(&matrix.destinations))
+    {
+        if snap.is_none() {
+            eprintln!("Warning: location {} could not be snapped", location.id);
         }
     }
+    osm4routing::writers::matrix_csv(&origins, &destinations, &matrix, output)
+}
+
+fn map_match(
+    source_pbf: &str,
+    trace: &str,
+    mode: osm4routing::Mode,
+    sigma: f64,
+    radius: f64,
+    output: &str,
+) -> Result<(), osm4routing::Error> {
+    let trace = osm4routing::read_trace(trace)?;
+    let graph = osm4routing::read_graph(source_pbf)?;
+    let matcher = osm4routing::MapMatcher::new(&graph, mode)
+        .sigma(sigma)
+        .radius(radius);
+    let matched = matcher.match_trace(&trace);
+    let unmatched = matched.points.iter().filter(|p| p.is_none()).count();
+    if unmatched > 0 {
+        eprintln!("Warning: {unmatched} points have no edge within {radius} m");
+    }
+    osm4routing::writers::matched_csv(&graph, &matched, output)
+}
+
+fn validate(source_pbf: &str, merge_edges: bool, output: &str) -> Result<(), osm4routing::Error> {
+    let mut reader = osm4routing::Reader::new();
+    if merge_edges {
+        reader = reader.merge_ways();
+    }
+    let (nodes, edges) = reader.read(source_pbf)?;
+    let report = osm4routing::Report::new(&nodes, &edges);
+    for kind in osm4routing::IssueKind::ALL {
+        println!("{}: {}", kind.name(), report.count(kind));
+    }
+    osm4routing::writers::report_json(&report, output)
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Some(Command::Matrix {
+            source_pbf,
+            origins,
+            destinations,
+            mode,
+            weight,
+            output,
+        }) => matrix(
+            &source_pbf,
+            &origins,
+            destinations.as_deref(),
+            mode,
+            weight,
+            &output,
+        ),
+        Some(Command::Match {
+            source_pbf,
+            trace,
+            mode,
+            sigma,
+            radius,
+            output,
+        }) => map_match(&source_pbf, &trace, mode, sigma, radius, &output),
+        Some(Command::Validate {
+            source_pbf,
+            merge_edges,
+            output,
+        }) => validate(&source_pbf, merge_edges, &output),
+        None => convert(cli.convert),
+    };
+    if let Err(error) = result {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
 }
@@ -3,6 +3,7 @@
 //! This module defines enums and logic for determining whether an edge
 //! is accessible by foot, car, bike, or train based on OpenStreetMap tags.
 
+use super::lanes::Lanes;
 use serde::Serialize;
 
 /// Accessibility level for pedestrians.
@@ -37,6 +38,22 @@ pub enum CarAccessibility {
     Motorway,
 }
 
+impl CarAccessibility {
+    /// Default speed in km/h of the road class, used when `maxspeed` is not tagged.
+    ///
+    /// Returns `None` if the road is not open to cars.
+    pub fn default_speed(self) -> Option<u16> {
+        match self {
+            CarAccessibility::Unknown | CarAccessibility::Forbidden => None,
+            CarAccessibility::Residential => Some(40),
+            CarAccessibility::Tertiary | CarAccessibility::Secondary => Some(50),
+            CarAccessibility::Primary => Some(60),
+            CarAccessibility::Trunk => Some(80),
+            CarAccessibility::Motorway => Some(110),
+        }
+    }
+}
+
 /// Accessibility level for bicycles.
 #[derive(Clone, Copy, Debug, Serialize, PartialEq)]
 pub enum BikeAccessibility {
@@ -65,6 +82,337 @@ pub enum TrainAccessibility {
     Allowed,
 }
 
+/// Accessibility level for mopeds.
+///
+/// Mopeds are not a routing mode: their access is only derived, by default
+/// from the car access and by country from the highway type (Dutch
+/// cycleways are open to mopeds), and written with the other properties.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+pub enum MopedAccessibility {
+    /// Not yet determined from tags.
+    Unknown,
+    /// Forbidden for mopeds.
+    Forbidden,
+    /// Allowed for mopeds.
+    Allowed,
+}
+
+/// Transportation modes handled by the categorization.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Pedestrians.
+    Foot,
+    /// Cars.
+    Car,
+    /// Bicycles.
+    Bike,
+    /// Trains.
+    Train,
+}
+
+impl Mode {
+    /// All the modes, in a stable order.
+    pub const ALL: [Mode; 4] = [Mode::Foot, Mode::Car, Mode::Bike, Mode::Train];
+
+    /// Position of the mode in [`Mode::ALL`], to index per-mode arrays.
+    pub(crate) fn index(self) -> usize {
+        match self {
+            Mode::Foot => 0,
+            Mode::Car => 1,
+            Mode::Bike => 2,
+            Mode::Train => 3,
+        }
+    }
+}
+
+impl std::str::FromStr for Mode {
+    type Err = String;
+
+    /// Parses `foot`, `car`, `bike` or `train`.
+    fn from_str(s: &str) -> Result<Mode, String> {
+        match s {
+            "foot" => Ok(Mode::Foot),
+            "car" => Ok(Mode::Car),
+            "bike" => Ok(Mode::Bike),
+            "train" => Ok(Mode::Train),
+            _ => Err(format!(
+                "unknown mode {s}, expected foot, car, bike or train"
+            )),
+        }
+    }
+}
+
+/// Direction of travel along an edge, relative to its geometry.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// From source to target.
+    Forward,
+    /// From target to source.
+    Backward,
+}
+
+impl Direction {
+    /// Returns the opposite direction.
+    pub fn reverse(self) -> Direction {
+        match self {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+}
+
+/// Normalized road surface.
+///
+/// http://wiki.openstreetmap.org/wiki/Key:surface
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, Hash)]
+pub enum Surface {
+    /// No `surface` tag, or an unrecognized value.
+    Unknown,
+    /// `asphalt`, `chipseal`
+    Asphalt,
+    /// `concrete`, `concrete:plates`, `concrete:lanes`
+    Concrete,
+    /// `paving_stones`, `bricks`
+    PavingStones,
+    /// `sett`, `cobblestone`, `unhewn_cobblestone`
+    Sett,
+    /// `wood`
+    Wood,
+    /// `metal`, `metal_grid`
+    Metal,
+    /// `paved` without further details
+    Paved,
+    /// `compacted`
+    Compacted,
+    /// `fine_gravel`
+    FineGravel,
+    /// `gravel`, `pebblestone`, `rock`
+    Gravel,
+    /// `ground`, `dirt`, `earth`, `mud`
+    Ground,
+    /// `grass`, `grass_paver`
+    Grass,
+    /// `sand`
+    Sand,
+    /// `unpaved` without further details
+    Unpaved,
+}
+
+impl Surface {
+    /// Parses the value of a `surface` tag.
+    pub fn from_tag(val: &str) -> Surface {
+        match val {
+            "asphalt" | "chipseal" => Surface::Asphalt,
+            "concrete" | "concrete:plates" | "concrete:lanes" => Surface::Concrete,
+            "paving_stones" | "bricks" => Surface::PavingStones,
+            "sett" | "cobblestone" | "unhewn_cobblestone" => Surface::Sett,
+            "wood" => Surface::Wood,
+            "metal" | "metal_grid" => Surface::Metal,
+            "paved" => Surface::Paved,
+            "compacted" => Surface::Compacted,
+            "fine_gravel" => Surface::FineGravel,
+            "gravel" | "pebblestone" | "rock" => Surface::Gravel,
+            "ground" | "dirt" | "earth" | "mud" => Surface::Ground,
+            "grass" | "grass_paver" => Surface::Grass,
+            "sand" => Surface::Sand,
+            "unpaved" => Surface::Unpaved,
+            _ => Surface::Unknown,
+        }
+    }
+
+    /// Returns true if the surface is known to be paved.
+    pub fn is_paved(self) -> bool {
+        matches!(
+            self,
+            Surface::Asphalt
+                | Surface::Concrete
+                | Surface::PavingStones
+                | Surface::Sett
+                | Surface::Wood
+                | Surface::Metal
+                | Surface::Paved
+        )
+    }
+}
+
+/// Physical usability of a way for wheeled vehicles.
+///
+/// Variants are ordered from best to worst, with `Unknown` first.
+///
+/// http://wiki.openstreetmap.org/wiki/Key:smoothness
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Smoothness {
+    /// No `smoothness` tag, or an unrecognized value.
+    Unknown,
+    /// Usable by roller blades.
+    Excellent,
+    /// Usable by racing bikes.
+    Good,
+    /// Usable by city bikes, wheelchairs and sport cars.
+    Intermediate,
+    /// Usable by trekking bikes and normal cars.
+    Bad,
+    /// Usable by high-clearance vehicles.
+    VeryBad,
+    /// Usable by off-road vehicles.
+    Horrible,
+    /// Usable by specialized off-road vehicles.
+    VeryHorrible,
+    /// No wheeled vehicle.
+    Impassable,
+}
+
+impl Smoothness {
+    /// Parses the value of a `smoothness` tag.
+    pub fn from_tag(val: &str) -> Smoothness {
+        match val {
+            "excellent" => Smoothness::Excellent,
+            "good" => Smoothness::Good,
+            "intermediate" => Smoothness::Intermediate,
+            "bad" => Smoothness::Bad,
+            "very_bad" => Smoothness::VeryBad,
+            "horrible" => Smoothness::Horrible,
+            "very_horrible" => Smoothness::VeryHorrible,
+            "impassable" => Smoothness::Impassable,
+            _ => Smoothness::Unknown,
+        }
+    }
+}
+
+/// Firmness of a `highway=track`, from solid (grade 1) to soft (grade 5).
+///
+/// http://wiki.openstreetmap.org/wiki/Key:tracktype
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrackType {
+    /// No `tracktype` tag, or an unrecognized value.
+    Unknown,
+    /// Paved or heavily compacted hardcore surface.
+    Grade1,
+    /// Gravel or compacted material, mostly solid.
+    Grade2,
+    /// Mixture of hard and soft materials.
+    Grade3,
+    /// Mostly soft, grass or soil with some hard material.
+    Grade4,
+    /// Soft, without hard materials.
+    Grade5,
+}
+
+impl TrackType {
+    /// Parses the value of a `tracktype` tag.
+    pub fn from_tag(val: &str) -> TrackType {
+        match val {
+            "grade1" => TrackType::Grade1,
+            "grade2" => TrackType::Grade2,
+            "grade3" => TrackType::Grade3,
+            "grade4" => TrackType::Grade4,
+            "grade5" => TrackType::Grade5,
+            _ => TrackType::Unknown,
+        }
+    }
+}
+
+/// Mountain bike difficulty, from S0 (no difficulty) to S6 (not rideable).
+///
+/// http://wiki.openstreetmap.org/wiki/Key:mtb:scale
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MtbScale {
+    /// No `mtb:scale` tag, or an unrecognized value.
+    Unknown,
+    /// Gravel or packed earth, no obstacles.
+    S0,
+    /// Small obstacles such as roots and small stones.
+    S1,
+    /// Larger obstacles, steps and loose ground.
+    S2,
+    /// Blocked switchbacks, big rocks.
+    S3,
+    /// Very steep, rock plates and big roots.
+    S4,
+    /// Very steep with boulder fields.
+    S5,
+    /// Not rideable.
+    S6,
+}
+
+impl MtbScale {
+    /// Parses the value of a `mtb:scale` tag.
+    ///
+    /// The `+` and `-` modifiers (e.g. `2+`) are ignored.
+    pub fn from_tag(val: &str) -> MtbScale {
+        match val.trim_end_matches(['+', '-']) {
+            "0" => MtbScale::S0,
+            "1" => MtbScale::S1,
+            "2" => MtbScale::S2,
+            "3" => MtbScale::S3,
+            "4" => MtbScale::S4,
+            "5" => MtbScale::S5,
+            "6" => MtbScale::S6,
+            _ => MtbScale::Unknown,
+        }
+    }
+}
+
+/// Bicycle Level of Traffic Stress, following the Mineta/Furth methodology.
+///
+/// LTS 1 is suitable for children, LTS 4 only for strong and fearless cyclists.
+/// Values are serialized as their number (`1` to `4`).
+///
+/// https://transweb.sjsu.edu/research/low-stress-bicycling-and-network-connectivity
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lts {
+    /// Not yet computed.
+    Unknown,
+    /// Bikes are not allowed.
+    Forbidden,
+    /// Separated from traffic, or calm residential streets.
+    #[serde(rename = "1")]
+    Lts1,
+    /// Tolerable by most adults.
+    #[serde(rename = "2")]
+    Lts2,
+    /// Tolerable by enthused and confident cyclists.
+    #[serde(rename = "3")]
+    Lts3,
+    /// Only tolerated by strong and fearless cyclists.
+    #[serde(rename = "4")]
+    Lts4,
+}
+
+/// Parses the value of a `maxspeed` tag into km/h.
+///
+/// Handles plain numbers, `mph` values, `walk` and the usual implicit
+/// country zones (`FR:urban`, `DE:rural`…).
+fn parse_maxspeed(val: &str) -> Option<u16> {
+    if let Some(mph) = val.strip_suffix("mph") {
+        return mph
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .map(|mph| (mph * 1.609_344).round() as u16);
+    }
+    if let Ok(speed) = val.trim().parse::<f64>() {
+        return Some(speed.round() as u16);
+    }
+    match val.split_once(':').map(|(_, zone)| zone).unwrap_or(val) {
+        "walk" => Some(6),
+        "living_street" => Some(20),
+        "urban" => Some(50),
+        "rural" => Some(90),
+        "trunk" => Some(110),
+        "motorway" => Some(130),
+        _ => None,
+    }
+}
+
+/// Returns true if the value of a `parking*` tag means cars park on the street.
+fn is_parking(val: &str) -> bool {
+    !matches!(
+        val,
+        "no" | "none" | "separate" | "no_parking" | "no_stopping" | "fire_lane"
+    )
+}
+
 /// Properties of an edge describing accessibility for all transportation modes.
 ///
 /// Tracks accessibility separately for forward and backward directions
@@ -83,6 +431,24 @@ pub struct EdgeProperties {
     pub bike_backward: BikeAccessibility,
     /// Train accessibility (direction-independent).
     pub train: TrainAccessibility,
+    /// Moped accessibility (direction-independent).
+    pub moped: MopedAccessibility,
+    /// Road surface.
+    pub surface: Surface,
+    /// Physical usability for wheeled vehicles.
+    pub smoothness: Smoothness,
+    /// Firmness of tracks.
+    pub tracktype: TrackType,
+    /// Mountain bike difficulty.
+    pub mtb_scale: MtbScale,
+    /// Speed limit in km/h, if tagged.
+    pub maxspeed: Option<u16>,
+    /// Cars park along the road.
+    pub parking: bool,
+    /// Bike Level of Traffic Stress in the forward direction.
+    pub lts_forward: Lts,
+    /// Bike Level of Traffic Stress in the backward direction.
+    pub lts_backward: Lts,
 }
 
 impl Default for EdgeProperties {
@@ -94,6 +460,15 @@ impl Default for EdgeProperties {
             bike_forward: BikeAccessibility::Unknown,
             bike_backward: BikeAccessibility::Unknown,
             train: TrainAccessibility::Unknown,
+            moped: MopedAccessibility::Unknown,
+            surface: Surface::Unknown,
+            smoothness: Smoothness::Unknown,
+            tracktype: TrackType::Unknown,
+            mtb_scale: MtbScale::Unknown,
+            maxspeed: None,
+            parking: false,
+            lts_forward: Lts::Unknown,
+            lts_backward: Lts::Unknown,
         }
     }
 }
@@ -104,7 +479,11 @@ impl EdgeProperties {
     /// Applies the following rules:
     /// - If `car_backward` is Unknown, copy from `car_forward`
     /// - If `bike_backward` is Unknown, copy from `bike_forward`
+    /// - If `moped` is Unknown, allow it on the roads open to cars but motorways
     /// - Any remaining Unknown values become Forbidden
+    ///
+    /// The bike Level of Traffic Stress is then computed for both directions,
+    /// with the default lane count of the road class; see [`EdgeProperties::update_lts`].
     pub fn normalize(&mut self) {
         if self.car_backward == CarAccessibility::Unknown {
             self.car_backward = self.car_forward;
@@ -130,6 +509,122 @@ impl EdgeProperties {
         if self.train == TrainAccessibility::Unknown {
             self.train = TrainAccessibility::Forbidden;
         }
+        if self.moped == MopedAccessibility::Unknown {
+            let road = |car| {
+                !matches!(
+                    car,
+                    CarAccessibility::Forbidden | CarAccessibility::Motorway
+                )
+            };
+            self.moped = if road(self.car_forward) || road(self.car_backward) {
+                MopedAccessibility::Allowed
+            } else {
+                MopedAccessibility::Forbidden
+            };
+        }
+        self.lts_forward = self.lts(self.bike_forward, self.car_backward, None);
+        self.lts_backward = self.lts(self.bike_backward, self.car_forward, None);
+    }
+
+    /// Computes the bike Level of Traffic Stress again with the lanes of the edge.
+    ///
+    /// The lanes must be normalized with these properties. A bike riding
+    /// against a one-way street meets the lanes of the other direction.
+    pub fn update_lts(&mut self, lanes: &Lanes) {
+        let count = |own: u8, other: u8| Some(if own > 0 { own } else { other }).filter(|&n| n > 0);
+        self.lts_forward = self.lts(
+            self.bike_forward,
+            self.car_backward,
+            count(lanes.forward, lanes.backward),
+        );
+        self.lts_backward = self.lts(
+            self.bike_backward,
+            self.car_forward,
+            count(lanes.backward, lanes.forward),
+        );
+    }
+
+    /// Computes the Level of Traffic Stress of a bike in one direction.
+    ///
+    /// The road class gives default speed and lane count when `maxspeed`
+    /// is not tagged and the lanes are not known.
+    ///
+    /// # Arguments
+    /// * `bike` - The bike accessibility in that direction.
+    /// * `opposite_car` - The car accessibility in the other direction,
+    ///   used to know if the lanes are shared between both directions.
+    /// * `lanes` - The number of lanes in that direction, if known.
+    fn lts(
+        &self,
+        bike: BikeAccessibility,
+        opposite_car: CarAccessibility,
+        lanes: Option<u8>,
+    ) -> Lts {
+        let road = if self.car_forward != CarAccessibility::Forbidden {
+            self.car_forward
+        } else {
+            self.car_backward
+        };
+        let Some(default_speed) = road.default_speed() else {
+            // No motorized traffic at all
+            return match bike {
+                BikeAccessibility::Unknown | BikeAccessibility::Forbidden => Lts::Forbidden,
+                _ => Lts::Lts1,
+            };
+        };
+        let default_lanes = match road {
+            CarAccessibility::Trunk | CarAccessibility::Motorway => 2,
+            _ => 1,
+        };
+        let speed = self.maxspeed.unwrap_or(default_speed);
+        let lanes = lanes.unwrap_or(default_lanes);
+
+        match bike {
+            BikeAccessibility::Unknown | BikeAccessibility::Forbidden => Lts::Forbidden,
+            BikeAccessibility::Track => Lts::Lts1,
+            BikeAccessibility::Lane | BikeAccessibility::Busway => {
+                let lts = if self.parking {
+                    match (lanes, speed) {
+                        (2.., _) => Lts::Lts3,
+                        (_, ..=40) => Lts::Lts1,
+                        (_, ..=50) => Lts::Lts2,
+                        _ => Lts::Lts3,
+                    }
+                } else {
+                    match (lanes, speed) {
+                        (3.., ..=65) => Lts::Lts3,
+                        (3.., _) => Lts::Lts4,
+                        (2, ..=65) => Lts::Lts2,
+                        (_, ..=50) => Lts::Lts1,
+                        (_, ..=65) => Lts::Lts3,
+                        _ => Lts::Lts4,
+                    }
+                };
+                // Sharing the lane with buses is never a low-stress experience
+                if bike == BikeAccessibility::Busway {
+                    lts.max(Lts::Lts2)
+                } else {
+                    lts
+                }
+            }
+            BikeAccessibility::Allowed => {
+                // Mixed traffic, lanes counted for both directions
+                let total_lanes = if opposite_car == CarAccessibility::Forbidden {
+                    lanes
+                } else {
+                    lanes * 2
+                };
+                let residential = road == CarAccessibility::Residential;
+                match (total_lanes, speed) {
+                    (..=3, ..=40) if residential => Lts::Lts1,
+                    (..=3, ..=40) => Lts::Lts2,
+                    (..=5, ..=40) => Lts::Lts3,
+                    (..=3, ..=50) if residential => Lts::Lts2,
+                    (..=3, ..=56) => Lts::Lts3,
+                    _ => Lts::Lts4,
+                }
+            }
+        }
     }
 
     /// Check if at least one transportation mode can use this edge in any direction.
@@ -144,6 +639,83 @@ impl EdgeProperties {
             || self.train != TrainAccessibility::Forbidden
     }
 
+    /// Forbid a transportation mode in both directions.
+    pub fn forbid(&mut self, mode: Mode) {
+        match mode {
+            Mode::Foot => self.foot = FootAccessibility::Forbidden,
+            Mode::Car => {
+                self.car_forward = CarAccessibility::Forbidden;
+                self.car_backward = CarAccessibility::Forbidden;
+            }
+            Mode::Bike => {
+                self.bike_forward = BikeAccessibility::Forbidden;
+                self.bike_backward = BikeAccessibility::Forbidden;
+                self.lts_forward = Lts::Forbidden;
+                self.lts_backward = Lts::Forbidden;
+            }
+            Mode::Train => self.train = TrainAccessibility::Forbidden,
+        }
+    }
+
+    /// Returns the properties of the edge travelled from its target to its source.
+    ///
+    /// Forward and backward accessibility and traffic stress are swapped.
+    pub fn reverse(self) -> EdgeProperties {
+        EdgeProperties {
+            car_forward: self.car_backward,
+            car_backward: self.car_forward,
+            bike_forward: self.bike_backward,
+            bike_backward: self.bike_forward,
+            lts_forward: self.lts_backward,
+            lts_backward: self.lts_forward,
+            ..self
+        }
+    }
+
+    /// Check if a transportation mode can use this edge in a direction.
+    ///
+    /// Pedestrians and trains can always use an edge in both directions.
+    pub fn allows(self, mode: Mode, direction: Direction) -> bool {
+        let (car, bike) = match direction {
+            Direction::Forward => (self.car_forward, self.bike_forward),
+            Direction::Backward => (self.car_backward, self.bike_backward),
+        };
+        match mode {
+            Mode::Foot => self.foot == FootAccessibility::Allowed,
+            Mode::Car => !matches!(car, CarAccessibility::Unknown | CarAccessibility::Forbidden),
+            Mode::Bike => !matches!(
+                bike,
+                BikeAccessibility::Unknown | BikeAccessibility::Forbidden
+            ),
+            Mode::Train => self.train == TrainAccessibility::Allowed,
+        }
+    }
+
+    /// Returns the typical speed in km/h of a transportation mode in a direction.
+    ///
+    /// Cars drive at `maxspeed` if tagged, otherwise at the default speed of the
+    /// road class (see [`CarAccessibility::default_speed`]). Pedestrians walk at 5 km/h, bikes ride at 15 km/h and trains
+    /// run at 100 km/h. Returns `None` if the mode cannot use the edge.
+    pub fn speed(self, mode: Mode, direction: Direction) -> Option<f64> {
+        if !self.allows(mode, direction) {
+            return None;
+        }
+        let car = match direction {
+            Direction::Forward => self.car_forward,
+            Direction::Backward => self.car_backward,
+        };
+        let speed = match mode {
+            Mode::Foot => 5,
+            Mode::Bike => 15,
+            Mode::Train => 100,
+            Mode::Car => self
+                .maxspeed
+                .filter(|speed| *speed > 0)
+                .or(car.default_speed())?,
+        };
+        Some(speed as f64)
+    }
+
     /// Update properties based on an OSM tag key-value pair (owned strings).
     ///
     /// # Arguments
@@ -162,10 +734,13 @@ impl EdgeProperties {
     /// - `pedestrian`/`foot`: pedestrian access
     /// - `cycleway`: bike lane/track configuration
     /// - `bicycle`: bike access
+    /// - `moped`: moped access
     /// - `busway`: bus lane access for bikes
-    /// - `oneway`: one-way restriction
+    /// - `oneway`, `oneway:bicycle=no`: one-way restriction, and bikes exempted from it
     /// - `junction=roundabout`: roundabout handling
     /// - `railway`: train access
+    /// - `surface`, `smoothness`, `tracktype`, `mtb:scale`: surface quality
+    /// - `maxspeed`, `parking:*`: traffic stress inputs, with the lanes read by [`Lanes`]
     ///
     /// # Arguments
     /// * `key` - The OSM tag key.
@@ -231,6 +806,10 @@ impl EdgeProperties {
                 "no" | "false" => self.bike_forward = BikeAccessibility::Forbidden,
                 _ => self.bike_forward = BikeAccessibility::Allowed,
             },
+            "moped" => match val {
+                "no" | "false" => self.moped = MopedAccessibility::Forbidden,
+                _ => self.moped = MopedAccessibility::Allowed,
+            },
             "busway" => match val {
                 "opposite_lane" | "opposite_track" => {
                     self.bike_backward = BikeAccessibility::Busway
@@ -246,17 +825,32 @@ impl EdgeProperties {
                 }
                 _ => {}
             },
-            "junction" => {
-                if val == "roundabout" {
-                    self.car_backward = CarAccessibility::Forbidden;
-                    if self.bike_backward == BikeAccessibility::Unknown {
-                        self.bike_backward = BikeAccessibility::Forbidden;
-                    }
+            // Read after `oneway`, as tags are sorted by key
+            "oneway:bicycle"
+                if val == "no" && self.bike_backward == BikeAccessibility::Forbidden =>
+            {
+                self.bike_backward = BikeAccessibility::Unknown;
+            }
+            "junction" if val == "roundabout" => {
+                self.car_backward = CarAccessibility::Forbidden;
+                if self.bike_backward == BikeAccessibility::Unknown {
+                    self.bike_backward = BikeAccessibility::Forbidden;
                 }
             }
             "railway" => {
                 self.train = TrainAccessibility::Allowed;
             }
+            "surface" => self.surface = Surface::from_tag(val),
+            "smoothness" => self.smoothness = Smoothness::from_tag(val),
+            "tracktype" => self.tracktype = TrackType::from_tag(val),
+            "mtb:scale" => self.mtb_scale = MtbScale::from_tag(val),
+            "maxspeed" => self.maxspeed = parse_maxspeed(val),
+            "parking:lane:both" | "parking:lane:left" | "parking:lane:right" | "parking:both"
+            | "parking:left" | "parking:right"
+                if is_parking(val) =>
+            {
+                self.parking = true;
+            }
             _ => {}
         }
     }
@@ -273,9 +867,26 @@ fn test_accessible() {
 }
 
 #[test]
-fn test_normalize() {
+fn test_allows() {
     let mut p = EdgeProperties::default();
-    p.bike_forward = BikeAccessibility::Lane;
+    p.update_with_str("highway", "secondary");
+    p.update_with_str("oneway", "yes");
+    p.normalize();
+    assert!(p.allows(Mode::Car, Direction::Forward));
+    assert!(!p.allows(Mode::Car, Direction::Backward));
+    assert!(p.allows(Mode::Foot, Direction::Backward));
+    assert!(!p.allows(Mode::Train, Direction::Forward));
+    assert_eq!(Some(50.), p.speed(Mode::Car, Direction::Forward));
+    assert_eq!(None, p.speed(Mode::Car, Direction::Backward));
+    assert_eq!(Some(5.), p.speed(Mode::Foot, Direction::Backward));
+}
+
+#[test]
+fn test_normalize() {
+    let mut p = EdgeProperties {
+        bike_forward: BikeAccessibility::Lane,
+        ..Default::default()
+    };
     p.normalize();
     assert_eq!(BikeAccessibility::Lane, p.bike_backward);
     p.bike_forward = BikeAccessibility::Allowed;
@@ -367,3 +978,95 @@ fn test_update() {
     p.update_with_str("junction", "roundabout");
     assert_eq!(BikeAccessibility::Forbidden, p.bike_backward);
 }
+
+#[test]
+fn test_surface() {
+    let mut p = EdgeProperties::default();
+    p.update_with_str("surface", "fine_gravel");
+    assert_eq!(Surface::FineGravel, p.surface);
+    assert!(!p.surface.is_paved());
+
+    p.update_with_str("surface", "concrete:plates");
+    assert_eq!(Surface::Concrete, p.surface);
+    assert!(p.surface.is_paved());
+
+    p.update_with_str("surface", "something_else");
+    assert_eq!(Surface::Unknown, p.surface);
+
+    p.update_with_str("smoothness", "very_bad");
+    assert_eq!(Smoothness::VeryBad, p.smoothness);
+    assert!(p.smoothness > Smoothness::Intermediate);
+
+    p.update_with_str("tracktype", "grade3");
+    assert_eq!(TrackType::Grade3, p.tracktype);
+
+    p.update_with_str("mtb:scale", "2+");
+    assert_eq!(MtbScale::S2, p.mtb_scale);
+}
+
+#[test]
+fn test_parse_maxspeed() {
+    assert_eq!(Some(50), parse_maxspeed("50"));
+    assert_eq!(Some(48), parse_maxspeed("30 mph"));
+    assert_eq!(Some(50), parse_maxspeed("FR:urban"));
+    assert_eq!(Some(6), parse_maxspeed("walk"));
+    assert_eq!(None, parse_maxspeed("none"));
+}
+
+#[test]
+fn test_lts() {
+    let lts = |tags: &[(&str, &str)]| {
+        let mut p = EdgeProperties::default();
+        for (key, val) in tags {
+            p.update_with_str(key, val);
+        }
+        p.normalize();
+        let mut lanes = Lanes::default();
+        for (key, val) in tags {
+            lanes.update_with_str(key, val);
+        }
+        lanes.normalize(&p);
+        p.update_lts(&lanes);
+        (p.lts_forward, p.lts_backward)
+    };
+
+    assert_eq!((Lts::Lts1, Lts::Lts1), lts(&[("highway", "cycleway")]));
+    assert_eq!((Lts::Lts1, Lts::Lts1), lts(&[("highway", "residential")]));
+    assert_eq!(
+        (Lts::Lts2, Lts::Lts2),
+        lts(&[("highway", "residential"), ("maxspeed", "50")])
+    );
+    assert_eq!(
+        (Lts::Lts4, Lts::Lts4),
+        lts(&[("highway", "primary"), ("maxspeed", "70")])
+    );
+    assert_eq!(
+        (Lts::Lts1, Lts::Lts1),
+        lts(&[
+            ("highway", "primary"),
+            ("maxspeed", "70"),
+            ("cycleway", "track")
+        ])
+    );
+    assert_eq!(
+        (Lts::Lts3, Lts::Lts3),
+        lts(&[
+            ("highway", "secondary"),
+            ("cycleway", "lane"),
+            ("lanes", "4"),
+            ("parking:lane:both", "parallel"),
+        ])
+    );
+    assert_eq!(
+        (Lts::Lts1, Lts::Forbidden),
+        lts(&[
+            ("highway", "tertiary"),
+            ("cycleway", "lane"),
+            ("oneway", "yes")
+        ])
+    );
+    assert_eq!(
+        (Lts::Forbidden, Lts::Forbidden),
+        lts(&[("highway", "motorway")])
+    );
+}
@@ -0,0 +1,801 @@
+//! Contraction Hierarchies for fast shortest path queries.
+//!
+//! Nodes are contracted one by one, least important first: shortcuts are
+//! added between their neighbors when no other path (a witness) is as short.
+//! Queries then only follow arcs towards more important nodes, from both ends,
+//! and settle a few hundred nodes instead of a whole country.
+//!
+//! A hierarchy is built for one mode and one weight from a [`Router`], and can
+//! be saved to disk. It refers to nodes and edges by their index in the
+//! [`Graph`] it was built from, and records the mode, the weight, the size of
+//! that graph and a fingerprint of its costs to detect a hierarchy used with
+//! another graph or another profile.
+
+use super::categorize::{Direction, Mode};
+use super::csr::{invalid_data, read_values, write_values};
+use super::error::Error;
+use super::graph::{Arc, Graph};
+use super::models::fnv1a;
+use super::routing::{Path, Router, State};
+use ahash::{HashMap, HashMapExt};
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{Read, Write};
+use std::path::Path as FilePath;
+
+/// Header of the binary file, with the format version.
+const MAGIC: &[u8; 8] = b"O4RCH003";
+
+/// Weights that can be recorded in the binary file, see [`Weight::name`](super::routing::Weight::name).
+const WEIGHTS: [&str; 4] = ["length", "travel_time", "profile", "custom"];
+
+/// Marks an arc that is not a shortcut, or a shortcut that has no edge.
+const NONE: u32 = u32::MAX;
+
+/// Maximum number of nodes settled by a witness search.
+///
+/// A larger limit finds more witnesses, hence fewer shortcuts, but makes
+/// the preprocessing slower.
+const WITNESS_LIMIT: usize = 500;
+
+/// Costs from the reached nodes to each target, by target position.
+pub(crate) type Buckets = HashMap<usize, Vec<(usize, f64)>>;
+
+/// Arc used to reach each node of a search, with the node at its other end.
+type SearchTree = HashMap<usize, (usize, ChArc)>;
+
+/// An arc of the hierarchy: an original arc, or a shortcut.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ChArc {
+    /// The node at the other end of the arc.
+    node: u32,
+    weight: f64,
+    /// The contracted node bypassed by a shortcut, `NONE` for an original arc.
+    via: u32,
+    /// The edge of an original arc.
+    edge: u32,
+    /// Whether an original arc follows its edge from source to target.
+    forward: bool,
+}
+
+/// Inserts an arc, keeping only the cheapest arc towards each node.
+fn insert_min(arcs: &mut Vec<ChArc>, arc: ChArc) {
+    match arcs.iter_mut().find(|a| a.node == arc.node) {
+        Some(existing) if arc.weight < existing.weight => *existing = arc,
+        Some(_) => {}
+        None => arcs.push(arc),
+    }
+}
+
+/// Arcs of the hierarchy stored at their least important node.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ChAdjacency {
+    offsets: Vec<u32>,
+    arcs: Vec<ChArc>,
+}
+
+impl ChAdjacency {
+    fn new(lists: Vec<Vec<ChArc>>) -> ChAdjacency {
+        let mut offsets = vec![0];
+        let mut arcs = Vec::new();
+        for list in lists {
+            arcs.extend(list);
+            offsets.push(arcs.len() as u32);
+        }
+        ChAdjacency { offsets, arcs }
+    }
+
+    fn of(&self, node: usize) -> &[ChArc] {
+        &self.arcs[self.offsets[node] as usize..self.offsets[node + 1] as usize]
+    }
+}
+
+/// Bounded Dijkstra looking for paths that make a shortcut useless.
+struct Witness {
+    costs: Vec<f64>,
+    touched: Vec<usize>,
+}
+
+impl Witness {
+    /// Computes the costs from `source` without going through `skip`, up to `max`.
+    fn run(&mut self, out: &[Vec<ChArc>], source: usize, skip: usize, max: f64) {
+        for node in self.touched.drain(..) {
+            self.costs[node] = f64::INFINITY;
+        }
+        let mut queue = BinaryHeap::new();
+        self.costs[source] = 0.;
+        self.touched.push(source);
+        queue.push(State {
+            key: 0.,
+            node: source,
+        });
+        let mut settled = 0;
+        while let Some(State { key, node }) = queue.pop() {
+            if key > self.costs[node] {
+                continue;
+            }
+            settled += 1;
+            if key > max || settled > WITNESS_LIMIT {
+                break;
+            }
+            for arc in &out[node] {
+                let next = arc.node as usize;
+                let cost = key + arc.weight;
+                if next != skip && cost < self.costs[next] {
+                    if self.costs[next].is_infinite() {
+                        self.touched.push(next);
+                    }
+                    self.costs[next] = cost;
+                    queue.push(State {
+                        key: cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// The graph being contracted.
+struct Contraction {
+    out: Vec<Vec<ChArc>>,
+    inc: Vec<Vec<ChArc>>,
+    witness: Witness,
+}
+
+impl Contraction {
+    /// Returns the shortcuts `(from, to, weight)` needed to contract a node.
+    fn shortcuts(&mut self, node: usize) -> Vec<(usize, usize, f64)> {
+        let mut shortcuts = Vec::new();
+        let max_out = self.out[node].iter().map(|a| a.weight).fold(0., f64::max);
+        for i in 0..self.inc[node].len() {
+            let incoming = self.inc[node][i];
+            let from = incoming.node as usize;
+            self.witness
+                .run(&self.out, from, node, incoming.weight + max_out);
+            for outgoing in &self.out[node] {
+                let to = outgoing.node as usize;
+                let weight = incoming.weight + outgoing.weight;
+                if to != from && self.witness.costs[to] > weight {
+                    shortcuts.push((from, to, weight));
+                }
+            }
+        }
+        shortcuts
+    }
+
+    /// Importance of a node: the edge difference, plus the contracted neighbors.
+    fn priority(&mut self, node: usize, contracted_neighbors: &[i64]) -> i64 {
+        let shortcuts = self.shortcuts(node).len() as i64;
+        let removed = (self.out[node].len() + self.inc[node].len()) as i64;
+        shortcuts - removed + contracted_neighbors[node]
+    }
+}
+
+/// A contraction hierarchy for one mode and one weight.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{ContractionHierarchy, Mode, Router, Weight};
+///
+/// let graph = osm4routing::read_graph("country.osm.pbf").unwrap();
+/// let router = Router::new(&graph, Mode::Car, Weight::TravelTime);
+/// let ch = ContractionHierarchy::new(&router);
+/// ch.save("country-car.ch").unwrap();
+///
+/// let ch = ContractionHierarchy::load("country-car.ch").unwrap();
+/// assert!(ch.is_built_from(&router));
+/// let seconds = ch.distance(0, 42);
+/// let from_depot = ch.one_to_many(0, &[42, 43, 44]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContractionHierarchy {
+    mode: Option<Mode>,
+    /// Name of the weight, see [`Weight::name`](super::routing::Weight::name).
+    weight: Option<&'static str>,
+    /// Number of edges of the graph.
+    edges_count: usize,
+    /// Hash of the cost of each arc of the graph, see [`fingerprint`].
+    fingerprint: u64,
+    /// Contraction order of each node: higher is more important.
+    rank: Vec<u32>,
+    /// Arcs towards more important nodes, stored at their tail.
+    up: ChAdjacency,
+    /// Arcs from more important nodes, stored at their head.
+    down: ChAdjacency,
+}
+
+impl ContractionHierarchy {
+    /// Contracts the graph of a router, using its mode and weight.
+    pub fn new(router: &Router) -> ContractionHierarchy {
+        let graph = router.graph();
+        let nodes_count = graph.nodes.len();
+        let mut out = vec![Vec::new(); nodes_count];
+        let mut inc = vec![Vec::new(); nodes_count];
+        for (node, out) in out.iter_mut().enumerate() {
+            for arc in graph.outgoing(node, router.mode()) {
+                let weight = router.cost(arc);
+                if arc.node != node && weight.is_finite() {
+                    insert_min(
+                        out,
+                        ChArc {
+                            node: arc.node as u32,
+                            weight,
+                            via: NONE,
+                            edge: arc.edge as u32,
+                            forward: arc.direction == Direction::Forward,
+                        },
+                    );
+                }
+            }
+        }
+        for (node, arcs) in out.iter().enumerate() {
+            for arc in arcs {
+                inc[arc.node as usize].push(ChArc {
+                    node: node as u32,
+                    ..*arc
+                });
+            }
+        }
+
+        let mut contraction = Contraction {
+            out,
+            inc,
+            witness: Witness {
+                costs: vec![f64::INFINITY; nodes_count],
+                touched: Vec::new(),
+            },
+        };
+        let mut contracted_neighbors = vec![0; nodes_count];
+        let mut queue: BinaryHeap<_> = (0..nodes_count)
+            .map(|node| Reverse((contraction.priority(node, &contracted_neighbors), node)))
+            .collect();
+        let mut rank = vec![0; nodes_count];
+        let mut up = vec![Vec::new(); nodes_count];
+        let mut down = vec![Vec::new(); nodes_count];
+        let mut order = 0;
+        while let Some(Reverse((_, node))) = queue.pop() {
+            // Lazy update: the priority may have grown since it was computed
+            let priority = contraction.priority(node, &contracted_neighbors);
+            if let Some(Reverse((next, _))) = queue.peek()
+                && priority > *next
+            {
+                queue.push(Reverse((priority, node)));
+                continue;
+            }
+
+            for (from, to, weight) in contraction.shortcuts(node) {
+                let shortcut = ChArc {
+                    node: to as u32,
+                    weight,
+                    via: node as u32,
+                    edge: NONE,
+                    forward: true,
+                };
+                insert_min(&mut contraction.out[from], shortcut);
+                insert_min(
+                    &mut contraction.inc[to],
+                    ChArc {
+                        node: from as u32,
+                        ..shortcut
+                    },
+                );
+            }
+            let outgoing = std::mem::take(&mut contraction.out[node]);
+            let incoming = std::mem::take(&mut contraction.inc[node]);
+            for arc in &outgoing {
+                contraction.inc[arc.node as usize].retain(|a| a.node as usize != node);
+                contracted_neighbors[arc.node as usize] += 1;
+            }
+            for arc in &incoming {
+                contraction.out[arc.node as usize].retain(|a| a.node as usize != node);
+                contracted_neighbors[arc.node as usize] += 1;
+            }
+            up[node] = outgoing;
+            down[node] = incoming;
+            rank[node] = order;
+            order += 1;
+        }
+
+        ContractionHierarchy {
+            mode: Some(router.mode()),
+            weight: Some(router.weight()),
+            edges_count: graph.edges.len(),
+            fingerprint: fingerprint(router),
+            rank,
+            up: ChAdjacency::new(up),
+            down: ChAdjacency::new(down),
+        }
+    }
+
+    /// The mode the hierarchy was built for.
+    pub fn mode(&self) -> Option<Mode> {
+        self.mode
+    }
+
+    /// The name of the weight the hierarchy was built for.
+    pub fn weight(&self) -> Option<&'static str> {
+        self.weight
+    }
+
+    /// Number of nodes of the hierarchy.
+    pub fn nodes_count(&self) -> usize {
+        self.rank.len()
+    }
+
+    /// Number of edges of the graph the hierarchy was built from.
+    pub fn edges_count(&self) -> usize {
+        self.edges_count
+    }
+
+    /// Returns true if the hierarchy was built from a graph of the size of the
+    /// graph of the router, for its mode and weight, with the same costs.
+    ///
+    /// The costs are compared with a fingerprint, so that a hierarchy built
+    /// for another [`Profile`](super::profile::Profile) or another custom
+    /// cost function is rejected too; this takes a pass over the graph.
+    /// A hierarchy loaded from a file should be checked before being used
+    /// with a router, as it refers to nodes and edges by their index.
+    pub fn is_built_from(&self, router: &Router) -> bool {
+        self.mode == Some(router.mode())
+            && self.weight == Some(router.weight())
+            && self.matches(router.graph())
+            && self.fingerprint == fingerprint(router)
+    }
+
+    /// Returns true if the graph has the number of nodes and edges of the hierarchy.
+    fn matches(&self, graph: &Graph) -> bool {
+        graph.nodes.len() == self.nodes_count() && graph.edges.len() == self.edges_count
+    }
+
+    /// Returns the cost of the shortest path between two nodes.
+    ///
+    /// Returns `None` if the target cannot be reached, or if a node is not
+    /// in the hierarchy.
+    pub fn distance(&self, source: usize, target: usize) -> Option<f64> {
+        self.query(source, target).map(|(cost, ..)| cost)
+    }
+
+    /// Returns the shortest path between two nodes of the graph the hierarchy was built from.
+    ///
+    /// Shortcuts are unpacked into the arcs of the graph.
+    /// Returns `None` if the target cannot be reached, if a node is not in the
+    /// hierarchy, or if the graph does not have the number of nodes and edges
+    /// of the hierarchy.
+    pub fn path(&self, graph: &Graph, source: usize, target: usize) -> Option<Path> {
+        if !self.matches(graph) {
+            return None;
+        }
+        let (cost, meeting, forward, backward) = self.query(source, target)?;
+        // Arcs of the hierarchy as (tail, head, arc)
+        let mut ch_arcs = Vec::new();
+        let mut node = meeting;
+        while let Some(&(previous, arc)) = forward.get(&node) {
+            ch_arcs.push((previous, node, arc));
+            node = previous;
+        }
+        ch_arcs.reverse();
+        let mut node = meeting;
+        while let Some(&(next, arc)) = backward.get(&node) {
+            ch_arcs.push((node, next, arc));
+            node = next;
+        }
+
+        let mut arcs = Vec::new();
+        for (tail, head, arc) in ch_arcs {
+            self.unpack(tail, head, arc, &mut arcs);
+        }
+        Some(Path {
+            cost,
+            geometry: graph.merge_geometry(source, &arcs),
+            arcs,
+        })
+    }
+
+    /// Returns the cost from a source to each target, `None` if unreachable
+    /// or not in the hierarchy.
+    pub fn one_to_many(&self, source: usize, targets: &[usize]) -> Vec<Option<f64>> {
+        self.many_to_many(&[source], targets).remove(0)
+    }
+
+    /// Returns the cost from each source to each target, `None` if unreachable
+    /// or not in the hierarchy.
+    ///
+    /// Uses buckets: one backward search per target, then one forward
+    /// search per source, run in parallel.
+    pub fn many_to_many(&self, sources: &[usize], targets: &[usize]) -> Vec<Vec<Option<f64>>> {
+        let buckets = self.buckets(targets);
+        sources
+            .par_iter()
+            .map(|&source| self.scan_buckets(source, &buckets, targets.len()))
+            .collect()
+    }
+
+    /// Runs a backward search from each target, storing `(target, cost)` at each reached node.
+    pub(crate) fn buckets(&self, targets: &[usize]) -> Buckets {
+        let mut buckets: Buckets = HashMap::new();
+        for (i, &target) in targets.iter().enumerate() {
+            if target >= self.nodes_count() {
+                continue;
+            }
+            for (node, (cost, _)) in self.upward(target, &self.down) {
+                buckets.entry(node).or_default().push((i, cost));
+            }
+        }
+        buckets
+    }
+
+    /// Runs a forward search from a source and combines it with the buckets of the targets.
+    pub(crate) fn scan_buckets(
+        &self,
+        source: usize,
+        buckets: &Buckets,
+        targets_count: usize,
+    ) -> Vec<Option<f64>> {
+        let mut costs = vec![f64::INFINITY; targets_count];
+        if source >= self.nodes_count() {
+            return vec![None; targets_count];
+        }
+        for (node, (cost, _)) in self.upward(source, &self.up) {
+            for &(target, to_target) in buckets.get(&node).into_iter().flatten() {
+                costs[target] = costs[target].min(cost + to_target);
+            }
+        }
+        costs
+            .into_iter()
+            .map(|cost| cost.is_finite().then_some(cost))
+            .collect()
+    }
+
+    /// Dijkstra on the arcs towards more important nodes.
+    ///
+    /// Returns the cost of every reached node and the arc used to reach it.
+    fn upward(
+        &self,
+        start: usize,
+        adjacency: &ChAdjacency,
+    ) -> HashMap<usize, (f64, Option<(usize, ChArc)>)> {
+        let mut reached = HashMap::new();
+        reached.insert(start, (0., None));
+        let mut queue = BinaryHeap::new();
+        queue.push(State {
+            key: 0.,
+            node: start,
+        });
+        while let Some(State { key, node }) = queue.pop() {
+            if key > reached[&node].0 {
+                continue;
+            }
+            for arc in adjacency.of(node) {
+                let next = arc.node as usize;
+                let cost = key + arc.weight;
+                if reached.get(&next).is_none_or(|&(c, _)| cost < c) {
+                    reached.insert(next, (cost, Some((node, *arc))));
+                    queue.push(State {
+                        key: cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+        reached
+    }
+
+    /// Bidirectional search.
+    ///
+    /// Returns the cost, the meeting node, and the arcs of both search trees
+    /// (by node, the previous node towards the source or the next node
+    /// towards the target).
+    fn query(&self, source: usize, target: usize) -> Option<(f64, usize, SearchTree, SearchTree)> {
+        if source >= self.nodes_count() || target >= self.nodes_count() {
+            return None;
+        }
+        let mut costs = [HashMap::new(), HashMap::new()];
+        let mut parents = [HashMap::new(), HashMap::new()];
+        let mut queues = [BinaryHeap::new(), BinaryHeap::new()];
+        for (side, start) in [source, target].into_iter().enumerate() {
+            costs[side].insert(start, 0.);
+            queues[side].push(State {
+                key: 0.,
+                node: start,
+            });
+        }
+        let mut best = f64::INFINITY;
+        let mut meeting = None;
+        loop {
+            let side = match (queues[0].peek(), queues[1].peek()) {
+                (Some(f), Some(b)) => usize::from(b.key < f.key),
+                (Some(_), None) => 0,
+                (None, Some(_)) => 1,
+                (None, None) => break,
+            };
+            let State { key, node } = queues[side].pop().unwrap();
+            if key >= best {
+                queues[side].clear();
+                continue;
+            }
+            if key > costs[side][&node] {
+                continue;
+            }
+            if let Some(other) = costs[1 - side].get(&node)
+                && key + other < best
+            {
+                best = key + other;
+                meeting = Some(node);
+            }
+            let adjacency = if side == 0 { &self.up } else { &self.down };
+            for arc in adjacency.of(node) {
+                let next = arc.node as usize;
+                let cost = key + arc.weight;
+                if costs[side].get(&next).is_none_or(|&c| cost < c) {
+                    costs[side].insert(next, cost);
+                    parents[side].insert(next, (node, *arc));
+                    queues[side].push(State {
+                        key: cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+        let [forward, backward] = parents;
+        meeting.map(|meeting| (best, meeting, forward, backward))
+    }
+
+    /// Returns the arc of the hierarchy from `tail` to `head`.
+    fn find(&self, tail: usize, head: usize) -> ChArc {
+        let (arcs, other) = if self.rank[tail] < self.rank[head] {
+            (self.up.of(tail), head)
+        } else {
+            (self.down.of(head), tail)
+        };
+        *arcs
+            .iter()
+            .filter(|arc| arc.node as usize == other)
+            .min_by(|a, b| a.weight.total_cmp(&b.weight))
+            .expect("shortcuts bypass existing arcs")
+    }
+
+    /// Appends the original arcs of an arc of the hierarchy.
+    fn unpack(&self, tail: usize, head: usize, arc: ChArc, arcs: &mut Vec<Arc>) {
+        let mut stack = vec![(tail, head, arc)];
+        while let Some((tail, head, arc)) = stack.pop() {
+            if arc.via == NONE {
+                arcs.push(Arc {
+                    edge: arc.edge as usize,
+                    direction: if arc.forward {
+                        Direction::Forward
+                    } else {
+                        Direction::Backward
+                    },
+                    node: head,
+                });
+            } else {
+                let via = arc.via as usize;
+                stack.push((via, head, self.find(via, head)));
+                stack.push((tail, via, self.find(tail, via)));
+            }
+        }
+    }
+
+    /// Saves the hierarchy to a binary file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save<P: AsRef<FilePath>>(&self, path: P) -> Result<(), Error> {
+        let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+        w.write_all(MAGIC)?;
+        let mode = self
+            .mode
+            .and_then(|mode| Mode::ALL.iter().position(|m| *m == mode))
+            .map_or(u8::MAX, |i| i as u8);
+        w.write_all(&[mode])?;
+        let weight = self
+            .weight
+            .and_then(|weight| WEIGHTS.iter().position(|w| *w == weight))
+            .map_or(u8::MAX, |i| i as u8);
+        w.write_all(&[weight])?;
+        w.write_all(&(self.edges_count as u64).to_le_bytes())?;
+        w.write_all(&self.fingerprint.to_le_bytes())?;
+        write_values(&mut w, &self.rank, |v| v.to_le_bytes())?;
+        for adjacency in [&self.up, &self.down] {
+            write_values(&mut w, &adjacency.offsets, |v| v.to_le_bytes())?;
+            write_values(&mut w, &adjacency.arcs, |arc| {
+                let mut bytes = [0; 21];
+                bytes[..4].copy_from_slice(&arc.node.to_le_bytes());
+                bytes[4..12].copy_from_slice(&arc.weight.to_le_bytes());
+                bytes[12..16].copy_from_slice(&arc.via.to_le_bytes());
+                bytes[16..20].copy_from_slice(&arc.edge.to_le_bytes());
+                bytes[20] = arc.forward as u8;
+                bytes
+            })?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Loads a hierarchy saved with [`ContractionHierarchy::save`].
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a hierarchy.
+    pub fn load<P: AsRef<FilePath>>(path: P) -> Result<ContractionHierarchy, Error> {
+        let mut r = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an osm4routing contraction hierarchy"));
+        }
+        let mut mode_and_weight = [0; 2];
+        r.read_exact(&mut mode_and_weight)?;
+        let mut edges_count = [0; 8];
+        r.read_exact(&mut edges_count)?;
+        let mut fingerprint = [0; 8];
+        r.read_exact(&mut fingerprint)?;
+        let mut ch = ContractionHierarchy {
+            mode: Mode::ALL.get(mode_and_weight[0] as usize).copied(),
+            weight: WEIGHTS.get(mode_and_weight[1] as usize).copied(),
+            edges_count: u64::from_le_bytes(edges_count) as usize,
+            fingerprint: u64::from_le_bytes(fingerprint),
+            rank: read_values(&mut r, u32::from_le_bytes)?,
+            ..Default::default()
+        };
+        for adjacency in [&mut ch.up, &mut ch.down] {
+            adjacency.offsets = read_values(&mut r, u32::from_le_bytes)?;
+            adjacency.arcs = read_values(&mut r, |b: [u8; 21]| ChArc {
+                node: u32::from_le_bytes(b[..4].try_into().unwrap()),
+                weight: f64::from_le_bytes(b[4..12].try_into().unwrap()),
+                via: u32::from_le_bytes(b[12..16].try_into().unwrap()),
+                edge: u32::from_le_bytes(b[16..20].try_into().unwrap()),
+                forward: b[20] != 0,
+            })?;
+        }
+        let nodes = ch.rank.len();
+        let consistent = [&ch.up, &ch.down].iter().all(|a| {
+            a.offsets.len() == nodes + 1
+                && a.offsets.windows(2).all(|w| w[0] <= w[1])
+                && a.offsets.last().map(|&o| o as usize) == Some(a.arcs.len())
+                && a.arcs.iter().all(|arc| {
+                    (arc.node as usize) < nodes
+                        && if arc.via == NONE {
+                            (arc.edge as usize) < ch.edges_count
+                        } else {
+                            (arc.via as usize) < nodes
+                        }
+                })
+        });
+        if !consistent {
+            return Err(invalid_data("inconsistent contraction hierarchy"));
+        }
+        Ok(ch)
+    }
+}
+
+/// Hash of the ends, the edge and the cost of each arc of the graph of a router.
+fn fingerprint(router: &Router) -> u64 {
+    let graph = router.graph();
+    fnv1a((0..graph.nodes.len()).flat_map(|node| {
+        graph
+            .outgoing(node, router.mode())
+            .iter()
+            .flat_map(move |arc| {
+                [
+                    node as i64,
+                    arc.node as i64,
+                    arc.edge as i64,
+                    (arc.direction == Direction::Forward) as i64,
+                    router.cost(arc).to_bits() as i64,
+                ]
+            })
+    }))
+}
+
+#[cfg(test)]
+fn grid() -> Graph {
+    use super::categorize::EdgeProperties;
+    use super::models::*;
+
+    // 5×5 grid of streets, with one-way streets on odd rows
+    let mut street = EdgeProperties::default();
+    street.update_with_str("highway", "residential");
+    street.normalize();
+    let mut oneway = EdgeProperties::default();
+    oneway.update_with_str("highway", "residential");
+    oneway.update_with_str("oneway", "yes");
+    oneway.normalize();
+
+    let coord = |i: i64| geo_types::Coord {
+        x: (i % 5) as f64 * 0.001,
+        y: (i / 5) as f64 * 0.001 + (i % 3) as f64 * 0.0001,
+    };
+    let nodes = (0..25)
+        .map(|i| Node {
+            id: NodeId(i),
+            coord: coord(i),
+            ..Default::default()
+        })
+        .collect();
+    let mut edges = Vec::new();
+    for i in 0..25 {
+        let mut add = |j: i64, properties| {
+            edges.push(Edge {
+                composite_id: format!("{i}-{j}"),
+                source: NodeId(i),
+                target: NodeId(j),
+                geometry: vec![coord(i), coord(j)],
+                properties,
+                ..Default::default()
+            })
+        };
+        if i % 5 < 4 {
+            add(i + 1, if (i / 5) % 2 == 1 { oneway } else { street });
+        }
+        if i < 20 {
+            add(i + 5, street);
+        }
+    }
+    Graph::new(nodes, edges)
+}
+
+#[test]
+fn test_queries() {
+    use super::routing::Weight;
+
+    let graph = grid();
+    let router = Router::new(&graph, Mode::Car, Weight::Length);
+    let ch = ContractionHierarchy::new(&router);
+    let targets: Vec<usize> = (0..25).collect();
+    for source in 0..25 {
+        let many = ch.one_to_many(source, &targets);
+        for (target, cost) in many.into_iter().enumerate() {
+            let expected = router.dijkstra(source, target).unwrap();
+            let distance = ch.distance(source, target).unwrap();
+            assert!((expected.cost - distance).abs() < 1e-6);
+            assert!((expected.cost - cost.unwrap()).abs() < 1e-6);
+
+            let path = ch.path(&graph, source, target).unwrap();
+            let cost: f64 = path.arcs.iter().map(|arc| router.cost(arc)).sum();
+            assert!((expected.cost - cost).abs() < 1e-6);
+            assert_eq!(
+                Some(target),
+                path.arcs.last().map(|arc| arc.node).or(Some(source))
+            );
+        }
+    }
+}
+
+#[test]
+fn test_save_load() {
+    use super::routing::Weight;
+
+    let graph = grid();
+    let ch = ContractionHierarchy::new(&Router::new(&graph, Mode::Car, Weight::TravelTime));
+    let path = std::env::temp_dir().join(format!("osm4routing_test_{}.ch", std::process::id()));
+    ch.save(&path).unwrap();
+    let loaded = ContractionHierarchy::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(ch, loaded);
+    assert_eq!(Some(Mode::Car), loaded.mode());
+    assert_eq!(Some("travel_time"), loaded.weight());
+    assert_eq!(graph.edges.len(), loaded.edges_count());
+    assert!(loaded.is_built_from(&Router::new(&graph, Mode::Car, Weight::TravelTime)));
+    assert!(!loaded.is_built_from(&Router::new(&graph, Mode::Car, Weight::Length)));
+    assert!(!loaded.is_built_from(&Router::new(&graph, Mode::Bike, Weight::TravelTime)));
+
+    // Another cost function with the same name is detected
+    let custom = |factor: f64| Weight::custom(move |edge, _| Some(edge.length() * factor));
+    let ch = ContractionHierarchy::new(&Router::new(&graph, Mode::Car, custom(1.)));
+    assert!(ch.is_built_from(&Router::new(&graph, Mode::Car, custom(1.))));
+    assert!(!ch.is_built_from(&Router::new(&graph, Mode::Car, custom(2.))));
+
+    // A path cannot be unpacked in another graph
+    let other = super::routing::test_graph();
+    assert!(loaded.path(&graph, 0, 24).is_some());
+    assert!(loaded.path(&other, 0, 24).is_none());
+
+    // Nodes out of the hierarchy are unreachable
+    assert_eq!(None, loaded.distance(0, 25));
+    assert!(loaded.path(&graph, 25, 0).is_none());
+    assert_eq!(vec![Some(0.), None], loaded.one_to_many(0, &[0, 25]));
+    assert_eq!(vec![None], loaded.one_to_many(25, &[0]));
+}
@@ -0,0 +1,278 @@
+//! Connected components of the graph.
+//!
+//! Extracts often contain fragments that cannot be reached from the rest of
+//! the network: parking lots, mis-tagged ways, ways clipped at the border of
+//! the extract. [`Components`] finds them, per mode or over every mode, so
+//! they can be reported or pruned with [`Reader::keep_largest_component`](super::reader::Reader::keep_largest_component).
+
+use super::categorize::Mode;
+use super::graph::Graph;
+
+/// How nodes must be linked to be in the same component.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Connectivity {
+    /// Linked by edges, whatever their direction.
+    #[default]
+    Weak,
+    /// Each node can reach the other one, following the directions of the edges.
+    Strong,
+}
+
+impl std::str::FromStr for Connectivity {
+    type Err = String;
+
+    /// Parses `weak` or `strong`.
+    fn from_str(s: &str) -> Result<Connectivity, String> {
+        match s {
+            "weak" => Ok(Connectivity::Weak),
+            "strong" => Ok(Connectivity::Strong),
+            _ => Err(format!("unknown connectivity {s}, expected weak or strong")),
+        }
+    }
+}
+
+/// Connected components of a graph.
+///
+/// Components are numbered by decreasing number of nodes: the largest one is `0`.
+/// Nodes and edges that cannot be used by the mode have no component.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{Components, Connectivity, Mode};
+///
+/// let graph = osm4routing::read_graph("map.osm.pbf").unwrap();
+/// let components = Components::new(&graph, Some(Mode::Car), Connectivity::Strong);
+/// for c in 1..components.count() {
+///     println!("Island of {} nodes, {} m", components.nodes_count(c), components.length(c));
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Components {
+    nodes: Vec<Option<usize>>,
+    edges: Vec<Option<usize>>,
+    nodes_counts: Vec<usize>,
+    lengths: Vec<f64>,
+}
+
+impl Components {
+    /// Computes the components for a mode, or for every mode with `None`.
+    pub fn new(graph: &Graph, mode: Option<Mode>, connectivity: Connectivity) -> Components {
+        let modes = match mode {
+            Some(mode) => vec![mode],
+            None => Mode::ALL.to_vec(),
+        };
+        let successors = |node: usize| {
+            modes
+                .iter()
+                .flat_map(move |mode| graph.outgoing(node, *mode))
+                .map(|arc| arc.node)
+        };
+        let mut usable_nodes = vec![false; graph.nodes.len()];
+        let mut usable_edges = vec![false; graph.edges.len()];
+        for node in 0..graph.nodes.len() {
+            for mode in &modes {
+                for arc in graph.outgoing(node, *mode) {
+                    usable_nodes[node] = true;
+                    usable_nodes[arc.node] = true;
+                    usable_edges[arc.edge] = true;
+                }
+            }
+        }
+
+        let raw = match connectivity {
+            Connectivity::Weak => weak(graph.nodes.len(), &usable_nodes, successors),
+            Connectivity::Strong => strong(graph.nodes.len(), &usable_nodes, successors),
+        };
+
+        // Number the components by decreasing size
+        let raw_count = raw.iter().flatten().map(|c| c + 1).max().unwrap_or(0);
+        let mut raw_sizes = vec![0; raw_count];
+        for c in raw.iter().flatten() {
+            raw_sizes[*c] += 1;
+        }
+        let mut order: Vec<usize> = (0..raw_count).collect();
+        order.sort_by_key(|&c| std::cmp::Reverse(raw_sizes[c]));
+        let mut renumber = vec![0; raw_count];
+        for (id, c) in order.iter().enumerate() {
+            renumber[*c] = id;
+        }
+        let nodes: Vec<Option<usize>> = raw.iter().map(|c| c.map(|c| renumber[c])).collect();
+        let nodes_counts = order.iter().map(|&c| raw_sizes[c]).collect();
+
+        let mut lengths = vec![0.; raw_count];
+        let edges = (0..graph.edges.len())
+            .map(|edge| {
+                let (source, target) = graph.ends(edge)?;
+                let component = nodes[source]?;
+                let inside = match connectivity {
+                    Connectivity::Weak => true,
+                    Connectivity::Strong => nodes[target] == Some(component),
+                };
+                (usable_edges[edge] && inside).then(|| {
+                    lengths[component] += graph.edges[edge].length();
+                    component
+                })
+            })
+            .collect();
+
+        Components {
+            nodes,
+            edges,
+            nodes_counts,
+            lengths,
+        }
+    }
+
+    /// Number of components.
+    pub fn count(&self) -> usize {
+        self.nodes_counts.len()
+    }
+
+    /// Component of a node, given by its index in [`Graph::nodes`].
+    pub fn node(&self, node: usize) -> Option<usize> {
+        self.nodes[node]
+    }
+
+    /// Component of an edge, given by its index in [`Graph::edges`].
+    ///
+    /// With strong connectivity, an edge between two components has none.
+    pub fn edge(&self, edge: usize) -> Option<usize> {
+        self.edges[edge]
+    }
+
+    /// Number of nodes of a component.
+    pub fn nodes_count(&self, component: usize) -> usize {
+        self.nodes_counts[component]
+    }
+
+    /// Total length of the edges of a component, in meters.
+    pub fn length(&self, component: usize) -> f64 {
+        self.lengths[component]
+    }
+}
+
+/// Union-find over the arcs.
+fn weak<I: Iterator<Item = usize>>(
+    nodes_count: usize,
+    usable: &[bool],
+    successors: impl Fn(usize) -> I,
+) -> Vec<Option<usize>> {
+    fn find(parents: &mut [usize], mut node: usize) -> usize {
+        while parents[node] != node {
+            parents[node] = parents[parents[node]];
+            node = parents[node];
+        }
+        node
+    }
+    let mut parents: Vec<usize> = (0..nodes_count).collect();
+    for node in 0..nodes_count {
+        for next in successors(node) {
+            let (a, b) = (find(&mut parents, node), find(&mut parents, next));
+            if a != b {
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+    }
+    let mut ids = vec![None; nodes_count];
+    let mut count = 0;
+    (0..nodes_count)
+        .map(|node| {
+            if !usable[node] {
+                return None;
+            }
+            let root = find(&mut parents, node);
+            Some(*ids[root].get_or_insert_with(|| {
+                count += 1;
+                count - 1
+            }))
+        })
+        .collect()
+}
+
+/// Tarjan's algorithm, with an explicit stack.
+fn strong<I: Iterator<Item = usize>>(
+    nodes_count: usize,
+    usable: &[bool],
+    successors: impl Fn(usize) -> I,
+) -> Vec<Option<usize>> {
+    let mut index = vec![usize::MAX; nodes_count];
+    let mut low = vec![0; nodes_count];
+    let mut on_stack = vec![false; nodes_count];
+    let mut stack = Vec::new();
+    let mut components = vec![None; nodes_count];
+    let mut count = 0;
+    let mut next_index = 0;
+    for root in (0..nodes_count).filter(|&n| usable[n]) {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        let mut calls = vec![(root, successors(root).collect::<Vec<_>>(), 0)];
+        index[root] = next_index;
+        low[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some((node, nexts, position)) = calls.last_mut() {
+            let node = *node;
+            if let Some(&next) = nexts.get(*position) {
+                *position += 1;
+                if index[next] == usize::MAX {
+                    index[next] = next_index;
+                    low[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    calls.push((next, successors(next).collect(), 0));
+                } else if on_stack[next] {
+                    low[node] = low[node].min(index[next]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some((parent, ..)) = calls.last() {
+                low[*parent] = low[*parent].min(low[node]);
+            }
+            if low[node] == index[node] {
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    components[member] = Some(count);
+                    if member == node {
+                        break;
+                    }
+                }
+                count += 1;
+            }
+        }
+    }
+    components
+}
+
+#[test]
+fn test_components() {
+    use super::routing::test_graph;
+
+    // 1 - 2 → 3 for cars, 4 only reached by footways
+    let graph = test_graph();
+    let weak = Components::new(&graph, Some(Mode::Car), Connectivity::Weak);
+    assert_eq!(1, weak.count());
+    assert_eq!(
+        vec![Some(0), Some(0), Some(0), None],
+        (0..4).map(|n| weak.node(n)).collect::<Vec<_>>()
+    );
+    assert_eq!(Some(0), weak.edge(1));
+    assert_eq!(None, weak.edge(2));
+    assert!((weak.length(0) - 2_223.9).abs() < 1.);
+
+    let strong = Components::new(&graph, Some(Mode::Car), Connectivity::Strong);
+    assert_eq!(2, strong.count());
+    assert_eq!(2, strong.nodes_count(0));
+    assert_eq!(Some(0), strong.node(0));
+    assert_eq!(Some(1), strong.node(2));
+    assert_eq!(Some(0), strong.edge(0));
+    assert_eq!(None, strong.edge(1));
+
+    let all = Components::new(&graph, None, Connectivity::Strong);
+    assert_eq!(1, all.count());
+    assert_eq!(4, all.nodes_count(0));
+}
@@ -0,0 +1,605 @@
+//! Conditional restrictions (`*:conditional` tags).
+//!
+//! This module parses time-limited restrictions such as
+//! `access:conditional=no @ (Mo-Fr 07:00-09:00)` into structured
+//! restrictions that can be evaluated at a given local time.
+//!
+//! Only time conditions are supported, using a subset of the
+//! `opening_hours` syntax: months (`Nov-Mar`), weekdays (`Mo-Fr`, `Sa,Su`)
+//! and time ranges (`07:00-09:00,16:00-18:00`), as well as `24/7`.
+//! Restrictions with other conditions (e.g. `weight>7.5`, `wet`) are ignored.
+//!
+//! https://wiki.openstreetmap.org/wiki/Conditional_restrictions
+
+use super::categorize::*;
+
+/// A local date and time at which conditional restrictions are evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalTime {
+    /// Month, from 1 (January) to 12 (December).
+    month: u8,
+    /// Day of the week, from 0 (Monday) to 6 (Sunday).
+    weekday: u8,
+    /// Minutes since midnight.
+    minute: u16,
+}
+
+impl LocalTime {
+    /// Creates a local time from its components.
+    ///
+    /// Returns `None` if a component is out of range.
+    ///
+    /// # Arguments
+    /// * `month` - From 1 (January) to 12 (December).
+    /// * `weekday` - From 0 (Monday) to 6 (Sunday).
+    /// * `hour` - From 0 to 23.
+    /// * `minute` - From 0 to 59.
+    pub fn new(month: u8, weekday: u8, hour: u8, minute: u8) -> Option<LocalTime> {
+        ((1..=12).contains(&month) && weekday < 7 && hour < 24 && minute < 60).then_some(
+            LocalTime {
+                month,
+                weekday,
+                minute: hour as u16 * 60 + minute as u16,
+            },
+        )
+    }
+
+    /// Creates a local time from a Unix timestamp.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Seconds since 1970-01-01T00:00:00Z.
+    /// * `utc_offset` - Offset of the local time zone in seconds (e.g. 3600 for UTC+1).
+    pub fn from_unix(timestamp: i64, utc_offset: i64) -> LocalTime {
+        let local = timestamp + utc_offset;
+        let days = local.div_euclid(86_400);
+        let seconds = local.rem_euclid(86_400);
+        // 1970-01-01 was a Thursday
+        let weekday = (days + 3).rem_euclid(7) as u8;
+
+        // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719_468;
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+
+        LocalTime {
+            month,
+            weekday,
+            minute: (seconds / 60) as u16,
+        }
+    }
+}
+
+/// One rule of a time condition, e.g. `Nov-Mar Mo-Fr 07:00-09:00`.
+///
+/// Each missing part matches any value.
+#[derive(Clone, Debug, PartialEq)]
+struct TimeRule {
+    /// Bitmask of months, bit 0 being January.
+    months: Option<u16>,
+    /// Bitmask of weekdays, bit 0 being Monday.
+    weekdays: Option<u8>,
+    /// Time ranges in minutes since midnight, the end can be before the start.
+    times: Vec<(u16, u16)>,
+}
+
+impl TimeRule {
+    fn matches(&self, time: LocalTime) -> bool {
+        self.months
+            .is_none_or(|months| months & (1 << (time.month - 1)) != 0)
+            && self
+                .weekdays
+                .is_none_or(|weekdays| weekdays & (1 << time.weekday) != 0)
+            && (self.times.is_empty()
+                || self.times.iter().any(|&(start, end)| {
+                    if start <= end {
+                        start <= time.minute && time.minute < end
+                    } else {
+                        start <= time.minute || time.minute < end
+                    }
+                }))
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+/// Parses a comma separated list of names and ranges (e.g. `Mo-We,Fr`) into a bitmask.
+///
+/// Ranges can wrap around (e.g. `Nov-Feb`).
+fn parse_bitmask(token: &str, names: &[&str]) -> Option<u16> {
+    let index = |name: &str| names.iter().position(|n| *n == name);
+    let mut mask = 0;
+    for part in token.split(',') {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (index(start)?, index(end)?);
+                let mut i = start;
+                loop {
+                    mask |= 1 << i;
+                    if i == end {
+                        break;
+                    }
+                    i = (i + 1) % names.len();
+                }
+            }
+            None => mask |= 1 << index(part)?,
+        }
+    }
+    Some(mask)
+}
+
+/// Parses `HH:MM` into minutes since midnight.
+fn parse_minutes(val: &str) -> Option<u16> {
+    let (hours, minutes) = val.split_once(':')?;
+    let hours: u16 = hours.parse().ok()?;
+    let minutes: u16 = minutes.parse().ok()?;
+    // 24:00 is the end of the day, the only valid time of the 24th hour
+    ((hours < 24 && minutes < 60) || (hours == 24 && minutes == 0)).then_some(hours * 60 + minutes)
+}
+
+/// Parses a comma separated list of time ranges (e.g. `07:00-09:00,16:00-18:00`).
+fn parse_times(token: &str) -> Option<Vec<(u16, u16)>> {
+    token
+        .split(',')
+        .map(|range| {
+            let (start, end) = range.split_once('-')?;
+            Some((parse_minutes(start)?, parse_minutes(end)?))
+        })
+        .collect()
+}
+
+/// Parses a single rule such as `Mo-Fr 07:00-09:00`.
+fn parse_rule(rule: &str) -> Option<TimeRule> {
+    let mut result = TimeRule {
+        months: None,
+        weekdays: None,
+        times: vec![],
+    };
+    for token in rule.split_whitespace() {
+        if token == "24/7" {
+            continue;
+        }
+        // Public and school holidays cannot be evaluated, they are ignored
+        let token = token
+            .split(',')
+            .filter(|part| *part != "PH" && *part != "SH")
+            .collect::<Vec<_>>()
+            .join(",");
+        if token.is_empty() {
+            continue;
+        }
+        if token.contains(':') {
+            result.times.extend(parse_times(&token)?);
+        } else if let Some(weekdays) = parse_bitmask(&token, &WEEKDAYS) {
+            result.weekdays = Some(weekdays as u8);
+        } else {
+            result.months = Some(parse_bitmask(&token, &MONTHS)?);
+        }
+    }
+    Some(result)
+}
+
+/// A time condition, active when any of its rules matches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeCondition {
+    rules: Vec<TimeRule>,
+}
+
+impl TimeCondition {
+    /// Parses a condition using a subset of the `opening_hours` syntax.
+    ///
+    /// Returns `None` if the condition is not a time condition or uses
+    /// unsupported syntax.
+    ///
+    /// # Example
+    /// ```
+    /// use osm4routing::{LocalTime, TimeCondition};
+    ///
+    /// let condition = TimeCondition::parse("Mo-Fr 07:00-09:00").unwrap();
+    /// assert!(condition.matches(LocalTime::new(5, 0, 8, 30).unwrap()));
+    /// assert!(!condition.matches(LocalTime::new(5, 5, 8, 30).unwrap()));
+    /// ```
+    pub fn parse(condition: &str) -> Option<TimeCondition> {
+        let condition = condition.trim();
+        let condition = condition
+            .strip_prefix('(')
+            .and_then(|c| c.strip_suffix(')'))
+            .unwrap_or(condition);
+        let rules = condition
+            .split(';')
+            .flat_map(|rule| rule.split(", "))
+            .filter(|rule| !rule.trim().is_empty())
+            .map(parse_rule)
+            .collect::<Option<Vec<_>>>()?;
+        (!rules.is_empty()).then_some(TimeCondition { rules })
+    }
+
+    /// Returns true if the condition is active at the given time.
+    pub fn matches(&self, time: LocalTime) -> bool {
+        self.rules.iter().any(|rule| rule.matches(time))
+    }
+}
+
+/// A restriction on one mode, active only when its condition matches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConditionalRestriction {
+    /// The restricted transportation mode.
+    pub mode: Mode,
+    /// The restricted direction, `None` meaning both directions.
+    pub direction: Option<Direction>,
+    /// Whether the mode is allowed or forbidden while the condition is active.
+    pub allowed: bool,
+    /// When the restriction applies.
+    pub condition: TimeCondition,
+    /// How specific the key of the tag is for the mode, from 0 for `access`
+    /// to 3 for the mode itself or `oneway`.
+    pub specificity: u8,
+}
+
+/// How specific an access key is for a mode, from 0 for `access` to 3 for the mode itself.
+///
+/// `access` < `vehicle` < `motor_vehicle` < `motorcar`, `bicycle` and `foot`
+/// (or `pedestrian`). Returns `None` if the key does not restrict the mode.
+pub fn specificity(key: &str, mode: Mode) -> Option<u8> {
+    match (key, mode) {
+        ("access", Mode::Car | Mode::Bike | Mode::Foot) => Some(0),
+        ("vehicle", Mode::Car | Mode::Bike) => Some(1),
+        ("motor_vehicle", Mode::Car) => Some(2),
+        ("motorcar", Mode::Car) | ("bicycle", Mode::Bike) | ("foot" | "pedestrian", Mode::Foot) => {
+            Some(3)
+        }
+        _ => None,
+    }
+}
+
+/// Reads the value of an access tag.
+///
+/// `destination` ways stay open, as the trips to the places they serve use
+/// them; ways only open to `delivery`, `customers` or other groups count as
+/// closed, like `private` ones. Returns `None` for unknown values.
+fn access_value(value: &str) -> Option<bool> {
+    match value {
+        "yes" | "permissive" | "designated" | "official" | "destination" => Some(true),
+        "no" | "private" | "delivery" | "customers" | "permit" | "agricultural" | "forestry" => {
+            Some(false)
+        }
+        _ => None,
+    }
+}
+
+impl ConditionalRestriction {
+    /// Parses a `*:conditional` tag into restrictions.
+    ///
+    /// Recognizes `access`, `vehicle`, `motor_vehicle`, `motorcar`, `bicycle`,
+    /// `foot` and `oneway`. Returns an empty list for other keys, and ignores
+    /// values whose condition is not a supported time condition or whose
+    /// access value is unknown. Restrictions of several tags must be sorted
+    /// with [`ConditionalRestriction::prioritize`] before being applied.
+    ///
+    /// # Arguments
+    /// * `key` - The OSM tag key, e.g. `access:conditional`.
+    /// * `val` - The OSM tag value, e.g. `no @ (Mo-Fr 07:00-09:00)`.
+    pub fn parse(key: &str, val: &str) -> Vec<ConditionalRestriction> {
+        let Some(key) = key.strip_suffix(":conditional") else {
+            return vec![];
+        };
+        let mut result = Vec::new();
+        for part in split_top_level(val) {
+            let Some((value, condition)) = part.split_once('@') else {
+                continue;
+            };
+            let Some(condition) = TimeCondition::parse(condition) else {
+                continue;
+            };
+            let value = value.trim();
+            let mut push = |mode, direction, allowed| {
+                let specificity = match key {
+                    "oneway" => Some(3),
+                    _ => specificity(key, mode),
+                };
+                if let Some(specificity) = specificity {
+                    result.push(ConditionalRestriction {
+                        mode,
+                        direction,
+                        allowed,
+                        condition: condition.clone(),
+                        specificity,
+                    })
+                }
+            };
+            match key {
+                "access" | "vehicle" | "motor_vehicle" | "motorcar" | "bicycle" | "foot" => {
+                    if let Some(allowed) = access_value(value) {
+                        for mode in [Mode::Foot, Mode::Car, Mode::Bike] {
+                            push(mode, None, allowed);
+                        }
+                    }
+                }
+                "oneway" => match value {
+                    "yes" | "true" | "1" => {
+                        push(Mode::Car, Some(Direction::Backward), false);
+                        push(Mode::Bike, Some(Direction::Backward), false);
+                    }
+                    "-1" => {
+                        push(Mode::Car, Some(Direction::Forward), false);
+                        push(Mode::Bike, Some(Direction::Forward), false);
+                    }
+                    "no" => {
+                        push(Mode::Car, None, true);
+                        push(Mode::Bike, None, true);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Sorts restrictions from the most generic key to the most specific one,
+    /// and drops the ones overridden by a more specific plain tag.
+    ///
+    /// Applied in this order, `bicycle:conditional` wins over
+    /// `vehicle:conditional`, and `access:conditional=no @ …` does not close
+    /// a way to bicycles if it is tagged `bicycle=yes`.
+    ///
+    /// # Arguments
+    /// * `restrictions` - The restrictions of a way.
+    /// * `keys` - The keys of the plain tags of the way.
+    pub fn prioritize<'a>(
+        restrictions: &mut Vec<ConditionalRestriction>,
+        keys: impl IntoIterator<Item = &'a str>,
+    ) {
+        let keys: Vec<&str> = keys.into_iter().collect();
+        restrictions.retain(|restriction| {
+            keys.iter()
+                .filter_map(|key| specificity(key, restriction.mode))
+                .all(|plain| plain <= restriction.specificity)
+        });
+        restrictions.sort_by_key(|restriction| restriction.specificity);
+    }
+
+    /// Returns true if the restriction applies at the given time.
+    pub fn is_active(&self, time: LocalTime) -> bool {
+        self.condition.matches(time)
+    }
+
+    /// Applies the restriction to the properties, regardless of its condition.
+    ///
+    /// When a mode becomes allowed in a direction where it was forbidden,
+    /// it takes the accessibility of the other direction (or a generic one).
+    pub fn apply(&self, properties: &mut EdgeProperties) {
+        let directions = match self.direction {
+            Some(direction) => vec![direction],
+            None => vec![Direction::Forward, Direction::Backward],
+        };
+        for direction in directions {
+            match (self.mode, direction, self.allowed) {
+                (Mode::Foot, _, false) => properties.foot = FootAccessibility::Forbidden,
+                (Mode::Foot, _, true) => properties.foot = FootAccessibility::Allowed,
+                (Mode::Car, Direction::Forward, false) => {
+                    properties.car_forward = CarAccessibility::Forbidden
+                }
+                (Mode::Car, Direction::Backward, false) => {
+                    properties.car_backward = CarAccessibility::Forbidden
+                }
+                (Mode::Car, Direction::Forward, true) => {
+                    properties.car_forward =
+                        allowed_car(properties.car_forward, properties.car_backward)
+                }
+                (Mode::Car, Direction::Backward, true) => {
+                    properties.car_backward =
+                        allowed_car(properties.car_backward, properties.car_forward)
+                }
+                (Mode::Bike, Direction::Forward, false) => {
+                    properties.bike_forward = BikeAccessibility::Forbidden
+                }
+                (Mode::Bike, Direction::Backward, false) => {
+                    properties.bike_backward = BikeAccessibility::Forbidden
+                }
+                (Mode::Bike, Direction::Forward, true) => {
+                    properties.bike_forward = allowed_bike(properties.bike_forward)
+                }
+                (Mode::Bike, Direction::Backward, true) => {
+                    properties.bike_backward = allowed_bike(properties.bike_backward)
+                }
+                (Mode::Train, _, allowed) => {
+                    properties.train = if allowed {
+                        TrainAccessibility::Allowed
+                    } else {
+                        TrainAccessibility::Forbidden
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn allowed_car(current: CarAccessibility, other: CarAccessibility) -> CarAccessibility {
+    match (current, other) {
+        (CarAccessibility::Forbidden | CarAccessibility::Unknown, CarAccessibility::Forbidden)
+        | (CarAccessibility::Forbidden | CarAccessibility::Unknown, CarAccessibility::Unknown) => {
+            CarAccessibility::Residential
+        }
+        (CarAccessibility::Forbidden | CarAccessibility::Unknown, other) => other,
+        (current, _) => current,
+    }
+}
+
+fn allowed_bike(current: BikeAccessibility) -> BikeAccessibility {
+    match current {
+        BikeAccessibility::Forbidden | BikeAccessibility::Unknown => BikeAccessibility::Allowed,
+        current => current,
+    }
+}
+
+/// Splits a conditional value on `;`, ignoring the ones inside parentheses.
+fn split_top_level(val: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in val.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ';' if depth == 0 => {
+                result.push(&val[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(&val[start..]);
+    result
+}
+
+#[test]
+fn test_from_unix() {
+    // 2024-03-15T08:30:00Z was a Friday
+    let time = LocalTime::from_unix(1_710_491_400, 0);
+    assert_eq!(LocalTime::new(3, 4, 8, 30).unwrap(), time);
+
+    // One hour later in UTC+1
+    let time = LocalTime::from_unix(1_710_491_400, 3600);
+    assert_eq!(LocalTime::new(3, 4, 9, 30).unwrap(), time);
+
+    // 1969-12-31T23:00:00Z was a Wednesday
+    assert_eq!(
+        LocalTime::new(12, 2, 23, 0).unwrap(),
+        LocalTime::from_unix(-3600, 0)
+    );
+}
+
+#[test]
+fn test_local_time() {
+    assert!(LocalTime::new(12, 6, 23, 59).is_some());
+    assert!(LocalTime::new(0, 0, 0, 0).is_none());
+    assert!(LocalTime::new(13, 0, 0, 0).is_none());
+    assert!(LocalTime::new(1, 7, 0, 0).is_none());
+    assert!(LocalTime::new(1, 0, 24, 0).is_none());
+    assert!(LocalTime::new(1, 0, 0, 60).is_none());
+}
+
+#[test]
+fn test_time_condition() {
+    let c = TimeCondition::parse("(Mo-Fr 07:00-09:00,16:00-18:00; Sa 10:00-12:00)").unwrap();
+    assert!(c.matches(LocalTime::new(1, 0, 7, 0).unwrap()));
+    assert!(c.matches(LocalTime::new(1, 4, 17, 59).unwrap()));
+    assert!(!c.matches(LocalTime::new(1, 4, 18, 0).unwrap()));
+    assert!(c.matches(LocalTime::new(1, 5, 11, 0).unwrap()));
+    assert!(!c.matches(LocalTime::new(1, 6, 11, 0).unwrap()));
+
+    let c = TimeCondition::parse("Nov-Feb").unwrap();
+    assert!(c.matches(LocalTime::new(12, 0, 0, 0).unwrap()));
+    assert!(c.matches(LocalTime::new(1, 0, 0, 0).unwrap()));
+    assert!(!c.matches(LocalTime::new(3, 0, 0, 0).unwrap()));
+
+    let c = TimeCondition::parse("22:00-06:00").unwrap();
+    assert!(c.matches(LocalTime::new(1, 0, 23, 0).unwrap()));
+    assert!(c.matches(LocalTime::new(1, 0, 5, 0).unwrap()));
+    assert!(!c.matches(LocalTime::new(1, 0, 12, 0).unwrap()));
+
+    let c = TimeCondition::parse("18:00-24:00").unwrap();
+    assert!(c.matches(LocalTime::new(1, 0, 23, 59).unwrap()));
+    assert!(!c.matches(LocalTime::new(1, 0, 0, 0).unwrap()));
+    assert!(TimeCondition::parse("18:00-24:59").is_none());
+    assert!(TimeCondition::parse("18:00-25:00").is_none());
+
+    assert!(TimeCondition::parse("weight>7.5").is_none());
+    assert!(TimeCondition::parse("wet").is_none());
+}
+
+#[test]
+fn test_parse_restriction() {
+    let r = ConditionalRestriction::parse("access:conditional", "no @ (Mo-Fr 07:00-09:00)");
+    assert_eq!(3, r.len());
+    assert!(r.iter().all(|r| !r.allowed && r.direction.is_none()));
+
+    let r = ConditionalRestriction::parse(
+        "bicycle:conditional",
+        "yes @ (Sa,Su); no @ (Mo-Fr 07:00-09:00; Sa 08:00-10:00)",
+    );
+    assert_eq!(2, r.len());
+    assert!(r[0].allowed);
+    assert!(!r[1].allowed);
+    assert!(r[1].is_active(LocalTime::new(1, 5, 9, 0).unwrap()));
+
+    let r = ConditionalRestriction::parse("oneway:conditional", "yes @ 06:00-10:00");
+    assert_eq!(2, r.len());
+    assert_eq!(Some(Direction::Backward), r[0].direction);
+
+    assert!(ConditionalRestriction::parse("maxweight:conditional", "3.5 @ wet").is_empty());
+    assert!(ConditionalRestriction::parse("access", "no").is_empty());
+}
+
+#[test]
+fn test_apply_restriction() {
+    let mut p = EdgeProperties::default();
+    p.update_with_str("highway", "secondary");
+    p.normalize();
+
+    for r in ConditionalRestriction::parse("oneway:conditional", "yes @ 06:00-10:00") {
+        r.apply(&mut p);
+    }
+    assert_eq!(CarAccessibility::Secondary, p.car_forward);
+    assert_eq!(CarAccessibility::Forbidden, p.car_backward);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_backward);
+
+    for r in ConditionalRestriction::parse("oneway:conditional", "no @ 10:00-12:00") {
+        r.apply(&mut p);
+    }
+    assert_eq!(CarAccessibility::Secondary, p.car_backward);
+    assert_eq!(BikeAccessibility::Allowed, p.bike_backward);
+}
+
+#[test]
+fn test_access_values() {
+    let r = ConditionalRestriction::parse("motor_vehicle:conditional", "delivery @ 06:00-10:00");
+    assert_eq!(1, r.len());
+    assert!(!r[0].allowed);
+    let r = ConditionalRestriction::parse("motor_vehicle:conditional", "destination @ 06:00-10:00");
+    assert!(r[0].allowed);
+    assert!(
+        ConditionalRestriction::parse("bicycle:conditional", "dismount @ 06:00-10:00").is_empty()
+    );
+}
+
+#[test]
+fn test_prioritize() {
+    let mut r = ConditionalRestriction::parse("vehicle:conditional", "no @ (Sa,Su)");
+    r.extend(ConditionalRestriction::parse(
+        "bicycle:conditional",
+        "yes @ (Sa,Su)",
+    ));
+    r.extend(ConditionalRestriction::parse(
+        "access:conditional",
+        "no @ (Sa,Su)",
+    ));
+    ConditionalRestriction::prioritize(&mut r, ["highway", "oneway"]);
+    assert_eq!(
+        vec![0, 0, 0, 1, 1, 3],
+        r.iter().map(|r| r.specificity).collect::<Vec<_>>()
+    );
+    let mut p = EdgeProperties::default();
+    p.update_with_str("highway", "residential");
+    p.normalize();
+    for restriction in &r {
+        restriction.apply(&mut p);
+    }
+    assert_eq!(BikeAccessibility::Allowed, p.bike_forward);
+    assert_eq!(CarAccessibility::Forbidden, p.car_forward);
+    assert_eq!(FootAccessibility::Forbidden, p.foot);
+
+    // A generic restriction does not override a more specific plain tag
+    let mut r = ConditionalRestriction::parse("access:conditional", "no @ (Sa,Su)");
+    ConditionalRestriction::prioritize(&mut r, ["highway", "bicycle", "foot"]);
+    assert_eq!(
+        vec![Mode::Car],
+        r.iter().map(|r| r.mode).collect::<Vec<_>>()
+    );
+}
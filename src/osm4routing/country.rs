@@ -0,0 +1,477 @@
+//! Country-specific default access rules.
+//!
+//! The default access of a highway type differs between countries: in
+//! Germany bikes may use a `highway=trunk` unless signed otherwise, while
+//! in the Netherlands they may not. The tables below follow the OpenStreetMap
+//! wiki page "OSM tags for routing/Access restrictions" for pedestrians,
+//! bicycles and mopeds (e.g. allowed on Dutch cycleways). Defaults for
+//! other vehicles are not represented.
+//!
+//! Rules can be selected explicitly with a country code, or derived from
+//! the country boundary (`admin_level=2`) a way falls in.
+//!
+//! https://wiki.openstreetmap.org/wiki/OSM_tags_for_routing/Access_restrictions
+
+use super::categorize::*;
+use osmpbfreader::objects::NodeId;
+
+type Coord = geo_types::Coord<f64>;
+
+/// Default access for pedestrians, bicycles and mopeds per highway type in a country.
+///
+/// Only the highway types whose defaults differ from the worldwide rules
+/// of [`EdgeProperties::update_with_str`] are listed.
+#[derive(Debug, PartialEq)]
+pub struct AccessRules {
+    /// ISO 3166-1 alpha-2 code of the country.
+    pub country: &'static str,
+    /// `(highway, foot, bicycle, moped)`, `None` keeping the worldwide default.
+    highways: &'static [HighwayAccess],
+}
+
+/// Default access of a highway type: `(highway, foot, bicycle, moped)`.
+type HighwayAccess = (&'static str, Option<bool>, Option<bool>, Option<bool>);
+
+const YES: Option<bool> = Some(true);
+const NO: Option<bool> = Some(false);
+
+/// Built-in access rules, by country.
+const RULES: &[AccessRules] = &[
+    AccessRules {
+        country: "AT",
+        highways: &[
+            ("footway", None, NO, None),
+            ("pedestrian", None, NO, None),
+            ("cycleway", NO, None, None),
+            ("bridleway", NO, NO, None),
+        ],
+    },
+    AccessRules {
+        country: "BE",
+        highways: &[
+            ("trunk", YES, YES, None),
+            ("trunk_link", YES, YES, None),
+            ("footway", None, NO, None),
+            ("cycleway", NO, None, None),
+            ("bridleway", NO, NO, None),
+        ],
+    },
+    AccessRules {
+        country: "CH",
+        highways: &[
+            ("footway", None, NO, None),
+            ("pedestrian", None, NO, None),
+            ("cycleway", NO, None, None),
+            ("bridleway", NO, NO, None),
+        ],
+    },
+    AccessRules {
+        country: "DE",
+        highways: &[
+            ("trunk", YES, YES, None),
+            ("trunk_link", YES, YES, None),
+            ("footway", None, NO, None),
+            ("pedestrian", None, NO, None),
+            ("steps", None, NO, None),
+            ("cycleway", NO, None, None),
+            ("bridleway", NO, NO, None),
+        ],
+    },
+    AccessRules {
+        country: "FR",
+        highways: &[
+            ("trunk", YES, YES, None),
+            ("trunk_link", YES, YES, None),
+            ("footway", None, NO, None),
+            ("cycleway", NO, None, None),
+            ("bridleway", YES, NO, None),
+        ],
+    },
+    AccessRules {
+        country: "GB",
+        highways: &[
+            ("trunk", YES, YES, None),
+            ("trunk_link", YES, YES, None),
+            ("footway", None, NO, None),
+            ("pedestrian", None, NO, None),
+            ("bridleway", YES, YES, None),
+        ],
+    },
+    AccessRules {
+        country: "NL",
+        highways: &[
+            ("footway", None, NO, None),
+            ("pedestrian", None, NO, None),
+            ("cycleway", NO, None, YES),
+            ("bridleway", NO, NO, None),
+        ],
+    },
+    AccessRules {
+        country: "US",
+        highways: &[
+            ("trunk", YES, YES, None),
+            ("trunk_link", YES, YES, None),
+            ("bridleway", YES, NO, None),
+        ],
+    },
+];
+
+/// Returns the built-in access rules of a country.
+///
+/// # Arguments
+/// * `country` - ISO 3166-1 alpha-2 code, case insensitive (e.g. `"DE"`).
+///
+/// # Example
+/// ```
+/// assert!(osm4routing::access_rules("de").is_some());
+/// assert!(osm4routing::access_rules("XX").is_none());
+/// ```
+pub fn access_rules(country: &str) -> Option<&'static AccessRules> {
+    RULES
+        .iter()
+        .find(|rules| rules.country.eq_ignore_ascii_case(country))
+}
+
+/// Returns all the built-in access rules.
+pub(crate) fn all_access_rules() -> &'static [AccessRules] {
+    RULES
+}
+
+impl AccessRules {
+    /// Applies the country defaults to properties computed from the tags.
+    ///
+    /// Must be called before [`EdgeProperties::normalize`]. Explicit
+    /// `foot`, `bicycle` or `moped` tags always win over the defaults, and
+    /// bike infrastructure (lanes, tracks, busways) is never downgraded.
+    /// Bicycle defaults apply to both directions, but do not lift a one-way
+    /// restriction.
+    ///
+    /// # Arguments
+    /// * `access` - The tags of the way relevant to default access.
+    /// * `properties` - The properties to update.
+    pub fn apply(&self, access: &DefaultAccess, properties: &mut EdgeProperties) {
+        let Some(&(_, foot, bicycle, moped)) = self
+            .highways
+            .iter()
+            .find(|(highway, ..)| *highway == access.highway)
+        else {
+            return;
+        };
+        if !access.foot_tagged {
+            match foot {
+                Some(true) => properties.foot = FootAccessibility::Allowed,
+                Some(false) => properties.foot = FootAccessibility::Forbidden,
+                None => {}
+            }
+        }
+        if !access.bicycle_tagged {
+            match (bicycle, properties.bike_forward) {
+                (Some(true), BikeAccessibility::Unknown | BikeAccessibility::Forbidden) => {
+                    properties.bike_forward = BikeAccessibility::Allowed
+                }
+                (Some(false), BikeAccessibility::Unknown | BikeAccessibility::Allowed) => {
+                    properties.bike_forward = BikeAccessibility::Forbidden
+                }
+                _ => {}
+            }
+            // An unknown backward direction follows the forward one, and a
+            // forbidden one comes from a one-way restriction
+            if bicycle == NO && properties.bike_backward == BikeAccessibility::Allowed {
+                properties.bike_backward = BikeAccessibility::Forbidden;
+            }
+        }
+        if !access.moped_tagged {
+            match moped {
+                Some(true) => properties.moped = MopedAccessibility::Allowed,
+                Some(false) => properties.moped = MopedAccessibility::Forbidden,
+                None => {}
+            }
+        }
+    }
+}
+
+/// The tags of a way that country defaults depend on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DefaultAccess {
+    /// Value of the `highway` tag, empty if missing.
+    pub highway: String,
+    /// A `foot` (or `pedestrian`) tag is present.
+    pub foot_tagged: bool,
+    /// A `bicycle` tag is present.
+    pub bicycle_tagged: bool,
+    /// A `moped` tag is present.
+    pub moped_tagged: bool,
+}
+
+impl DefaultAccess {
+    /// Extracts the relevant tags of a way.
+    pub fn from_tags(tags: &osmpbfreader::Tags) -> DefaultAccess {
+        DefaultAccess {
+            highway: tags
+                .get("highway")
+                .map(|highway| highway.to_string())
+                .unwrap_or_default(),
+            foot_tagged: tags.contains_key("foot") || tags.contains_key("pedestrian"),
+            bicycle_tagged: tags.contains_key("bicycle"),
+            moped_tagged: tags.contains_key("moped"),
+        }
+    }
+}
+
+/// Returns the country of a national boundary relation having built-in rules.
+///
+/// National boundaries are `boundary=administrative` relations with
+/// `admin_level=2`, identified by their `ISO3166-1:alpha2` (or `ISO3166-1`) tag.
+pub(crate) fn boundary_country(relation: &osmpbfreader::Relation) -> Option<&'static str> {
+    let tags = &relation.tags;
+    if tags.get("boundary").map(|v| v.as_str()) != Some("administrative")
+        || tags.get("admin_level").map(|v| v.as_str()) != Some("2")
+    {
+        return None;
+    }
+    tags.get("ISO3166-1:alpha2")
+        .or_else(|| tags.get("ISO3166-1"))
+        .and_then(|code| access_rules(code))
+        .map(|rules| rules.country)
+}
+
+/// Joins boundary ways into closed rings.
+///
+/// Ways are reversed as needed. Ways that cannot be closed into a ring
+/// (e.g. boundaries clipped by the extract) are dropped.
+pub(crate) fn assemble_rings(mut ways: Vec<Vec<NodeId>>) -> Vec<Vec<NodeId>> {
+    ways.retain(|way| way.len() > 1);
+    let mut rings = Vec::new();
+    while let Some(mut ring) = ways.pop() {
+        while ring.first() != ring.last() {
+            let end = *ring.last().unwrap();
+            let Some(i) = ways
+                .iter()
+                .position(|way| way.first() == Some(&end) || way.last() == Some(&end))
+            else {
+                break;
+            };
+            let mut next = ways.swap_remove(i);
+            if next.first() != Some(&end) {
+                next.reverse();
+            }
+            ring.extend_from_slice(&next[1..]);
+        }
+        if ring.len() > 3 && ring.first() == ring.last() {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+/// Number of horizontal bands used to speed up point in ring tests.
+const BANDS: usize = 256;
+
+/// A closed ring, with its segments indexed by horizontal bands.
+struct Ring {
+    min: Coord,
+    max: Coord,
+    band_height: f64,
+    bands: Vec<Vec<(Coord, Coord)>>,
+}
+
+impl Ring {
+    fn new(coords: &[Coord]) -> Ring {
+        let mut min = coords[0];
+        let mut max = coords[0];
+        for coord in coords {
+            min.x = min.x.min(coord.x);
+            min.y = min.y.min(coord.y);
+            max.x = max.x.max(coord.x);
+            max.y = max.y.max(coord.y);
+        }
+        let band_height = ((max.y - min.y) / BANDS as f64).max(f64::EPSILON);
+        let mut bands = vec![Vec::new(); BANDS];
+        for segment in coords.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let first = ((a.y.min(b.y) - min.y) / band_height) as usize;
+            let last = ((a.y.max(b.y) - min.y) / band_height) as usize;
+            for band in bands
+                .iter_mut()
+                .take(last.min(BANDS - 1) + 1)
+                .skip(first.min(BANDS - 1))
+            {
+                band.push((a, b));
+            }
+        }
+        Ring {
+            min,
+            max,
+            band_height,
+            bands,
+        }
+    }
+
+    /// Counts the crossings of a ray going east from the point (even-odd rule).
+    fn crossings(&self, point: Coord) -> usize {
+        if point.x < self.min.x
+            || point.x > self.max.x
+            || point.y < self.min.y
+            || point.y > self.max.y
+        {
+            return 0;
+        }
+        let band = (((point.y - self.min.y) / self.band_height) as usize).min(BANDS - 1);
+        self.bands[band]
+            .iter()
+            .filter(|(a, b)| {
+                (a.y > point.y) != (b.y > point.y)
+                    && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+            })
+            .count()
+    }
+}
+
+/// Country boundaries used to find the country of a point.
+#[derive(Default)]
+pub(crate) struct Boundaries {
+    countries: Vec<(String, Vec<Ring>)>,
+}
+
+impl Boundaries {
+    /// Adds the boundary of a country.
+    ///
+    /// Inner and outer rings are handled alike with the even-odd rule.
+    pub(crate) fn add(&mut self, country: &str, rings: &[Vec<Coord>]) {
+        let rings = rings
+            .iter()
+            .filter(|ring| !ring.is_empty())
+            .map(|ring| Ring::new(ring))
+            .collect::<Vec<_>>();
+        if !rings.is_empty() {
+            self.countries.push((country.to_string(), rings));
+        }
+    }
+
+    /// Returns the code of the country containing the point.
+    pub(crate) fn country_at(&self, point: Coord) -> Option<&str> {
+        self.countries
+            .iter()
+            .find(|(_, rings)| {
+                rings
+                    .iter()
+                    .map(|ring| ring.crossings(point))
+                    .sum::<usize>()
+                    % 2
+                    == 1
+            })
+            .map(|(country, _)| country.as_str())
+    }
+}
+
+#[test]
+fn test_apply_rules() {
+    let rules = |country: &str, tags: &[(&str, &str)]| {
+        let mut p = EdgeProperties::default();
+        let mut access = DefaultAccess::default();
+        for (key, val) in tags {
+            p.update_with_str(key, val);
+            match *key {
+                "highway" => access.highway = val.to_string(),
+                "foot" => access.foot_tagged = true,
+                "bicycle" => access.bicycle_tagged = true,
+                "moped" => access.moped_tagged = true,
+                _ => {}
+            }
+        }
+        if let Some(rules) = access_rules(country) {
+            rules.apply(&access, &mut p);
+        }
+        p.normalize();
+        p
+    };
+
+    let p = rules("XX", &[("highway", "trunk")]);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_forward);
+
+    let p = rules("DE", &[("highway", "trunk")]);
+    assert_eq!(BikeAccessibility::Allowed, p.bike_forward);
+    assert_eq!(BikeAccessibility::Allowed, p.bike_backward);
+    assert_eq!(FootAccessibility::Allowed, p.foot);
+
+    let p = rules("DE", &[("highway", "trunk"), ("bicycle", "no")]);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_forward);
+
+    let p = rules("DE", &[("highway", "trunk"), ("oneway", "yes")]);
+    assert_eq!(BikeAccessibility::Allowed, p.bike_forward);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_backward);
+
+    // Both directions follow the rules when bikes are exempted from the one-way
+    let contraflow = [
+        ("highway", "trunk"),
+        ("oneway", "yes"),
+        ("oneway:bicycle", "no"),
+    ];
+    let p = rules("DE", &contraflow);
+    assert_eq!(BikeAccessibility::Allowed, p.bike_forward);
+    assert_eq!(BikeAccessibility::Allowed, p.bike_backward);
+    let p = rules("XX", &contraflow);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_forward);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_backward);
+
+    let p = rules("NL", &[("highway", "footway")]);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_forward);
+
+    let p = rules("NL", &[("cycleway", "opposite"), ("highway", "footway")]);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_forward);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_backward);
+
+    let p = rules("NL", &[("highway", "footway"), ("bicycle", "yes")]);
+    assert_eq!(BikeAccessibility::Allowed, p.bike_forward);
+
+    let p = rules("NL", &[("highway", "cycleway")]);
+    assert_eq!(FootAccessibility::Forbidden, p.foot);
+    assert_eq!(BikeAccessibility::Track, p.bike_forward);
+    assert_eq!(MopedAccessibility::Allowed, p.moped);
+
+    let p = rules("NL", &[("highway", "cycleway"), ("moped", "no")]);
+    assert_eq!(MopedAccessibility::Forbidden, p.moped);
+
+    let p = rules("DE", &[("highway", "cycleway")]);
+    assert_eq!(MopedAccessibility::Forbidden, p.moped);
+
+    let p = rules("XX", &[("highway", "residential")]);
+    assert_eq!(MopedAccessibility::Allowed, p.moped);
+    let p = rules("XX", &[("highway", "motorway")]);
+    assert_eq!(MopedAccessibility::Forbidden, p.moped);
+
+    let p = rules("GB", &[("highway", "bridleway")]);
+    assert!(p.accessible());
+}
+
+#[test]
+fn test_rings() {
+    let ways = vec![
+        vec![NodeId(1), NodeId(2), NodeId(3)],
+        vec![NodeId(5), NodeId(4), NodeId(3)],
+        vec![NodeId(5), NodeId(1)],
+        vec![NodeId(10), NodeId(11)],
+    ];
+    let rings = assemble_rings(ways);
+    assert_eq!(1, rings.len());
+    assert_eq!(6, rings[0].len());
+    assert_eq!(rings[0].first(), rings[0].last());
+}
+
+#[test]
+fn test_country_at() {
+    let square = |min: f64, max: f64| {
+        [(min, min), (max, min), (max, max), (min, max), (min, min)]
+            .iter()
+            .map(|&(x, y)| Coord { x, y })
+            .collect::<Vec<_>>()
+    };
+
+    let mut boundaries = Boundaries::default();
+    boundaries.add("DE", &[square(0., 10.), square(4., 6.)]);
+    boundaries.add("LU", &[square(4., 6.)]);
+
+    assert_eq!(Some("DE"), boundaries.country_at(Coord { x: 1., y: 1. }));
+    assert_eq!(Some("LU"), boundaries.country_at(Coord { x: 5., y: 5. }));
+    assert_eq!(None, boundaries.country_at(Coord { x: 11., y: 5. }));
+}
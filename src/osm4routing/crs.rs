@@ -0,0 +1,234 @@
+//! Coordinate reference systems of the output.
+//!
+//! Nodes and edges are read in WGS84 degrees. Writers can reproject them to a
+//! metric system for GIS and simulation tools, with built-in UTM, Web Mercator
+//! and Lambert-93 definitions or any PROJ string. Lengths are still measured
+//! on the ellipsoid, with the [`DistanceModel`](super::distance::DistanceModel) of each edge.
+
+use super::error::Error;
+use proj4rs::Proj;
+use std::str::FromStr;
+
+type Coord = geo_types::Coord<f64>;
+
+const WGS84_PROJ: &str = "+proj=longlat +datum=WGS84 +no_defs";
+const WEB_MERCATOR_PROJ: &str = "+proj=merc +a=6378137 +b=6378137 +lat_ts=0 +lon_0=0 +x_0=0 +y_0=0 +k=1 +units=m +nadgrids=@null +no_defs";
+const LAMBERT_93_PROJ: &str = "+proj=lcc +lat_0=46.5 +lon_0=3 +lat_1=49 +lat_2=44 +x_0=700000 +y_0=6600000 +ellps=GRS80 +towgs84=0,0,0,0,0,0,0 +units=m +no_defs";
+
+/// A coordinate reference system.
+///
+/// Parsed from `wgs84`, `utm` (zone of the data), `utm31n`, `utm58s`,
+/// `webmercator`, `lambert93`, the matching `EPSG:` codes, or a PROJ string
+/// starting with `+`.
+///
+/// # Example
+///
+/// ```
+/// use osm4routing::Crs;
+///
+/// assert_eq!(Ok(Crs::Utm { zone: 31, south: false }), "EPSG:32631".parse());
+/// assert_eq!(Crs::Utm { zone: 32, south: false }, Crs::utm_zone(geo_types::Coord { x: 10.75, y: 59.91 }));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Crs {
+    /// Longitude and latitude in degrees (EPSG:4326).
+    #[default]
+    Wgs84,
+    /// UTM, in the zone of the center of the data.
+    UtmAuto,
+    /// UTM in a given zone (EPSG:326xx in the north, EPSG:327xx in the south).
+    Utm {
+        /// Zone number, from 1 to 60.
+        zone: u8,
+        /// Whether the zone is in the southern hemisphere.
+        south: bool,
+    },
+    /// Spherical Mercator used by web maps (EPSG:3857).
+    WebMercator,
+    /// Lambert-93, the official projection of metropolitan France (EPSG:2154).
+    Lambert93,
+    /// Any PROJ string, such as `+proj=utm +zone=33 +datum=WGS84`.
+    Proj(String),
+}
+
+impl Crs {
+    /// UTM zone of a coordinate, including the exceptions of Norway and Svalbard.
+    pub fn utm_zone(coord: Coord) -> Crs {
+        let (lon, lat) = (coord.x, coord.y);
+        let mut zone = (((lon + 180.) / 6.).floor() as i32 + 1).clamp(1, 60) as u8;
+        if (56. ..64.).contains(&lat) && (3. ..12.).contains(&lon) {
+            zone = 32;
+        } else if (72. ..84.).contains(&lat) && (0. ..42.).contains(&lon) {
+            zone = match lon {
+                lon if lon < 9. => 31,
+                lon if lon < 21. => 33,
+                lon if lon < 33. => 35,
+                _ => 37,
+            };
+        }
+        Crs::Utm {
+            zone,
+            south: lat < 0.,
+        }
+    }
+
+    /// PROJ string of the system, with [`Crs::UtmAuto`] resolved at `center`.
+    pub fn proj_string(&self, center: Coord) -> String {
+        match self {
+            Crs::Wgs84 => WGS84_PROJ.to_string(),
+            Crs::UtmAuto => Crs::utm_zone(center).proj_string(center),
+            Crs::Utm { zone, south } => format!(
+                "+proj=utm +zone={zone}{} +datum=WGS84 +units=m +no_defs",
+                if *south { " +south" } else { "" }
+            ),
+            Crs::WebMercator => WEB_MERCATOR_PROJ.to_string(),
+            Crs::Lambert93 => LAMBERT_93_PROJ.to_string(),
+            Crs::Proj(definition) => definition.clone(),
+        }
+    }
+}
+
+impl FromStr for Crs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with('+') {
+            return Ok(Crs::Proj(s.to_string()));
+        }
+        let lower = s.to_ascii_lowercase();
+        let utm = |zone: &str, south| match zone.parse() {
+            Ok(zone @ 1..=60) => Ok(Crs::Utm { zone, south }),
+            _ => Err(format!("invalid UTM zone in {s}")),
+        };
+        match lower.as_str() {
+            "wgs84" | "epsg:4326" => Ok(Crs::Wgs84),
+            "utm" => Ok(Crs::UtmAuto),
+            "webmercator" | "epsg:3857" => Ok(Crs::WebMercator),
+            "lambert93" | "epsg:2154" => Ok(Crs::Lambert93),
+            _ => {
+                if let Some(zone) = lower.strip_prefix("epsg:326") {
+                    utm(zone, false)
+                } else if let Some(zone) = lower.strip_prefix("epsg:327") {
+                    utm(zone, true)
+                } else if let Some(zone) =
+                    lower.strip_prefix("utm").and_then(|z| z.strip_suffix('n'))
+                {
+                    utm(zone, false)
+                } else if let Some(zone) =
+                    lower.strip_prefix("utm").and_then(|z| z.strip_suffix('s'))
+                {
+                    utm(zone, true)
+                } else {
+                    Err(format!(
+                        "unknown coordinate system {s}, expected wgs84, utm, utm<zone><n|s>, webmercator, lambert93, an EPSG code of those or a PROJ string"
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Reprojects WGS84 coordinates to a [`Crs`].
+///
+/// # Example
+///
+/// ```
+/// use osm4routing::{Crs, Reprojection};
+/// use geo_types::Coord;
+///
+/// let paris = Coord { x: 2.3522, y: 48.8566 };
+/// let lambert = Reprojection::new(&Crs::Lambert93, paris).unwrap();
+/// let projected = lambert.project(paris).unwrap();
+/// assert!((projected.x - 652_470.).abs() < 100.);
+/// ```
+pub struct Reprojection {
+    from: Proj,
+    to: Option<Proj>,
+}
+
+impl Reprojection {
+    /// Prepares the reprojection, [`Crs::UtmAuto`] taking the zone of `center`.
+    pub fn new(crs: &Crs, center: Coord) -> Result<Reprojection, Error> {
+        let to = match crs {
+            Crs::Wgs84 => None,
+            crs => Some(Proj::from_proj_string(&crs.proj_string(center))?),
+        };
+        Ok(Reprojection {
+            from: Proj::from_proj_string(WGS84_PROJ)?,
+            to,
+        })
+    }
+
+    /// Whether the output is longitude and latitude in degrees.
+    pub fn is_geographic(&self) -> bool {
+        self.to.as_ref().is_none_or(Proj::is_latlong)
+    }
+
+    /// Reprojects a coordinate given in WGS84 degrees.
+    pub fn project(&self, coord: Coord) -> Result<Coord, Error> {
+        let Some(to) = &self.to else {
+            return Ok(coord);
+        };
+        let mut point = Coord {
+            x: coord.x.to_radians(),
+            y: coord.y.to_radians(),
+        };
+        proj4rs::transform::transform(&self.from, to, &mut point)?;
+        if to.is_latlong() {
+            point = Coord {
+                x: point.x.to_degrees(),
+                y: point.y.to_degrees(),
+            };
+        }
+        Ok(point)
+    }
+}
+
+#[test]
+fn test_reprojection() {
+    let paris = Coord {
+        x: 2.3522,
+        y: 48.8566,
+    };
+    let close = |a: Coord, x: f64, y: f64| (a.x - x).abs() < 1. && (a.y - y).abs() < 1.;
+
+    let mercator = Reprojection::new(&Crs::WebMercator, paris).unwrap();
+    assert!(close(
+        mercator.project(paris).unwrap(),
+        261_845.7,
+        6_250_564.3
+    ));
+
+    let utm = Reprojection::new(&Crs::UtmAuto, paris).unwrap();
+    assert!(!utm.is_geographic());
+    assert!(close(utm.project(paris).unwrap(), 452_482.5, 5_411_717.2));
+
+    let wgs84 = Reprojection::new(&Crs::Wgs84, paris).unwrap();
+    assert!(wgs84.is_geographic());
+    assert_eq!(paris, wgs84.project(paris).unwrap());
+
+    let proj = Reprojection::new(&Crs::Proj("+proj=longlat +ellps=GRS80".into()), paris).unwrap();
+    assert!(proj.is_geographic());
+    assert!((proj.project(paris).unwrap().x - 2.3522).abs() < 1e-9);
+
+    assert_eq!(
+        Crs::Utm {
+            zone: 58,
+            south: true
+        },
+        Crs::utm_zone(Coord {
+            x: 166.45,
+            y: -22.27
+        })
+    );
+    assert_eq!(
+        Ok(Crs::Utm {
+            zone: 58,
+            south: true
+        }),
+        "utm58s".parse()
+    );
+    assert_eq!(Ok(Crs::Lambert93), "EPSG:2154".parse());
+    assert!("utm61n".parse::<Crs>().is_err());
+}
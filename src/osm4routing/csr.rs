@@ -0,0 +1,438 @@
+//! Compact graph in compressed sparse row (CSR) format.
+//!
+//! [`CsrGraph`] stores a routing graph in a few flat arrays: `u32` node
+//! indices, one offsets array per mode, per-mode travel times and all the
+//! geometries in a single coordinate buffer. It takes a fraction of the memory
+//! of `Vec<Edge>` and is meant for continent-sized networks.
+//!
+//! It can be saved to and loaded from a little-endian binary file.
+
+use super::categorize::{Direction, EdgeProperties, Mode};
+use super::distance::DistanceModel;
+use super::error::Error;
+use super::models::*;
+use ahash::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+type Coord = geo_types::Coord<f64>;
+
+/// Header of the binary file, with the format version.
+const MAGIC: &[u8; 8] = b"O4RCSR01";
+
+/// Coordinates are stored as integers, in 1e-7 degrees like in OSM PBF files.
+const COORD_SCALE: f64 = 1e7;
+
+/// An arc being built: `(tail, head, edge, forward, weight)`.
+type ArcTuple = (u32, u32, u32, bool, f32);
+
+/// Arcs of one mode in CSR format.
+///
+/// The arcs leaving node `n` are at positions `offsets[n]..offsets[n + 1]`
+/// of the other arrays.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CsrAdjacency {
+    /// Start of the arcs of each node, plus the total number of arcs.
+    pub offsets: Vec<u32>,
+    /// Node reached by each arc.
+    pub heads: Vec<u32>,
+    /// Edge of each arc.
+    pub edges: Vec<u32>,
+    /// Whether each arc follows its edge from source to target.
+    pub forward: Vec<bool>,
+    /// Travel time of each arc in seconds.
+    pub weights: Vec<f32>,
+}
+
+impl CsrAdjacency {
+    /// Builds the arrays from the arcs.
+    fn new(nodes_count: usize, mut arcs: Vec<ArcTuple>) -> CsrAdjacency {
+        arcs.sort_unstable_by_key(|&(tail, _, edge, forward, _)| (tail, edge, !forward));
+        let mut offsets = vec![0u32; nodes_count + 1];
+        for &(tail, ..) in &arcs {
+            offsets[tail as usize + 1] += 1;
+        }
+        for i in 0..nodes_count {
+            offsets[i + 1] += offsets[i];
+        }
+        CsrAdjacency {
+            offsets,
+            heads: arcs.iter().map(|arc| arc.1).collect(),
+            edges: arcs.iter().map(|arc| arc.2).collect(),
+            forward: arcs.iter().map(|arc| arc.3).collect(),
+            weights: arcs.iter().map(|arc| arc.4).collect(),
+        }
+    }
+
+    /// Returns the positions of the arcs leaving a node.
+    pub fn arcs(&self, node: u32) -> std::ops::Range<usize> {
+        self.offsets[node as usize] as usize..self.offsets[node as usize + 1] as usize
+    }
+}
+
+/// A routing graph in compressed sparse row format.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{CsrGraph, Mode, Reader};
+///
+/// let graph = Reader::new().read_csr("europe.osm.pbf").unwrap();
+/// graph.save("europe.csr").unwrap();
+///
+/// let graph = CsrGraph::load("europe.csr").unwrap();
+/// let car = graph.adjacency(Mode::Car);
+/// for arc in car.arcs(0) {
+///     println!("To node {} in {}s", car.heads[arc], car.weights[arc]);
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CsrGraph {
+    /// OSM id of each node.
+    pub node_ids: Vec<i64>,
+    /// `[lon, lat]` coordinates of each node, in 1e-7 degrees.
+    pub node_coords: Vec<[i32; 2]>,
+    /// OSM way id of each edge.
+    pub edge_osm_ids: Vec<i64>,
+    /// Source node of each edge.
+    pub sources: Vec<u32>,
+    /// Target node of each edge.
+    pub targets: Vec<u32>,
+    /// Length of each edge in meters.
+    pub lengths: Vec<f32>,
+    /// The geometry of edge `e` is `coords[geometry_offsets[e]..geometry_offsets[e + 1]]`.
+    pub geometry_offsets: Vec<u64>,
+    /// Shared buffer of `[lon, lat]` coordinates, in 1e-7 degrees.
+    pub coords: Vec<[i32; 2]>,
+    /// Arcs of each mode, in the order of [`Mode::ALL`].
+    modes: [CsrAdjacency; 4],
+}
+
+fn encode(coord: Coord) -> [i32; 2] {
+    [
+        (coord.x * COORD_SCALE).round() as i32,
+        (coord.y * COORD_SCALE).round() as i32,
+    ]
+}
+
+fn decode(coord: [i32; 2]) -> Coord {
+    Coord {
+        x: coord[0] as f64 / COORD_SCALE,
+        y: coord[1] as f64 / COORD_SCALE,
+    }
+}
+
+impl CsrGraph {
+    /// Number of nodes.
+    pub fn nodes_count(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    /// Number of edges.
+    pub fn edges_count(&self) -> usize {
+        self.edge_osm_ids.len()
+    }
+
+    /// Returns the arcs of a mode.
+    pub fn adjacency(&self, mode: Mode) -> &CsrAdjacency {
+        &self.modes[mode.index()]
+    }
+
+    /// Returns the coordinates of a node.
+    pub fn node_coord(&self, node: u32) -> Coord {
+        decode(self.node_coords[node as usize])
+    }
+
+    /// Returns the geometry of an edge, from source to target.
+    pub fn geometry(&self, edge: u32) -> Vec<Coord> {
+        let start = self.geometry_offsets[edge as usize] as usize;
+        let end = self.geometry_offsets[edge as usize + 1] as usize;
+        self.coords[start..end]
+            .iter()
+            .copied()
+            .map(decode)
+            .collect()
+    }
+
+    /// Saves the graph to a binary file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+        w.write_all(MAGIC)?;
+        write_values(&mut w, &self.node_ids, |v| v.to_le_bytes())?;
+        write_values(&mut w, &self.node_coords, coord_to_bytes)?;
+        write_values(&mut w, &self.edge_osm_ids, |v| v.to_le_bytes())?;
+        write_values(&mut w, &self.sources, |v| v.to_le_bytes())?;
+        write_values(&mut w, &self.targets, |v| v.to_le_bytes())?;
+        write_values(&mut w, &self.lengths, |v| v.to_le_bytes())?;
+        write_values(&mut w, &self.geometry_offsets, |v| v.to_le_bytes())?;
+        write_values(&mut w, &self.coords, coord_to_bytes)?;
+        for adjacency in &self.modes {
+            write_values(&mut w, &adjacency.offsets, |v| v.to_le_bytes())?;
+            write_values(&mut w, &adjacency.heads, |v| v.to_le_bytes())?;
+            write_values(&mut w, &adjacency.edges, |v| v.to_le_bytes())?;
+            write_values(&mut w, &adjacency.forward, |v| [v as u8])?;
+            write_values(&mut w, &adjacency.weights, |v| v.to_le_bytes())?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Loads a graph saved with [`CsrGraph::save`].
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a CSR graph.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<CsrGraph, Error> {
+        let mut r = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an osm4routing CSR graph"));
+        }
+        let mut graph = CsrGraph {
+            node_ids: read_values(&mut r, i64::from_le_bytes)?,
+            node_coords: read_values(&mut r, coord_from_bytes)?,
+            edge_osm_ids: read_values(&mut r, i64::from_le_bytes)?,
+            sources: read_values(&mut r, u32::from_le_bytes)?,
+            targets: read_values(&mut r, u32::from_le_bytes)?,
+            lengths: read_values(&mut r, f32::from_le_bytes)?,
+            geometry_offsets: read_values(&mut r, u64::from_le_bytes)?,
+            coords: read_values(&mut r, coord_from_bytes)?,
+            modes: Default::default(),
+        };
+        for adjacency in &mut graph.modes {
+            adjacency.offsets = read_values(&mut r, u32::from_le_bytes)?;
+            adjacency.heads = read_values(&mut r, u32::from_le_bytes)?;
+            adjacency.edges = read_values(&mut r, u32::from_le_bytes)?;
+            adjacency.forward = read_values(&mut r, |b: [u8; 1]| b[0] != 0)?;
+            adjacency.weights = read_values(&mut r, f32::from_le_bytes)?;
+        }
+        if !graph.is_consistent() {
+            return Err(invalid_data("inconsistent CSR graph"));
+        }
+        Ok(graph)
+    }
+
+    /// Checks that array lengths match, so that indexing cannot panic.
+    fn is_consistent(&self) -> bool {
+        let (nodes, edges) = (self.nodes_count(), self.edges_count());
+        let coords = self.coords.len() as u64;
+        self.node_coords.len() == nodes
+            && [&self.sources, &self.targets]
+                .iter()
+                .all(|ends| ends.len() == edges && ends.iter().all(|&n| (n as usize) < nodes))
+            && self.lengths.len() == edges
+            && self.geometry_offsets.len() == edges + 1
+            && self.geometry_offsets.windows(2).all(|w| w[0] <= w[1])
+            && self.geometry_offsets.last() == Some(&coords)
+            && self.modes.iter().all(|a| {
+                let arcs = a.heads.len();
+                a.offsets.len() == nodes + 1
+                    && a.offsets.windows(2).all(|w| w[0] <= w[1])
+                    && a.offsets.last().map(|&o| o as usize) == Some(arcs)
+                    && a.heads.iter().all(|&n| (n as usize) < nodes)
+                    && a.edges.len() == arcs
+                    && a.edges.iter().all(|&e| (e as usize) < edges)
+                    && a.forward.len() == arcs
+                    && a.weights.len() == arcs
+            })
+    }
+}
+
+fn coord_to_bytes(coord: [i32; 2]) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    bytes[..4].copy_from_slice(&coord[0].to_le_bytes());
+    bytes[4..].copy_from_slice(&coord[1].to_le_bytes());
+    bytes
+}
+
+fn coord_from_bytes(b: [u8; 8]) -> [i32; 2] {
+    [
+        i32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        i32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+    ]
+}
+
+pub(crate) fn invalid_data(message: &str) -> Error {
+    Error::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message,
+    ))
+}
+
+/// Writes the number of values, then each value.
+pub(crate) fn write_values<T: Copy, const N: usize>(
+    w: &mut impl Write,
+    values: &[T],
+    to_bytes: impl Fn(T) -> [u8; N],
+) -> std::io::Result<()> {
+    w.write_all(&(values.len() as u64).to_le_bytes())?;
+    for &value in values {
+        w.write_all(&to_bytes(value))?;
+    }
+    Ok(())
+}
+
+/// Reads values written by [`write_values`].
+pub(crate) fn read_values<T, const N: usize>(
+    r: &mut impl Read,
+    from_bytes: impl Fn([u8; N]) -> T,
+) -> Result<Vec<T>, Error> {
+    let mut len = [0; 8];
+    r.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len) as usize;
+    // Do not trust the length for the allocation: a corrupted file would abort
+    let mut values = Vec::with_capacity(len.min(1 << 20));
+    let mut bytes = [0; N];
+    for _ in 0..len {
+        r.read_exact(&mut bytes)?;
+        values.push(from_bytes(bytes));
+    }
+    Ok(values)
+}
+
+/// Builds a [`CsrGraph`] edge by edge.
+pub(crate) struct CsrBuilder {
+    graph: CsrGraph,
+    node_index: HashMap<NodeId, u32>,
+    /// Arcs of each mode.
+    arcs: [Vec<ArcTuple>; 4],
+    /// How the lengths of the edges are measured.
+    distance_model: DistanceModel,
+}
+
+impl CsrBuilder {
+    /// Starts a graph with the given nodes, sorted by id.
+    pub(crate) fn new(mut nodes: Vec<Node>) -> CsrBuilder {
+        nodes.sort_by_key(|node| node.id);
+        let mut graph = CsrGraph {
+            geometry_offsets: vec![0],
+            ..Default::default()
+        };
+        for node in &nodes {
+            graph.node_ids.push(node.id.0);
+            graph.node_coords.push(encode(node.coord));
+        }
+        CsrBuilder {
+            node_index: nodes
+                .iter()
+                .enumerate()
+                .map(|(i, node)| (node.id, i as u32))
+                .collect(),
+            graph,
+            arcs: Default::default(),
+            distance_model: DistanceModel::default(),
+        }
+    }
+
+    /// Sets how the lengths of the edges are measured.
+    pub(crate) fn distance_model(mut self, model: DistanceModel) -> CsrBuilder {
+        self.distance_model = model;
+        self
+    }
+
+    /// Adds an edge. Edges whose source or target is unknown are ignored.
+    pub(crate) fn push(
+        &mut self,
+        osm_id: WayId,
+        source: NodeId,
+        target: NodeId,
+        properties: &EdgeProperties,
+        geometry: &[Coord],
+    ) {
+        let (Some(&source), Some(&target)) =
+            (self.node_index.get(&source), self.node_index.get(&target))
+        else {
+            return;
+        };
+        let graph = &mut self.graph;
+        let edge = graph.edge_osm_ids.len() as u32;
+        let length = self.distance_model.length(geometry);
+        graph.edge_osm_ids.push(osm_id.0);
+        graph.sources.push(source);
+        graph.targets.push(target);
+        graph.lengths.push(length as f32);
+        graph.coords.extend(geometry.iter().copied().map(encode));
+        graph.geometry_offsets.push(graph.coords.len() as u64);
+
+        for (mode, arcs) in Mode::ALL.into_iter().zip(&mut self.arcs) {
+            for direction in [Direction::Forward, Direction::Backward] {
+                if let Some(speed) = properties.speed(mode, direction) {
+                    let weight = (length / (speed / 3.6)) as f32;
+                    let forward = direction == Direction::Forward;
+                    let (tail, head) = if forward {
+                        (source, target)
+                    } else {
+                        (target, source)
+                    };
+                    arcs.push((tail, head, edge, forward, weight));
+                }
+            }
+        }
+    }
+
+    /// Sorts the arcs and returns the graph.
+    pub(crate) fn build(self) -> CsrGraph {
+        let mut graph = self.graph;
+        let nodes_count = graph.nodes_count();
+        graph.modes = self.arcs.map(|arcs| CsrAdjacency::new(nodes_count, arcs));
+        graph
+    }
+}
+
+#[cfg(test)]
+fn test_graph() -> CsrGraph {
+    let mut properties = EdgeProperties::default();
+    properties.update_with_str("highway", "residential");
+    properties.update_with_str("oneway", "yes");
+    properties.normalize();
+    let node = |id, x| Node {
+        id: NodeId(id),
+        coord: Coord { x, y: 0. },
+        ..Default::default()
+    };
+
+    let mut builder = CsrBuilder::new(vec![node(3, 0.002), node(1, 0.), node(2, 0.001)]);
+    let coords = [0., 0.0005, 0.001, 0.002].map(|x| Coord { x, y: 0. });
+    builder.push(WayId(10), NodeId(1), NodeId(2), &properties, &coords[..3]);
+    builder.push(WayId(11), NodeId(2), NodeId(3), &properties, &coords[2..]);
+    builder.push(WayId(12), NodeId(3), NodeId(4), &properties, &coords[2..]);
+    builder.build()
+}
+
+#[test]
+fn test_build() {
+    let g = test_graph();
+    assert_eq!(vec![1, 2, 3], g.node_ids);
+    assert_eq!(2, g.edges_count());
+    assert_eq!(vec![0, 1], g.sources);
+    assert_eq!(3, g.geometry(0).len());
+    assert!((g.geometry(1)[1].x - 0.002).abs() < 1e-9);
+    assert!((g.node_coord(2).x - 0.002).abs() < 1e-9);
+
+    let car = g.adjacency(Mode::Car);
+    assert_eq!(vec![0, 1, 2, 2], car.offsets);
+    assert_eq!(vec![1, 2], car.heads);
+    // 111 m at 40 km/h
+    assert!((car.weights[0] - 10.0).abs() < 0.1);
+
+    let foot = g.adjacency(Mode::Foot);
+    assert_eq!(4, foot.heads.len());
+    let heads: Vec<_> = foot.arcs(1).map(|arc| foot.heads[arc]).collect();
+    assert_eq!(vec![0, 2], heads);
+}
+
+#[test]
+fn test_save_load() {
+    let g = test_graph();
+    let path = std::env::temp_dir().join("osm4routing_test.csr");
+    g.save(&path).unwrap();
+    let loaded = CsrGraph::load(&path).unwrap();
+    assert_eq!(g, loaded);
+
+    std::fs::write(&path, b"O4RCSR01").unwrap();
+    assert!(CsrGraph::load(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
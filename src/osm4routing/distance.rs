@@ -0,0 +1,191 @@
+//! Distance models between WGS84 coordinates.
+//!
+//! The haversine formula on a sphere is fast but can be off by up to 0.5%
+//! as it ignores the flattening of the Earth. Vincenty's formulae and
+//! Karney's algorithm measure on the WGS84 ellipsoid, to the millimeter.
+
+use geographiclib_rs::{Geodesic, InverseGeodesic};
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+type Coord = geo_types::Coord<f64>;
+
+/// Mean radius of the Earth in meters (IUGG).
+pub const MEAN_RADIUS: f64 = 6_371_008.8;
+
+/// Semi-major axis of the WGS84 ellipsoid in meters.
+const WGS84_A: f64 = 6_378_137.0;
+/// Flattening of the WGS84 ellipsoid.
+const WGS84_F: f64 = 1. / 298.257_223_563;
+
+/// Ratio of the smallest radius of curvature of the WGS84 ellipsoid to the
+/// mean radius, rounded down: haversine distances times this ratio never
+/// exceed geodesic distances, which keeps A* heuristics admissible.
+pub(crate) const HAVERSINE_LOWER_BOUND: f64 = 0.994;
+
+static WGS84: LazyLock<Geodesic> = LazyLock::new(Geodesic::wgs84);
+
+/// How distances between coordinates are measured.
+///
+/// # Example
+///
+/// ```
+/// use osm4routing::DistanceModel;
+/// use geo_types::Coord;
+///
+/// let a = Coord { x: 0., y: 0. };
+/// let b = Coord { x: 1., y: 0. };
+/// let haversine = DistanceModel::Haversine.distance(a, b);
+/// let geodesic = DistanceModel::Geodesic.distance(a, b);
+/// assert!((geodesic - 111_319.5).abs() < 0.1);
+/// assert!(geodesic > haversine);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DistanceModel {
+    /// Great-circle distance on a sphere of the [mean radius](MEAN_RADIUS).
+    #[default]
+    Haversine,
+    /// Vincenty's inverse formula on the WGS84 ellipsoid.
+    ///
+    /// Falls back to [`DistanceModel::Geodesic`] for nearly antipodal points,
+    /// where the iteration does not converge.
+    Vincenty,
+    /// Karney's algorithm on the WGS84 ellipsoid, accurate everywhere.
+    Geodesic,
+}
+
+impl DistanceModel {
+    /// Distance between two coordinates, in meters.
+    pub fn distance(self, from: Coord, to: Coord) -> f64 {
+        match self {
+            DistanceModel::Haversine => haversine(from, to),
+            DistanceModel::Vincenty => vincenty(from, to).unwrap_or_else(|| geodesic(from, to)),
+            DistanceModel::Geodesic => geodesic(from, to),
+        }
+    }
+
+    /// Length of a line string, in meters.
+    pub fn length(self, geometry: &[Coord]) -> f64 {
+        geometry
+            .windows(2)
+            .map(|coords| self.distance(coords[0], coords[1]))
+            .sum()
+    }
+}
+
+impl FromStr for DistanceModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "haversine" => Ok(DistanceModel::Haversine),
+            "vincenty" => Ok(DistanceModel::Vincenty),
+            "geodesic" | "karney" => Ok(DistanceModel::Geodesic),
+            _ => Err(format!(
+                "unknown distance model {s}, expected haversine, vincenty or geodesic"
+            )),
+        }
+    }
+}
+
+fn haversine(from: Coord, to: Coord) -> f64 {
+    let d_lon = (to.x - from.x).to_radians();
+    let d_lat = (to.y - from.y).to_radians();
+    let lat1 = from.y.to_radians();
+    let lat2 = to.y.to_radians();
+
+    let a = (d_lat / 2.).sin().powi(2) + (d_lon / 2.).sin().powi(2) * lat1.cos() * lat2.cos();
+    let c = 2. * a.sqrt().atan2((1. - a).sqrt());
+
+    MEAN_RADIUS * c
+}
+
+/// Vincenty's inverse formula, `None` if it does not converge.
+fn vincenty(from: Coord, to: Coord) -> Option<f64> {
+    let b = WGS84_A * (1. - WGS84_F);
+    let l = (to.x - from.x).to_radians();
+    let u1 = ((1. - WGS84_F) * from.y.to_radians().tan()).atan();
+    let u2 = ((1. - WGS84_F) * to.y.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0. {
+            return Some(0.);
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1. - sin_alpha * sin_alpha;
+        // Equatorial line: cos_sq_alpha is 0
+        let cos_2sigma_m = if cos_sq_alpha != 0. {
+            cos_sigma - 2. * sin_u1 * sin_u2 / cos_sq_alpha
+        } else {
+            0.
+        };
+        let c = WGS84_F / 16. * cos_sq_alpha * (4. + WGS84_F * (4. - 3. * cos_sq_alpha));
+        let previous = lambda;
+        lambda = l
+            + (1. - c)
+                * WGS84_F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1. + 2. * cos_2sigma_m.powi(2))));
+        if (lambda - previous).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (WGS84_A * WGS84_A - b * b) / (b * b);
+            let big_a = 1. + u_sq / 16384. * (4096. + u_sq * (-768. + u_sq * (320. - 175. * u_sq)));
+            let big_b = u_sq / 1024. * (256. + u_sq * (-128. + u_sq * (74. - 47. * u_sq)));
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + big_b / 4.
+                        * (cos_sigma * (-1. + 2. * cos_2sigma_m.powi(2))
+                            - big_b / 6.
+                                * cos_2sigma_m
+                                * (-3. + 4. * sin_sigma.powi(2))
+                                * (-3. + 4. * cos_2sigma_m.powi(2))));
+            return Some(b * big_a * (sigma - delta_sigma));
+        }
+    }
+    None
+}
+
+fn geodesic(from: Coord, to: Coord) -> f64 {
+    WGS84.inverse(from.y, from.x, to.y, to.x)
+}
+
+#[test]
+fn test_distance_models() {
+    // Flinders Peak to Buninyong, from Vincenty's paper
+    let flinders = Coord {
+        x: 144.424_867_89,
+        y: -37.951_033_42,
+    };
+    let buninyong = Coord {
+        x: 143.926_495_54,
+        y: -37.652_821_14,
+    };
+    let expected = 54_972.271;
+    assert!((DistanceModel::Vincenty.distance(flinders, buninyong) - expected).abs() < 1e-3);
+    assert!((DistanceModel::Geodesic.distance(flinders, buninyong) - expected).abs() < 1e-3);
+    let haversine = DistanceModel::Haversine.distance(flinders, buninyong);
+    assert!((haversine - expected).abs() / expected < 0.005);
+
+    // Vincenty does not converge for nearly antipodal points
+    let a = Coord { x: 0., y: 0. };
+    let b = Coord { x: 179.7, y: 0.5 };
+    assert_eq!(None, vincenty(a, b));
+    assert_eq!(
+        DistanceModel::Geodesic.distance(a, b),
+        DistanceModel::Vincenty.distance(a, b)
+    );
+
+    assert_eq!(0., DistanceModel::Vincenty.distance(a, a));
+    assert_eq!(Ok(DistanceModel::Geodesic), "karney".parse());
+}
@@ -0,0 +1,534 @@
+//! Elevation from local digital elevation model (DEM) tiles.
+//!
+//! Reads SRTM `.hgt` tiles (1 or 3 arc-second, named after their south-west
+//! corner like `N45E006.hgt`) and single band GeoTIFF files in WGS84.
+//! Elevations are bilinearly interpolated between the nearest samples.
+//!
+//! https://wiki.openstreetmap.org/wiki/SRTM
+
+use super::distance::DistanceModel;
+use super::error::Error;
+use ahash::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+type Coord = geo_types::Coord<f64>;
+
+/// Distance in meters between two elevation samples along an edge.
+///
+/// Close to the resolution of 1 arc-second SRTM tiles; sampling more
+/// often would mostly measure the noise of the model.
+const SAMPLE_STEP: f64 = 30.;
+
+/// Elevation gain and grades along an edge.
+///
+/// Values are in the forward direction (from source to target): the ascent
+/// of the backward direction is the `descent`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Climb {
+    /// Total elevation gain in meters.
+    pub ascent: f64,
+    /// Total elevation loss in meters.
+    pub descent: f64,
+    /// Steepest uphill grade in percent, in the forward direction.
+    pub max_grade_forward: f64,
+    /// Steepest uphill grade in percent, in the backward direction.
+    pub max_grade_backward: f64,
+}
+
+impl Climb {
+    /// Swaps the forward and backward directions.
+    pub(crate) fn reverse(&mut self) {
+        std::mem::swap(&mut self.ascent, &mut self.descent);
+        std::mem::swap(&mut self.max_grade_forward, &mut self.max_grade_backward);
+    }
+
+    /// Appends the climb of a following edge.
+    pub(crate) fn concat(&mut self, other: &Climb) {
+        self.ascent += other.ascent;
+        self.descent += other.descent;
+        self.max_grade_forward = self.max_grade_forward.max(other.max_grade_forward);
+        self.max_grade_backward = self.max_grade_backward.max(other.max_grade_backward);
+    }
+}
+
+/// A regular grid of elevations.
+#[derive(Clone)]
+struct Tile {
+    /// Longitude of the center of the first column.
+    x0: f64,
+    /// Latitude of the center of the first row (northmost).
+    y0: f64,
+    /// Width of a pixel in degrees.
+    dx: f64,
+    /// Height of a pixel in degrees.
+    dy: f64,
+    width: usize,
+    height: usize,
+    /// Elevations in meters, row by row from the north, `NaN` when missing.
+    data: Vec<f32>,
+}
+
+impl Tile {
+    /// Reads the grid of an SRTM `.hgt` tile, without its elevations.
+    ///
+    /// The size (1201² or 3601² big-endian 16 bits samples) gives the resolution,
+    /// and the file name the south-west corner. Samples are on the edges of the
+    /// tile, so neighbouring tiles share a row or a column.
+    fn hgt_grid(path: &Path) -> Result<Tile, Error> {
+        let invalid = || Error::InvalidDem(path.display().to_string());
+        let name = path
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .ok_or_else(invalid)?;
+        let (lat, lon) = parse_hgt_name(name).ok_or_else(invalid)?;
+        let bytes = std::fs::metadata(path)?.len() as usize;
+        let size = ((bytes / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != bytes {
+            return Err(invalid());
+        }
+        let step = 1. / (size - 1) as f64;
+        Ok(Tile {
+            x0: lon,
+            y0: lat + 1.,
+            dx: step,
+            dy: step,
+            width: size,
+            height: size,
+            data: Vec::new(),
+        })
+    }
+
+    /// Reads an SRTM `.hgt` tile.
+    fn from_hgt(path: &Path) -> Result<Tile, Error> {
+        let mut tile = Tile::hgt_grid(path)?;
+        let bytes = std::fs::read(path)?;
+        if bytes.len() != tile.width * tile.height * 2 {
+            return Err(Error::InvalidDem(path.display().to_string()));
+        }
+        tile.data = bytes
+            .chunks_exact(2)
+            .map(|b| match i16::from_be_bytes([b[0], b[1]]) {
+                -32768 => f32::NAN,
+                ele => ele as f32,
+            })
+            .collect();
+        Ok(tile)
+    }
+
+    /// Reads the grid of a single band GeoTIFF in WGS84 coordinates, without its elevations.
+    ///
+    /// The grid is located with the `ModelTiepoint` and `ModelPixelScale` tags.
+    fn geotiff_grid(path: &Path) -> Result<Tile, Error> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let (tile, _) = Tile::geotiff_header(path, &mut tiff::decoder::Decoder::new(file)?)?;
+        Ok(tile)
+    }
+
+    /// Reads the grid and the GDAL no-data value of a GeoTIFF.
+    fn geotiff_header<R: std::io::Read + std::io::Seek>(
+        path: &Path,
+        decoder: &mut tiff::decoder::Decoder<R>,
+    ) -> Result<(Tile, Option<f32>), Error> {
+        use tiff::tags::Tag;
+
+        let invalid = || Error::InvalidDem(path.display().to_string());
+        let (width, height) = decoder.dimensions()?;
+        let scale = decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag)?;
+        let tiepoint = decoder.get_tag_f64_vec(Tag::ModelTiepointTag)?;
+        if scale.len() < 2 || tiepoint.len() < 6 {
+            return Err(invalid());
+        }
+        let nodata = decoder
+            .get_tag_ascii_string(Tag::GdalNodata)
+            .ok()
+            .and_then(|nodata| {
+                nodata
+                    .trim_matches(char::from(0))
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+            });
+        let (dx, dy) = (scale[0], scale[1]);
+        // Pixels are areas: the tie point is the corner of the pixel
+        let tile = Tile {
+            x0: tiepoint[3] - tiepoint[0] * dx + dx / 2.,
+            y0: tiepoint[4] + tiepoint[1] * dy - dy / 2.,
+            dx,
+            dy,
+            width: width as usize,
+            height: height as usize,
+            data: Vec::new(),
+        };
+        Ok((tile, nodata))
+    }
+
+    /// Reads a single band GeoTIFF in WGS84 coordinates.
+    ///
+    /// The GDAL no-data value is honored.
+    fn from_geotiff(path: &Path) -> Result<Tile, Error> {
+        use tiff::decoder::{Decoder, DecodingResult};
+
+        let invalid = || Error::InvalidDem(path.display().to_string());
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut decoder = Decoder::new(file)?;
+        let (mut tile, nodata) = Tile::geotiff_header(path, &mut decoder)?;
+        let data: Vec<f32> = match decoder.read_image()? {
+            DecodingResult::U8(v) => v.into_iter().map(f32::from).collect(),
+            DecodingResult::U16(v) => v.into_iter().map(f32::from).collect(),
+            DecodingResult::I8(v) => v.into_iter().map(f32::from).collect(),
+            DecodingResult::I16(v) => v.into_iter().map(f32::from).collect(),
+            DecodingResult::I32(v) => v.into_iter().map(|e| e as f32).collect(),
+            DecodingResult::U32(v) => v.into_iter().map(|e| e as f32).collect(),
+            DecodingResult::F32(v) => v,
+            DecodingResult::F64(v) => v.into_iter().map(|e| e as f32).collect(),
+            _ => return Err(invalid()),
+        };
+        if data.len() != tile.width * tile.height {
+            return Err(invalid());
+        }
+        tile.data = data
+            .into_iter()
+            .map(|ele| if Some(ele) == nodata { f32::NAN } else { ele })
+            .collect();
+        Ok(tile)
+    }
+
+    /// Cells of one degree overlapped by the tile, by their south-west corner `(lon, lat)`.
+    fn cells(&self) -> impl Iterator<Item = (i32, i32)> + use<> {
+        // Samples cover half a pixel around them
+        let west = (self.x0 - self.dx / 2.).floor() as i32;
+        let east = (self.x0 + (self.width as f64 - 0.5) * self.dx).floor() as i32;
+        let north = (self.y0 + self.dy / 2.).floor() as i32;
+        let south = (self.y0 - (self.height as f64 - 0.5) * self.dy).floor() as i32;
+        (west..=east).flat_map(move |lon| (south..=north).map(move |lat| (lon, lat)))
+    }
+
+    /// Interpolates the elevation at a coordinate.
+    ///
+    /// Returns `None` outside of the tile or when all the surrounding samples are missing.
+    fn elevation(&self, coord: Coord) -> Option<f64> {
+        let col = (coord.x - self.x0) / self.dx;
+        let row = (self.y0 - coord.y) / self.dy;
+        let (max_col, max_row) = ((self.width - 1) as f64, (self.height - 1) as f64);
+        if !(-0.5..=max_col + 0.5).contains(&col) || !(-0.5..=max_row + 0.5).contains(&row) {
+            return None;
+        }
+        let (col, row) = (col.clamp(0., max_col), row.clamp(0., max_row));
+        let (c0, r0) = (col.floor() as usize, row.floor() as usize);
+        let (c1, r1) = ((c0 + 1).min(self.width - 1), (r0 + 1).min(self.height - 1));
+        let (fc, fr) = (col - c0 as f64, row - r0 as f64);
+
+        let mut total = 0.;
+        let mut weights = 0.;
+        for (r, c, weight) in [
+            (r0, c0, (1. - fc) * (1. - fr)),
+            (r0, c1, fc * (1. - fr)),
+            (r1, c0, (1. - fc) * fr),
+            (r1, c1, fc * fr),
+        ] {
+            let ele = self.data[r * self.width + c];
+            if !ele.is_nan() && weight > 0. {
+                total += ele as f64 * weight;
+                weights += weight;
+            }
+        }
+        (weights > 0.).then(|| total / weights)
+    }
+}
+
+/// Lowercase extension of a file.
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+}
+
+/// Parses the south-west corner `(lat, lon)` from a tile name like `N45E006`.
+fn parse_hgt_name(name: &str) -> Option<(f64, f64)> {
+    let name = name.get(..7)?.to_ascii_uppercase();
+    let lat: f64 = name.get(1..3)?.parse().ok()?;
+    let lon: f64 = name.get(4..7)?.parse().ok()?;
+    let lat = match &name[0..1] {
+        "N" => lat,
+        "S" => -lat,
+        _ => return None,
+    };
+    let lon = match &name[3..4] {
+        "E" => lon,
+        "W" => -lon,
+        _ => return None,
+    };
+    Some((lat, lon))
+}
+
+/// Cell of one degree containing a coordinate, by its south-west corner `(lon, lat)`.
+fn cell(coord: Coord) -> (i32, i32) {
+    (coord.x.floor() as i32, coord.y.floor() as i32)
+}
+
+/// A digital elevation model made of local tiles.
+///
+/// Tiles are indexed by the cells of one degree they overlap, and only read
+/// the first time a coordinate of one of their cells is looked up.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::Dem;
+///
+/// let dem = Dem::open("srtm/").unwrap();
+/// let ele = dem.elevation(geo_types::Coord { x: 6.86, y: 45.83 }).unwrap();
+/// ```
+#[derive(Default)]
+pub struct Dem {
+    /// Files of the tiles, in alphabetical order.
+    paths: Vec<PathBuf>,
+    /// Tiles read so far, by index in `paths`, or why the file could not be read.
+    tiles: Vec<OnceLock<Result<Tile, String>>>,
+    /// Indices of the tiles overlapping each cell of one degree.
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Dem {
+    /// Opens a tile, or all the `.hgt`, `.tif` and `.tiff` tiles of a directory.
+    ///
+    /// Only the size and the position of the tiles are read; their elevations
+    /// are read when needed.
+    ///
+    /// # Errors
+    /// Returns an error if a file cannot be read or is not a valid tile.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Dem, Error> {
+        let path = path.as_ref();
+        let mut paths = if path.is_dir() {
+            std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        paths.sort();
+
+        let mut dem = Dem::default();
+        for path in paths {
+            let grid = match extension(&path).as_deref() {
+                Some("hgt") => Tile::hgt_grid(&path)?,
+                Some("tif" | "tiff") => Tile::geotiff_grid(&path)?,
+                _ => continue,
+            };
+            dem.push(path, &grid, OnceLock::new());
+        }
+        Ok(dem)
+    }
+
+    /// Adds a tile, read or not, overlapping the cells of `grid`.
+    fn push(&mut self, path: PathBuf, grid: &Tile, tile: OnceLock<Result<Tile, String>>) {
+        for cell in grid.cells() {
+            self.cells.entry(cell).or_default().push(self.paths.len());
+        }
+        self.paths.push(path);
+        self.tiles.push(tile);
+    }
+
+    /// Returns the tile at an index, reading it the first time.
+    fn tile(&self, index: usize) -> Result<&Tile, Error> {
+        let path = &self.paths[index];
+        self.tiles[index]
+            .get_or_init(|| {
+                match extension(path).as_deref() {
+                    Some("hgt") => Tile::from_hgt(path),
+                    _ => Tile::from_geotiff(path),
+                }
+                .map_err(|e| e.to_string())
+            })
+            .as_ref()
+            .map_err(|e| Error::InvalidDem(format!("{} ({})", path.display(), e)))
+    }
+
+    /// Returns the elevation in meters at a coordinate, if covered by a tile.
+    ///
+    /// # Errors
+    /// Returns an error if a tile covering the coordinate cannot be read.
+    pub fn elevation(&self, coord: Coord) -> Result<Option<f64>, Error> {
+        for &index in self.cells.get(&cell(coord)).into_iter().flatten() {
+            if let Some(ele) = self.tile(index)?.elevation(coord) {
+                return Ok(Some(ele));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Computes the ascent, descent and max grades along a geometry.
+    ///
+    /// The geometry is sampled at regular intervals of about 30 meters,
+    /// measured with `distance_model` like the length of the edge.
+    /// Returns `None` if fewer than two samples have an elevation.
+    ///
+    /// # Errors
+    /// Returns an error if a tile covering the geometry cannot be read.
+    pub fn climb(
+        &self,
+        geometry: &[Coord],
+        distance_model: DistanceModel,
+    ) -> Result<Option<Climb>, Error> {
+        let distances: Vec<f64> = std::iter::once(0.)
+            .chain(geometry.windows(2).scan(0., |total, coords| {
+                *total += distance_model.distance(coords[0], coords[1]);
+                Some(*total)
+            }))
+            .collect();
+        let Some(&length) = distances.last() else {
+            return Ok(None);
+        };
+        let steps = ((length / SAMPLE_STEP).ceil() as usize).max(1);
+
+        let mut samples = Vec::with_capacity(steps + 1);
+        let mut segment = 0;
+        for i in 0..=steps {
+            let at = length * i as f64 / steps as f64;
+            while segment + 2 < distances.len() && distances[segment + 1] < at {
+                segment += 1;
+            }
+            let coord = match geometry.get(segment + 1) {
+                Some(&end) => {
+                    let start = geometry[segment];
+                    let span = distances[segment + 1] - distances[segment];
+                    let t = if span > 0. {
+                        (at - distances[segment]) / span
+                    } else {
+                        0.
+                    };
+                    start + (end - start) * t.clamp(0., 1.)
+                }
+                None => geometry[segment],
+            };
+            if let Some(ele) = self.elevation(coord)? {
+                samples.push((at, ele));
+            }
+        }
+
+        if samples.len() < 2 {
+            return Ok(None);
+        }
+        let mut climb = Climb::default();
+        for pair in samples.windows(2) {
+            let ((d0, e0), (d1, e1)) = (pair[0], pair[1]);
+            let rise = e1 - e0;
+            if rise > 0. {
+                climb.ascent += rise;
+            } else {
+                climb.descent -= rise;
+            }
+            if d1 > d0 {
+                let grade = 100. * rise / (d1 - d0);
+                climb.max_grade_forward = climb.max_grade_forward.max(grade);
+                climb.max_grade_backward = climb.max_grade_backward.max(-grade);
+            }
+        }
+        Ok(Some(climb))
+    }
+}
+
+#[cfg(test)]
+fn slope_tile() -> Tile {
+    // 11×11 samples of 0.001°, rising by 1 m per column towards the east
+    Tile {
+        x0: 0.,
+        y0: 0.01,
+        dx: 0.001,
+        dy: 0.001,
+        width: 11,
+        height: 11,
+        data: (0..121).map(|i| (i % 11) as f32).collect(),
+    }
+}
+
+#[test]
+fn test_parse_hgt_name() {
+    assert_eq!(Some((45., 6.)), parse_hgt_name("N45E006"));
+    assert_eq!(Some((-12., -77.)), parse_hgt_name("s12w077.SRTMGL1"));
+    assert_eq!(None, parse_hgt_name("dem"));
+}
+
+#[test]
+fn test_interpolate() {
+    let mut tile = slope_tile();
+    assert_eq!(Some(0.), tile.elevation(Coord { x: 0., y: 0. }));
+    assert!(
+        (tile
+            .elevation(Coord {
+                x: 0.0025,
+                y: 0.005
+            })
+            .unwrap()
+            - 2.5)
+            .abs()
+            < 1e-9
+    );
+    assert_eq!(None, tile.elevation(Coord { x: 0.02, y: 0.005 }));
+
+    tile.data[3] = f32::NAN;
+    let ele = tile
+        .elevation(Coord {
+            x: 0.0025,
+            y: 0.0095,
+        })
+        .unwrap();
+    assert!((ele - 7. / 3.).abs() < 1e-9);
+}
+
+#[test]
+fn test_climb() {
+    let mut dem = Dem::default();
+    let tile = slope_tile();
+    dem.push(PathBuf::new(), &tile, OnceLock::from(Ok(tile.clone())));
+    let east = [Coord { x: 0., y: 0.005 }, Coord { x: 0.01, y: 0.005 }];
+    let climb = dem.climb(&east, DistanceModel::Haversine).unwrap().unwrap();
+    assert!((climb.ascent - 10.).abs() < 1e-6);
+    assert!(climb.descent.abs() < 1e-6);
+    // 1 m every 0.001° of longitude (≈111 m)
+    assert!((climb.max_grade_forward - 0.9).abs() < 0.01);
+    assert_eq!(0., climb.max_grade_backward);
+
+    let mut reversed = climb;
+    reversed.reverse();
+    assert_eq!(climb.ascent, reversed.descent);
+    assert_eq!(climb.max_grade_forward, reversed.max_grade_backward);
+
+    let outside = [Coord { x: 1., y: 1. }, Coord { x: 1.01, y: 1. }];
+    assert_eq!(None, dem.climb(&outside, DistanceModel::Haversine).unwrap());
+
+    // Grades follow the distance model of the edge
+    let geodesic = dem.climb(&east, DistanceModel::Geodesic).unwrap().unwrap();
+    assert_ne!(climb.max_grade_forward, geodesic.max_grade_forward);
+}
+
+#[test]
+fn test_read_hgt() {
+    let dir = std::env::temp_dir().join(format!("osm4routing_hgt_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("N00E000.hgt");
+    let samples: Vec<u8> = (0..3 * 3)
+        .flat_map(|i: i16| (if i == 4 { -32768 } else { i * 10 }).to_be_bytes())
+        .collect();
+    std::fs::write(&path, samples).unwrap();
+    std::fs::write(dir.join("N01E000.hgt"), [0u8; 3]).unwrap();
+    assert!(Dem::open(&dir).is_err());
+    std::fs::remove_file(dir.join("N01E000.hgt")).unwrap();
+
+    let dem = Dem::open(&dir).unwrap();
+    assert!(dem.tiles.iter().all(|tile| tile.get().is_none()));
+    // The northern and eastern edges are shared with the neighbouring cells
+    assert_eq!(Some(20.), dem.elevation(Coord { x: 1., y: 1. }).unwrap());
+    assert_eq!(Some(60.), dem.elevation(Coord { x: 0., y: 0. }).unwrap());
+    assert_eq!(None, dem.elevation(Coord { x: 2.5, y: 0.5 }).unwrap());
+    let tile = dem.tile(0).unwrap();
+
+    // A tile that cannot be read when it is needed is an error
+    let dem = Dem::open(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(dem.elevation(Coord { x: 0.5, y: 0.5 }).is_err());
+
+    assert_eq!((3, 3), (tile.width, tile.height));
+    assert!(tile.data[4].is_nan());
+}
@@ -14,6 +14,18 @@ pub enum Error {
     Csv(csv::Error),
     /// A node referenced in a way was not found in the data.
     MissingNode(NodeId),
+    /// An error occurred while reading a GeoTIFF elevation tile.
+    Tiff(tiff::TiffError),
+    /// An elevation tile has an unexpected name, size or layout.
+    InvalidDem(String),
+    /// An error occurred while reading GeoJSON.
+    Json(serde_json::Error),
+    /// A file of locations is not a CSV with `lon` and `lat` columns or GeoJSON points.
+    InvalidLocations(String),
+    /// A GPS trace is not valid GPX or CSV.
+    InvalidTrace(String),
+    /// A coordinate reference system is invalid or a coordinate cannot be reprojected.
+    Projection(proj4rs::errors::Error),
 }
 
 impl fmt::Display for Error {
@@ -22,6 +34,12 @@ impl fmt::Display for Error {
             Error::Io(e) => write!(f, "I/O error: {}", e),
             Error::Csv(e) => write!(f, "CSV error: {}", e),
             Error::MissingNode(id) => write!(f, "Missing node with id: {}", id.0),
+            Error::Tiff(e) => write!(f, "GeoTIFF error: {}", e),
+            Error::InvalidDem(path) => write!(f, "Invalid elevation tile: {}", path),
+            Error::Json(e) => write!(f, "JSON error: {}", e),
+            Error::InvalidLocations(reason) => write!(f, "Invalid locations: {}", reason),
+            Error::InvalidTrace(reason) => write!(f, "Invalid trace: {}", reason),
+            Error::Projection(e) => write!(f, "Projection error: {}", e),
         }
     }
 }
@@ -31,7 +49,13 @@ impl std::error::Error for Error {
         match self {
             Error::Io(e) => Some(e),
             Error::Csv(e) => Some(e),
-            Error::MissingNode(_) => None,
+            Error::Tiff(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Projection(e) => Some(e),
+            Error::MissingNode(_)
+            | Error::InvalidDem(_)
+            | Error::InvalidLocations(_)
+            | Error::InvalidTrace(_) => None,
         }
     }
 }
@@ -47,3 +71,21 @@ impl From<csv::Error> for Error {
         Error::Csv(e)
     }
 }
+
+impl From<tiff::TiffError> for Error {
+    fn from(e: tiff::TiffError) -> Self {
+        Error::Tiff(e)
+    }
+}
+
+impl From<proj4rs::errors::Error> for Error {
+    fn from(e: proj4rs::errors::Error) -> Self {
+        Error::Projection(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
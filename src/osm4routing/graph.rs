@@ -0,0 +1,326 @@
+//! Routing graph with adjacency queries.
+//!
+//! [`Graph`] owns the nodes and edges extracted by the [`Reader`](super::reader::Reader)
+//! and indexes them with dense integer indices. For every transportation mode,
+//! it stores the outgoing and incoming arcs of each node, an arc being an edge
+//! traversed in one direction.
+
+use super::categorize::{Direction, Mode};
+use super::models::*;
+use ahash::HashMap;
+
+type Coord = geo_types::Coord<f64>;
+
+/// An edge traversed in a given direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Arc {
+    /// Index of the edge in [`Graph::edges`].
+    pub edge: usize,
+    /// Direction in which the edge is traversed.
+    pub direction: Direction,
+    /// Index of the node at the other end of the arc: the head of an
+    /// outgoing arc, or the tail of an incoming arc.
+    pub node: usize,
+}
+
+/// Arcs of every node, stored contiguously.
+#[derive(Clone, Debug, Default)]
+struct Adjacency {
+    /// Arcs of node `i` are `arcs[offsets[i]..offsets[i + 1]]`.
+    offsets: Vec<usize>,
+    arcs: Vec<Arc>,
+}
+
+impl Adjacency {
+    /// Groups `(node, arc)` pairs by node.
+    fn new(nodes_count: usize, mut pairs: Vec<(usize, Arc)>) -> Adjacency {
+        pairs.sort_by_key(|(node, arc)| (*node, arc.edge));
+        let mut offsets = vec![0; nodes_count + 1];
+        for (node, _) in &pairs {
+            offsets[node + 1] += 1;
+        }
+        for i in 0..nodes_count {
+            offsets[i + 1] += offsets[i];
+        }
+        Adjacency {
+            offsets,
+            arcs: pairs.into_iter().map(|(_, arc)| arc).collect(),
+        }
+    }
+
+    fn of(&self, node: usize) -> &[Arc] {
+        &self.arcs[self.offsets[node]..self.offsets[node + 1]]
+    }
+}
+
+/// A routing graph.
+///
+/// Nodes and edges are addressed by their index in [`Graph::nodes`] and
+/// [`Graph::edges`]. Edges whose source or target is not in the node list
+/// are kept but have no arc.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{Mode, Reader};
+///
+/// let graph = Reader::new().read_graph("map.osm.pbf").unwrap();
+/// let node = graph.node_by_osm_id(osm4routing::NodeId(42)).unwrap();
+/// for neighbor in graph.neighbors(node, Mode::Car) {
+///     println!("Can drive to {}", graph.nodes[neighbor].id.0);
+/// }
+///
+/// // The tuple returned by `Reader::read`
+/// let (nodes, edges) = graph.into();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    /// The nodes of the graph.
+    pub nodes: Vec<Node>,
+    /// The edges of the graph.
+    pub edges: Vec<Edge>,
+    /// Index of each node by OSM id.
+    node_index: HashMap<NodeId, usize>,
+    /// Index of the source and target of each edge.
+    ends: Vec<Option<(usize, usize)>>,
+    /// Outgoing arcs, by mode in the order of [`Mode::ALL`].
+    outgoing: [Adjacency; 4],
+    /// Incoming arcs, by mode in the order of [`Mode::ALL`].
+    incoming: [Adjacency; 4],
+}
+
+impl Graph {
+    /// Builds the graph and its adjacency from nodes and edges.
+    pub fn new(nodes: Vec<Node>, edges: Vec<Edge>) -> Graph {
+        let node_index: HashMap<NodeId, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id, i))
+            .collect();
+        let ends: Vec<_> = edges
+            .iter()
+            .map(|edge| {
+                Some((
+                    *node_index.get(&edge.source)?,
+                    *node_index.get(&edge.target)?,
+                ))
+            })
+            .collect();
+
+        let adjacency = |mode: Mode| {
+            let mut outgoing = Vec::new();
+            let mut incoming = Vec::new();
+            for (edge, (properties, ends)) in edges
+                .iter()
+                .map(|edge| edge.properties)
+                .zip(&ends)
+                .enumerate()
+            {
+                let Some((source, target)) = *ends else {
+                    continue;
+                };
+                for direction in [Direction::Forward, Direction::Backward] {
+                    if !properties.allows(mode, direction) {
+                        continue;
+                    }
+                    let (tail, head) = match direction {
+                        Direction::Forward => (source, target),
+                        Direction::Backward => (target, source),
+                    };
+                    outgoing.push((
+                        tail,
+                        Arc {
+                            edge,
+                            direction,
+                            node: head,
+                        },
+                    ));
+                    incoming.push((
+                        head,
+                        Arc {
+                            edge,
+                            direction,
+                            node: tail,
+                        },
+                    ));
+                }
+            }
+            (
+                Adjacency::new(nodes.len(), outgoing),
+                Adjacency::new(nodes.len(), incoming),
+            )
+        };
+        let [foot, car, bike, train] = Mode::ALL.map(adjacency);
+
+        Graph {
+            node_index,
+            ends,
+            outgoing: [foot.0, car.0, bike.0, train.0],
+            incoming: [foot.1, car.1, bike.1, train.1],
+            nodes,
+            edges,
+        }
+    }
+
+    /// Returns the index of a node from its OSM id.
+    pub fn node_by_osm_id(&self, id: NodeId) -> Option<usize> {
+        self.node_index.get(&id).copied()
+    }
+
+    /// Returns the indices of the source and target nodes of an edge.
+    ///
+    /// Returns `None` if one of them is not in the graph.
+    pub fn ends(&self, edge: usize) -> Option<(usize, usize)> {
+        self.ends[edge]
+    }
+
+    /// Returns the node an arc leaves from.
+    ///
+    /// # Panics
+    /// Panics if the arc is not from this graph.
+    pub fn tail(&self, arc: &Arc) -> usize {
+        let (source, target) = self.ends[arc.edge].expect("arcs have known ends");
+        match arc.direction {
+            Direction::Forward => source,
+            Direction::Backward => target,
+        }
+    }
+
+    /// Merges the geometries of consecutive arcs starting at a node.
+    pub fn merge_geometry(&self, source: usize, arcs: &[Arc]) -> Vec<Coord> {
+        let mut geometry = vec![self.nodes[source].coord];
+        for arc in arcs {
+            let edge = &self.edges[arc.edge];
+            match arc.direction {
+                Direction::Forward => geometry.extend(edge.geometry.iter().skip(1)),
+                Direction::Backward => geometry.extend(edge.geometry.iter().rev().skip(1)),
+            }
+        }
+        geometry
+    }
+
+    /// Returns the arcs leaving a node for a mode.
+    pub fn outgoing(&self, node: usize, mode: Mode) -> &[Arc] {
+        self.outgoing[mode.index()].of(node)
+    }
+
+    /// Returns the arcs arriving at a node for a mode.
+    pub fn incoming(&self, node: usize, mode: Mode) -> &[Arc] {
+        self.incoming[mode.index()].of(node)
+    }
+
+    /// Returns the indices of all the edges touching a node, whatever the mode.
+    ///
+    /// Each edge is returned once, even if it is a loop.
+    pub fn edges_of_node(&self, node: usize) -> Vec<usize> {
+        let mut edges: Vec<usize> = self
+            .outgoing
+            .iter()
+            .chain(&self.incoming)
+            .flat_map(|adjacency| adjacency.of(node))
+            .map(|arc| arc.edge)
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    }
+
+    /// Returns the nodes that can be reached from a node through one edge with a mode.
+    ///
+    /// A neighbor linked by multiple edges is returned once.
+    pub fn neighbors(&self, node: usize, mode: Mode) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self
+            .outgoing(node, mode)
+            .iter()
+            .map(|arc| arc.node)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    /// Returns the nodes and edges, as returned by [`Reader::read`](super::reader::Reader::read).
+    pub fn into_parts(self) -> (Vec<Node>, Vec<Edge>) {
+        (self.nodes, self.edges)
+    }
+}
+
+impl From<Graph> for (Vec<Node>, Vec<Edge>) {
+    fn from(graph: Graph) -> Self {
+        graph.into_parts()
+    }
+}
+
+#[cfg(test)]
+fn test_graph() -> Graph {
+    use super::categorize::EdgeProperties;
+
+    let properties = |tags: &[(&str, &str)]| {
+        let mut p = EdgeProperties::default();
+        for (key, val) in tags {
+            p.update_with_str(key, val);
+        }
+        p.normalize();
+        p
+    };
+    let node = |id| Node {
+        id: NodeId(id),
+        ..Default::default()
+    };
+    let edge = |id: &str, source, target, properties| Edge {
+        composite_id: id.to_string(),
+        source: NodeId(source),
+        target: NodeId(target),
+        properties,
+        ..Default::default()
+    };
+    let street = properties(&[("highway", "residential")]);
+    let oneway = properties(&[("highway", "residential"), ("oneway", "yes")]);
+    let footway = properties(&[("highway", "footway")]);
+    Graph::new(
+        vec![node(10), node(20), node(30)],
+        vec![
+            edge("a", 10, 20, street),
+            edge("b", 20, 30, oneway),
+            edge("c", 30, 10, footway),
+            edge("d", 30, 40, street),
+        ],
+    )
+}
+
+#[test]
+fn test_adjacency() {
+    let g = test_graph();
+    let n = |id| g.node_by_osm_id(NodeId(id)).unwrap();
+    assert_eq!(None, g.node_by_osm_id(NodeId(40)));
+    assert_eq!(None, g.ends(3));
+
+    assert_eq!(vec![n(20), n(30)], g.neighbors(n(10), Mode::Foot));
+    assert_eq!(vec![n(20)], g.neighbors(n(10), Mode::Car));
+    assert_eq!(vec![n(10), n(30)], g.neighbors(n(20), Mode::Car));
+    assert_eq!(Vec::<usize>::new(), g.neighbors(n(30), Mode::Car));
+
+    let incoming = g.incoming(n(30), Mode::Car);
+    assert_eq!(1, incoming.len());
+    assert_eq!(
+        Arc {
+            edge: 1,
+            direction: Direction::Forward,
+            node: n(20)
+        },
+        incoming[0]
+    );
+    assert_eq!(
+        Direction::Backward,
+        g.outgoing(n(10), Mode::Foot)[1].direction
+    );
+
+    assert_eq!(vec![1, 2], g.edges_of_node(n(30)));
+}
+
+#[test]
+fn test_into_parts() {
+    let (nodes, edges) = test_graph().into();
+    assert_eq!(3, nodes.len());
+    assert_eq!(4, edges.len());
+}
@@ -0,0 +1,276 @@
+//! Isochrones: everything reachable within a budget.
+//!
+//! A bounded Dijkstra runs from one or more seeds. Edges are reached entirely
+//! when the budget allows crossing them, or partially, cut at the point where
+//! the budget runs out assuming a constant cost along the edge.
+
+use super::categorize::Direction;
+use super::graph::{Arc, Graph};
+use super::matrix::Snapper;
+use super::routing::{Router, State};
+use geo::ConcaveHull;
+use geo::concave_hull::ConcaveHullOptions;
+use geo_types::{MultiPoint, Polygon};
+use serde_json::{Value, json};
+use std::collections::BinaryHeap;
+
+type Coord = geo_types::Coord<f64>;
+
+/// An edge reached by an isochrone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReachedEdge {
+    /// Index of the edge in [`Graph::edges`].
+    pub edge: usize,
+    /// Whether the whole edge is reachable.
+    pub complete: bool,
+    /// Reachable parts of the geometry: the whole geometry, or the parts
+    /// reached from the source and from the target.
+    pub parts: Vec<Vec<Coord>>,
+}
+
+/// Nodes and edges reachable within a budget.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{Isochrone, Mode, Router, Weight};
+///
+/// let graph = osm4routing::read_graph("city.osm.pbf").unwrap();
+/// let router = Router::new(&graph, Mode::Foot, Weight::TravelTime);
+/// let here = geo_types::Coord { x: 2.3522, y: 48.8566 };
+/// let isochrone = Isochrone::new(&router, &[here], 15. * 60.).with_hull(2.);
+/// osm4routing::writers::isochrone_geojson(&graph, &isochrone, "15min.geojson").unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Isochrone {
+    /// The budget, in the unit of the weight.
+    pub budget: f64,
+    /// Reached nodes, with their cost from the nearest seed.
+    pub nodes: Vec<(usize, f64)>,
+    /// Edges reached entirely or partially.
+    pub edges: Vec<ReachedEdge>,
+    /// Concave hull of the reached geometries, see [`Isochrone::with_hull`].
+    pub hull: Option<Polygon<f64>>,
+}
+
+impl Isochrone {
+    /// Computes the isochrone from seed coordinates, snapped to the nearest nodes usable by the mode.
+    pub fn new(router: &Router, seeds: &[Coord], budget: f64) -> Isochrone {
+        let snapper = Snapper::new(router.graph(), router.mode());
+        let seeds: Vec<usize> = seeds
+            .iter()
+            .filter_map(|seed| snapper.snap(*seed))
+            .map(|snap| snap.node)
+            .collect();
+        Isochrone::from_nodes(router, &seeds, budget)
+    }
+
+    /// Computes the isochrone from seed nodes, given by their index in [`Graph::nodes`].
+    pub fn from_nodes(router: &Router, seeds: &[usize], budget: f64) -> Isochrone {
+        let graph = router.graph();
+        let costs = bounded_search(router, seeds, budget);
+
+        let nodes = costs
+            .iter()
+            .enumerate()
+            .filter(|(_, cost)| cost.is_finite())
+            .map(|(node, cost)| (node, *cost))
+            .collect();
+
+        let mut edges = Vec::new();
+        for (edge, ends) in (0..graph.edges.len()).filter_map(|e| Some((e, graph.ends(e)?))) {
+            let (source, target) = ends;
+            let length = graph.edges[edge].length();
+            let geometry = |start, end| graph.edges[edge].slice(start, end);
+            // Distance reached along the edge from each end
+            let reach = |from: usize, direction| {
+                let remaining = budget - costs[from];
+                let cost = router.cost(&Arc {
+                    edge,
+                    direction,
+                    node: from,
+                });
+                if remaining < 0. || !cost.is_finite() {
+                    0.
+                } else if cost <= remaining {
+                    length
+                } else {
+                    length * remaining / cost
+                }
+            };
+            let forward = reach(source, Direction::Forward);
+            let backward = reach(target, Direction::Backward);
+            if forward <= 0. && backward <= 0. {
+                continue;
+            }
+            let (complete, parts) = if forward + backward >= length {
+                (true, vec![graph.edges[edge].geometry.clone()])
+            } else {
+                let parts = [
+                    (forward > 0.).then(|| geometry(0., forward)),
+                    (backward > 0.).then(|| geometry(length - backward, length)),
+                ];
+                (false, parts.into_iter().flatten().collect())
+            };
+            edges.push(ReachedEdge {
+                edge,
+                complete,
+                parts,
+            });
+        }
+
+        Isochrone {
+            budget,
+            nodes,
+            edges,
+            hull: None,
+        }
+    }
+
+    /// Computes the concave hull of the reached geometries.
+    ///
+    /// Lower concavity gives a more detailed outline, infinity gives the convex hull;
+    /// 2 is a good start.
+    pub fn with_hull(mut self, concavity: f64) -> Self {
+        let points: MultiPoint<f64> = self
+            .edges
+            .iter()
+            .flat_map(|edge| edge.parts.iter().flatten())
+            .map(|coord| geo_types::Point(*coord))
+            .collect();
+        self.hull = (!points.0.is_empty()).then(|| {
+            points.concave_hull_with_options(ConcaveHullOptions {
+                concavity,
+                length_threshold: 0.,
+            })
+        });
+        self
+    }
+
+    /// Returns the isochrone as a GeoJSON `FeatureCollection`.
+    ///
+    /// Reached nodes are points with their `id` and `cost`, reached edges are
+    /// line strings (or multi line strings when reached from both ends) with
+    /// their `id`, `osm_id` and `complete` flag, and the hull is a polygon with the `budget`.
+    pub fn to_geojson(&self, graph: &Graph) -> Value {
+        let position = |coord: &Coord| json!([coord.x, coord.y]);
+        let line = |coords: &Vec<Coord>| Value::Array(coords.iter().map(position).collect());
+
+        let mut features: Vec<Value> = self
+            .nodes
+            .iter()
+            .map(|(node, cost)| {
+                let node = &graph.nodes[*node];
+                json!({
+                    "type": "Feature",
+                    "properties": {"id": node.id.0, "cost": cost},
+                    "geometry": {"type": "Point", "coordinates": position(&node.coord)},
+                })
+            })
+            .collect();
+        features.extend(self.edges.iter().map(|reached| {
+            let edge = &graph.edges[reached.edge];
+            let geometry = match reached.parts.as_slice() {
+                [part] => json!({"type": "LineString", "coordinates": line(part)}),
+                parts => json!({
+                    "type": "MultiLineString",
+                    "coordinates": parts.iter().map(line).collect::<Vec<_>>(),
+                }),
+            };
+            json!({
+                "type": "Feature",
+                "properties": {
+                    "id": edge.id,
+                    "osm_id": edge.osm_id.0,
+                    "complete": reached.complete,
+                },
+                "geometry": geometry,
+            })
+        }));
+        if let Some(hull) = &self.hull {
+            let rings = std::iter::once(hull.exterior())
+                .chain(hull.interiors())
+                .map(|ring| line(&ring.0))
+                .collect::<Vec<_>>();
+            features.push(json!({
+                "type": "Feature",
+                "properties": {"budget": self.budget},
+                "geometry": {"type": "Polygon", "coordinates": rings},
+            }));
+        }
+        json!({"type": "FeatureCollection", "features": features})
+    }
+}
+
+/// Dijkstra from several seeds, returning the cost of each node, infinite beyond the budget.
+fn bounded_search(router: &Router, seeds: &[usize], budget: f64) -> Vec<f64> {
+    let graph = router.graph();
+    let mut costs = vec![f64::INFINITY; graph.nodes.len()];
+    let mut queue = BinaryHeap::new();
+    for &seed in seeds {
+        costs[seed] = 0.;
+        queue.push(State {
+            key: 0.,
+            node: seed,
+        });
+    }
+    while let Some(State { key, node }) = queue.pop() {
+        if key > costs[node] {
+            continue;
+        }
+        for arc in graph.outgoing(node, router.mode()) {
+            let cost = key + router.cost(arc);
+            if cost <= budget && cost < costs[arc.node] {
+                costs[arc.node] = cost;
+                queue.push(State {
+                    key: cost,
+                    node: arc.node,
+                });
+            }
+        }
+    }
+    costs
+}
+
+#[test]
+fn test_isochrone() {
+    use super::categorize::Mode;
+    use super::routing::{Weight, test_graph};
+
+    let graph = test_graph();
+    let router = Router::new(&graph, Mode::Car, Weight::Length);
+    let length = graph.edges[0].length();
+
+    // Half of the street from node 1 (index 0)
+    let isochrone = Isochrone::from_nodes(&router, &[0], length / 2.);
+    assert_eq!(vec![(0, 0.)], isochrone.nodes);
+    assert_eq!(1, isochrone.edges.len());
+    let reached = &isochrone.edges[0];
+    assert!(!reached.complete);
+    assert_eq!(1, reached.parts.len());
+    assert!((reached.parts[0][1].x - 0.005).abs() < 1e-9);
+
+    // Cars cannot leave node 3, which is a seed
+    let isochrone = Isochrone::from_nodes(&router, &[0, 2], length * 2.1);
+    assert_eq!(3, isochrone.nodes.len());
+    assert!(isochrone.edges.iter().all(|reached| reached.complete));
+    assert_eq!(
+        vec![0, 1],
+        isochrone.edges.iter().map(|r| r.edge).collect::<Vec<_>>()
+    );
+
+    // Pedestrians can walk the one-way street and the footway from node 3
+    let router = Router::new(&graph, Mode::Foot, Weight::Length);
+    let isochrone = Isochrone::new(&router, &[Coord { x: 0.021, y: 0. }], length / 2.);
+    let partial: Vec<_> = isochrone.edges.iter().map(|r| r.edge).collect();
+    assert_eq!(vec![1, 3], partial);
+
+    let isochrone = Isochrone::from_nodes(&router, &[0], length * 0.75).with_hull(2.);
+    let geojson = isochrone.to_geojson(&graph);
+    let features = geojson["features"].as_array().unwrap();
+    assert_eq!(
+        Some("Polygon"),
+        features.last().unwrap()["geometry"]["type"].as_str()
+    );
+    assert_eq!(Some(false), features[1]["properties"]["complete"].as_bool());
+}
@@ -0,0 +1,292 @@
+//! Lane count, width and turn lanes.
+//!
+//! Parses `lanes`, `lanes:forward`, `lanes:backward`, `width` and the
+//! `turn:lanes` family of tags into typed per-direction values. The total
+//! `lanes` count is split between the directions open to cars in
+//! [`EdgeProperties`].
+//!
+//! http://wiki.openstreetmap.org/wiki/Key:lanes
+
+use super::categorize::*;
+use serde::Serialize;
+
+/// A turn indication painted on a lane.
+///
+/// http://wiki.openstreetmap.org/wiki/Key:turn
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, Hash)]
+pub enum Turn {
+    /// No indication.
+    None,
+    /// `through`
+    Through,
+    /// `left`
+    Left,
+    /// `slight_left`
+    SlightLeft,
+    /// `sharp_left`
+    SharpLeft,
+    /// `right`
+    Right,
+    /// `slight_right`
+    SlightRight,
+    /// `sharp_right`
+    SharpRight,
+    /// `reverse`
+    Reverse,
+    /// `merge_to_left`
+    MergeToLeft,
+    /// `merge_to_right`
+    MergeToRight,
+}
+
+impl Turn {
+    /// Parses one value of a `turn:lanes` lane. Unknown values become `None`.
+    pub fn from_tag(val: &str) -> Turn {
+        match val.trim() {
+            "through" => Turn::Through,
+            "left" => Turn::Left,
+            "slight_left" => Turn::SlightLeft,
+            "sharp_left" => Turn::SharpLeft,
+            "right" => Turn::Right,
+            "slight_right" => Turn::SlightRight,
+            "sharp_right" => Turn::SharpRight,
+            "reverse" => Turn::Reverse,
+            "merge_to_left" => Turn::MergeToLeft,
+            "merge_to_right" => Turn::MergeToRight,
+            _ => Turn::None,
+        }
+    }
+
+    /// Returns the OSM value of the turn indication.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Turn::None => "none",
+            Turn::Through => "through",
+            Turn::Left => "left",
+            Turn::SlightLeft => "slight_left",
+            Turn::SharpLeft => "sharp_left",
+            Turn::Right => "right",
+            Turn::SlightRight => "slight_right",
+            Turn::SharpRight => "sharp_right",
+            Turn::Reverse => "reverse",
+            Turn::MergeToLeft => "merge_to_left",
+            Turn::MergeToRight => "merge_to_right",
+        }
+    }
+}
+
+/// Parses a `turn:lanes` value: lanes from left to right separated by `|`,
+/// each lane having one or more indications separated by `;`.
+fn parse_turn_lanes(val: &str) -> Vec<Vec<Turn>> {
+    val.split('|')
+        .map(|lane| lane.split(';').map(Turn::from_tag).collect())
+        .collect()
+}
+
+/// Formats turn lanes back into the `turn:lanes` notation.
+///
+/// Returns an empty string if there is no turn indication.
+pub fn format_turn_lanes(lanes: &[Vec<Turn>]) -> String {
+    lanes
+        .iter()
+        .map(|lane| {
+            lane.iter()
+                .map(|turn| turn.as_str())
+                .collect::<Vec<_>>()
+                .join(";")
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Parses a `width` value into meters.
+///
+/// Accepts plain numbers, numbers followed by `m`, and feet (`12'`).
+fn parse_width(val: &str) -> Option<f64> {
+    let val = val.trim();
+    if let Some(feet) = val.strip_suffix('\'') {
+        return feet.trim().parse::<f64>().ok().map(|feet| feet * 0.3048);
+    }
+    val.trim_end_matches('m').trim().parse().ok()
+}
+
+/// Lanes of an edge, per direction.
+///
+/// Built tag by tag with [`Lanes::update_with_str`], then completed with
+/// defaults by [`Lanes::normalize`] once the edge properties are known.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lanes {
+    /// Number of lanes in the forward direction.
+    pub forward: u8,
+    /// Number of lanes in the backward direction.
+    pub backward: u8,
+    /// Width of the carriageway in meters, if tagged.
+    pub width: Option<f64>,
+    /// Turn indications of the forward lanes, from left to right.
+    pub turn_forward: Vec<Vec<Turn>>,
+    /// Turn indications of the backward lanes, from left to right
+    /// (seen in the backward direction of travel).
+    pub turn_backward: Vec<Vec<Turn>>,
+    /// Value of `lanes`.
+    tagged_total: Option<u8>,
+    /// Value of `lanes:forward`.
+    tagged_forward: Option<u8>,
+    /// Value of `lanes:backward`.
+    tagged_backward: Option<u8>,
+    /// Value of `turn:lanes`, without direction.
+    turn_lanes: Vec<Vec<Turn>>,
+}
+
+impl Lanes {
+    /// Update lanes based on an OSM tag key-value pair.
+    ///
+    /// Recognizes `lanes`, `lanes:forward`, `lanes:backward`, `width`, `turn:lanes`,
+    /// `turn:lanes:forward` and `turn:lanes:backward`.
+    pub fn update_with_str(&mut self, key: &str, val: &str) {
+        match key {
+            "lanes" => self.tagged_total = val.trim().parse().ok(),
+            "lanes:forward" => self.tagged_forward = val.trim().parse().ok(),
+            "lanes:backward" => self.tagged_backward = val.trim().parse().ok(),
+            "width" => self.width = parse_width(val),
+            "turn:lanes" => self.turn_lanes = parse_turn_lanes(val),
+            "turn:lanes:forward" => self.turn_forward = parse_turn_lanes(val),
+            "turn:lanes:backward" => self.turn_backward = parse_turn_lanes(val),
+            _ => {}
+        }
+    }
+
+    /// Resolves the number of lanes per direction.
+    ///
+    /// Applies the following rules:
+    /// - `lanes:forward` and `lanes:backward` are used when tagged
+    /// - Otherwise the total `lanes` is split between the open directions
+    /// - Without any lane tag, the number of lanes depends on the road class:
+    ///   one per direction, two for trunks and motorways
+    /// - A direction forbidden to cars has no lane
+    /// - `turn:lanes` applies to the open direction of one-way roads
+    pub fn normalize(&mut self, properties: &EdgeProperties) {
+        let open = |car: CarAccessibility| {
+            car != CarAccessibility::Forbidden && car != CarAccessibility::Unknown
+        };
+        let forward_open = open(properties.car_forward);
+        let backward_open = open(properties.car_backward);
+        let default = |car: CarAccessibility| match car {
+            CarAccessibility::Trunk | CarAccessibility::Motorway => 2,
+            _ => 1,
+        };
+
+        let (forward, backward) =
+            match (self.tagged_forward, self.tagged_backward, self.tagged_total) {
+                (Some(forward), Some(backward), _) => (forward, backward),
+                (Some(forward), None, Some(total)) => (forward, total.saturating_sub(forward)),
+                (None, Some(backward), Some(total)) => (total.saturating_sub(backward), backward),
+                (forward, backward, Some(total)) if forward_open && backward_open => (
+                    forward.unwrap_or(total.div_ceil(2)),
+                    backward.unwrap_or(total / 2),
+                ),
+                (forward, backward, Some(total)) => {
+                    (forward.unwrap_or(total), backward.unwrap_or(total))
+                }
+                (forward, backward, None) => (
+                    forward.unwrap_or(default(properties.car_forward)),
+                    backward.unwrap_or(default(properties.car_backward)),
+                ),
+            };
+        self.forward = if forward_open { forward } else { 0 };
+        self.backward = if backward_open { backward } else { 0 };
+
+        if !self.turn_lanes.is_empty() {
+            if !backward_open && self.turn_forward.is_empty() {
+                self.turn_forward = self.turn_lanes.clone();
+            } else if !forward_open && self.turn_backward.is_empty() {
+                self.turn_backward = self.turn_lanes.clone();
+            }
+        }
+    }
+
+    /// Swaps the forward and backward lanes, for an edge travelled from its target.
+    pub fn reverse(&mut self) {
+        std::mem::swap(&mut self.forward, &mut self.backward);
+        std::mem::swap(&mut self.turn_forward, &mut self.turn_backward);
+        std::mem::swap(&mut self.tagged_forward, &mut self.tagged_backward);
+    }
+}
+
+#[test]
+fn test_turn_lanes() {
+    let lanes = parse_turn_lanes("left|through;right|");
+    assert_eq!(
+        vec![
+            vec![Turn::Left],
+            vec![Turn::Through, Turn::Right],
+            vec![Turn::None]
+        ],
+        lanes
+    );
+    assert_eq!("left|through;right|none", format_turn_lanes(&lanes));
+}
+
+#[test]
+fn test_width() {
+    assert_eq!(Some(3.5), parse_width("3.5"));
+    assert_eq!(Some(7.0), parse_width("7 m"));
+    assert!((parse_width("10'").unwrap() - 3.048).abs() < 1e-9);
+    assert_eq!(None, parse_width("narrow"));
+}
+
+#[test]
+fn test_normalize_lanes() {
+    let lanes = |tags: &[(&str, &str)]| {
+        let mut p = EdgeProperties::default();
+        let mut lanes = Lanes::default();
+        for (key, val) in tags {
+            p.update_with_str(key, val);
+            lanes.update_with_str(key, val);
+        }
+        p.normalize();
+        lanes.normalize(&p);
+        lanes
+    };
+
+    let l = lanes(&[("highway", "residential")]);
+    assert_eq!((1, 1), (l.forward, l.backward));
+
+    let l = lanes(&[("highway", "motorway"), ("oneway", "yes")]);
+    assert_eq!((2, 0), (l.forward, l.backward));
+
+    let l = lanes(&[("highway", "primary"), ("lanes", "3")]);
+    assert_eq!((2, 1), (l.forward, l.backward));
+
+    let l = lanes(&[
+        ("highway", "primary"),
+        ("lanes", "3"),
+        ("lanes:backward", "2"),
+    ]);
+    assert_eq!((1, 2), (l.forward, l.backward));
+
+    let l = lanes(&[
+        ("highway", "secondary"),
+        ("oneway", "yes"),
+        ("lanes", "2"),
+        ("turn:lanes", "left|through"),
+    ]);
+    assert_eq!((2, 0), (l.forward, l.backward));
+    assert_eq!(vec![vec![Turn::Left], vec![Turn::Through]], l.turn_forward);
+
+    // One-way road open only in the backward direction
+    let mut p = EdgeProperties::default();
+    p.update_with_str("highway", "secondary");
+    p.update_with_str("oneway", "yes");
+    p.normalize();
+    let p = p.reverse();
+    let mut l = Lanes::default();
+    l.update_with_str("lanes", "2");
+    l.update_with_str("turn:lanes", "left|through");
+    l.normalize(&p);
+    assert_eq!((0, 2), (l.forward, l.backward));
+    assert!(l.turn_forward.is_empty());
+    assert_eq!(vec![vec![Turn::Left], vec![Turn::Through]], l.turn_backward);
+
+    let l = lanes(&[("highway", "cycleway")]);
+    assert_eq!((0, 0), (l.forward, l.backward));
+}
@@ -0,0 +1,580 @@
+//! Map matching of GPS traces with a Hidden Markov Model.
+//!
+//! Each trace point has candidate projections on the nearby edges
+//! ([`EdgeIndex::edges_within`]). The emission probability of a candidate
+//! decreases with its distance to the point (Gaussian), and the transition
+//! probability between two candidates decreases with the difference between
+//! their network distance and the great-circle distance of the points
+//! (exponential), following Newson and Krumm. The Viterbi algorithm then
+//! finds the most likely sequence of candidates.
+
+use super::categorize::{Direction, Mode};
+use super::error::Error;
+use super::graph::{Arc, Graph};
+use super::models::*;
+use super::routing::{Router, State, Weight};
+use super::spatial::{EdgeIndex, Projection};
+use ahash::HashMap;
+use std::collections::BinaryHeap;
+use std::io::BufRead;
+use std::path::Path;
+
+type Coord = geo_types::Coord<f64>;
+
+/// A point of a GPS trace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TracePoint {
+    /// Coordinates in WGS84.
+    pub coord: Coord,
+    /// Timestamp as found in the trace, usually ISO 8601.
+    pub time: Option<String>,
+}
+
+/// A row of a CSV trace.
+#[derive(serde::Deserialize)]
+struct TraceRecord {
+    lon: f64,
+    lat: f64,
+    time: Option<String>,
+}
+
+/// Reads a GPS trace from a GPX or CSV file.
+///
+/// Files ending with `.gpx` are read for their track points (`trkpt`) and
+/// route points (`rtept`), with their `time`. Other files are CSV with `lon`
+/// and `lat` columns, and an optional `time` column.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is not a valid trace.
+pub fn read_trace<P: AsRef<Path>>(path: P) -> Result<Vec<TracePoint>, Error> {
+    let path = path.as_ref();
+    if path.extension().and_then(|e| e.to_str()) == Some("gpx") {
+        read_gpx(std::io::BufReader::new(std::fs::File::open(path)?))
+    } else {
+        let mut reader = csv::Reader::from_path(path)?;
+        reader
+            .deserialize()
+            .map(|record| {
+                let record: TraceRecord = record?;
+                Ok(TracePoint {
+                    coord: Coord {
+                        x: record.lon,
+                        y: record.lat,
+                    },
+                    time: record.time,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Reads the track and route points of a GPX document.
+pub fn read_gpx(reader: impl BufRead) -> Result<Vec<TracePoint>, Error> {
+    use quick_xml::events::Event;
+
+    let invalid = |e: &dyn std::fmt::Display| Error::InvalidTrace(e.to_string());
+    let mut reader = quick_xml::Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut points = Vec::new();
+    let mut in_time = false;
+    loop {
+        match reader.read_event_into(&mut buf).map_err(|e| invalid(&e))? {
+            Event::Start(tag) | Event::Empty(tag)
+                if matches!(tag.local_name().as_ref(), "trkpt" | "rtept") =>
+            {
+                let coordinate = |name: &str| -> Result<f64, Error> {
+                    let attribute = tag
+                        .try_get_attribute(name)
+                        .map_err(|e| invalid(&e))?
+                        .ok_or_else(|| invalid(&format!("point without {name}")))?;
+                    let value = attribute
+                        .normalized_value(quick_xml::XmlVersion::default())
+                        .map_err(|e| invalid(&e))?;
+                    value.trim().parse().map_err(|e| invalid(&e))
+                };
+                points.push(TracePoint {
+                    coord: Coord {
+                        x: coordinate("lon")?,
+                        y: coordinate("lat")?,
+                    },
+                    time: None,
+                });
+            }
+            Event::Start(tag) if tag.local_name().as_ref() == "time" => in_time = true,
+            Event::End(tag) if tag.local_name().as_ref() == "time" => in_time = false,
+            Event::Text(text) if in_time => {
+                if let Some(point) = points.last_mut() {
+                    point.time = Some(text.xml10_content().trim().to_string());
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(points)
+}
+
+/// An edge of a matched trace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchedEdge {
+    /// Index of the edge in [`Graph::edges`].
+    pub edge: usize,
+    /// Direction in which the edge is traversed.
+    pub direction: Direction,
+    /// Time of the first trace point matched on the edge, or of the point
+    /// before the edge if it was crossed between two points.
+    pub start: Option<String>,
+    /// Time of the last trace point matched on the edge, or of the point
+    /// after the edge if it was crossed between two points.
+    pub end: Option<String>,
+}
+
+/// The result of map matching a trace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatchedTrace {
+    /// Projection of each trace point, `None` if it has no candidate.
+    pub points: Vec<Option<Projection>>,
+    /// Edges followed by the trace, in order.
+    pub edges: Vec<MatchedEdge>,
+}
+
+/// Length and arc used to reach each node of a search.
+type SearchTree = HashMap<usize, (f64, Option<Arc>)>;
+
+/// Network path between two candidates.
+#[derive(Clone, Debug, Default)]
+struct Route {
+    length: f64,
+    /// Direction in which the edge of the first candidate is left.
+    exit: Option<Direction>,
+    /// Arcs between the two candidate edges.
+    arcs: Vec<Arc>,
+    /// Direction in which the edge of the second candidate is entered.
+    entry: Option<Direction>,
+}
+
+/// Best previous candidate of a candidate, with the route between them.
+#[derive(Clone, Debug)]
+struct Link {
+    step: usize,
+    candidate: usize,
+    route: Route,
+}
+
+/// Hidden Markov Model map matcher for one mode.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{MapMatcher, Mode};
+///
+/// let graph = osm4routing::read_graph("map.osm.pbf").unwrap();
+/// let trace = osm4routing::read_trace("drive.gpx").unwrap();
+/// let matcher = MapMatcher::new(&graph, Mode::Car).sigma(10.).radius(50.);
+/// for matched in matcher.match_trace(&trace).edges {
+///     println!("{} from {:?} to {:?}", graph.edges[matched.edge].id, matched.start, matched.end);
+/// }
+/// ```
+pub struct MapMatcher<'a> {
+    graph: &'a Graph,
+    router: Router<'a>,
+    index: EdgeIndex<'a>,
+    sigma: f64,
+    beta: f64,
+    radius: f64,
+    max_candidates: usize,
+}
+
+impl<'a> MapMatcher<'a> {
+    /// Creates a matcher with a GPS noise of 10 m, a transition scale of
+    /// 10 m and candidates within 50 m.
+    pub fn new(graph: &'a Graph, mode: Mode) -> MapMatcher<'a> {
+        MapMatcher {
+            graph,
+            router: Router::new(graph, mode, Weight::Length),
+            index: EdgeIndex::new(&graph.edges),
+            sigma: 10.,
+            beta: 10.,
+            radius: 50.,
+            max_candidates: 8,
+        }
+    }
+
+    /// Standard deviation of the GPS noise, in meters.
+    pub fn sigma(mut self, sigma: f64) -> Self {
+        self.sigma = sigma;
+        self
+    }
+
+    /// Scale of the difference between network and great-circle distances, in meters.
+    ///
+    /// A larger value tolerates more detours between two points.
+    pub fn beta(mut self, beta: f64) -> Self {
+        self.beta = beta;
+        self
+    }
+
+    /// Maximum distance between a point and its candidate edges, in meters.
+    pub fn radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Maximum number of candidate edges per point, the nearest ones.
+    pub fn max_candidates(mut self, max_candidates: usize) -> Self {
+        self.max_candidates = max_candidates;
+        self
+    }
+
+    /// Matches a trace to the graph.
+    ///
+    /// Points without candidate edges are skipped. When no path links two
+    /// consecutive points, the trace is matched as two separate parts.
+    pub fn match_trace(&self, trace: &[TracePoint]) -> MatchedTrace {
+        let candidates: Vec<Vec<Projection>> = trace
+            .iter()
+            .map(|point| {
+                let mut candidates =
+                    self.index
+                        .edges_within(point.coord, self.router.mode(), self.radius);
+                candidates.truncate(self.max_candidates);
+                candidates
+            })
+            .collect();
+        let steps: Vec<usize> = (0..trace.len())
+            .filter(|&i| !candidates[i].is_empty())
+            .collect();
+
+        // Viterbi: log probability of each candidate, and the best previous candidate
+        let mut matched: Vec<Option<usize>> = vec![None; trace.len()];
+        let mut back: Vec<Vec<Option<Link>>> = vec![Vec::new(); trace.len()];
+        let mut scores: Vec<f64> = Vec::new();
+        let mut previous_step: Option<usize> = None;
+        for &step in &steps {
+            let emissions: Vec<f64> = candidates[step]
+                .iter()
+                .map(|c| -0.5 * (c.distance / self.sigma).powi(2))
+                .collect();
+            back[step] = vec![None; candidates[step].len()];
+            let mut next_scores = vec![f64::NEG_INFINITY; candidates[step].len()];
+            if let Some(previous) = previous_step {
+                let great_circle = trace[previous].coord.distance_to(trace[step].coord);
+                let max = great_circle * 2. + self.radius * 4.;
+                for (a, from) in candidates[previous].iter().enumerate() {
+                    if scores[a].is_infinite() {
+                        continue;
+                    }
+                    for (b, route) in self.routes(from, &candidates[step], max) {
+                        let transition = -(route.length - great_circle).abs() / self.beta;
+                        let score = scores[a] + transition + emissions[b];
+                        if score > next_scores[b] {
+                            next_scores[b] = score;
+                            back[step][b] = Some(Link {
+                                step: previous,
+                                candidate: a,
+                                route,
+                            });
+                        }
+                    }
+                }
+            }
+            if next_scores.iter().all(|s| s.is_infinite()) {
+                // First point, or no path from the previous point: start a new part
+                if let Some(previous) = previous_step {
+                    backtrack(previous, &scores, &back, &mut matched);
+                }
+                next_scores = emissions;
+                for link in &mut back[step] {
+                    *link = None;
+                }
+            }
+            scores = next_scores;
+            previous_step = Some(step);
+        }
+        if let Some(last) = previous_step {
+            backtrack(last, &scores, &back, &mut matched);
+        }
+
+        let points: Vec<Option<Projection>> = matched
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c.map(|c| candidates[i][c]))
+            .collect();
+        let mut edges: Vec<MatchedEdge> = Vec::new();
+        let mut push =
+            |edge: usize, direction, start: &Option<String>, end: &Option<String>| match edges
+                .last_mut()
+            {
+                Some(last) if last.edge == edge => {
+                    last.direction = direction;
+                    last.end = end.clone();
+                }
+                _ => edges.push(MatchedEdge {
+                    edge,
+                    direction,
+                    start: start.clone(),
+                    end: end.clone(),
+                }),
+            };
+        for (step, point) in points.iter().enumerate() {
+            let Some(projection) = point else { continue };
+            let time = &trace[step].time;
+            match matched[step].and_then(|c| back[step][c].as_ref()) {
+                Some(Link {
+                    step: previous,
+                    route,
+                    ..
+                }) => {
+                    let from = points[*previous].expect("linked points are matched");
+                    let before = &trace[*previous].time;
+                    if let Some(exit) = route.exit {
+                        push(from.edge, exit, before, before);
+                    }
+                    for arc in &route.arcs {
+                        push(arc.edge, arc.direction, before, time);
+                    }
+                    let entry = route.entry.unwrap_or(Direction::Forward);
+                    push(projection.edge, entry, time, time);
+                }
+                None => push(projection.edge, Direction::Forward, time, time),
+            }
+        }
+        MatchedTrace { points, edges }
+    }
+
+    /// Returns the shortest routes from a candidate to the next candidates, up to a length.
+    fn routes(&self, from: &Projection, to: &[Projection], max: f64) -> Vec<(usize, Route)> {
+        let length = |p: &Projection| self.graph.edges[p.edge].length();
+        let allows = |p: &Projection, direction| {
+            self.graph.edges[p.edge]
+                .properties
+                .allows(self.router.mode(), direction)
+        };
+        let mut routes: Vec<Option<Route>> = vec![None; to.len()];
+        let mut keep = |b: usize, route: Route| {
+            if route.length <= max
+                && routes[b]
+                    .as_ref()
+                    .is_none_or(|best| route.length < best.length)
+            {
+                routes[b] = Some(route);
+            }
+        };
+
+        // Along the same edge
+        for (b, p) in to.iter().enumerate() {
+            if p.edge != from.edge {
+                continue;
+            }
+            let along = (p.offset - from.offset) * length(from);
+            let direction = if along >= 0. {
+                Direction::Forward
+            } else {
+                Direction::Backward
+            };
+            if allows(from, direction) {
+                keep(
+                    b,
+                    Route {
+                        length: along.abs(),
+                        entry: Some(direction),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        // Leaving the edge by one of its ends
+        let Some((source, target)) = self.graph.ends(from.edge) else {
+            return flatten(routes);
+        };
+        for (exit, node, cost) in [
+            (
+                Direction::Forward,
+                target,
+                (1. - from.offset) * length(from),
+            ),
+            (Direction::Backward, source, from.offset * length(from)),
+        ] {
+            if !allows(from, exit) {
+                continue;
+            }
+            let tree = self.search(node, max - cost);
+            for (b, p) in to.iter().enumerate() {
+                let Some((p_source, p_target)) = self.graph.ends(p.edge) else {
+                    continue;
+                };
+                for (entry, node, entry_cost) in [
+                    (Direction::Forward, p_source, p.offset * length(p)),
+                    (Direction::Backward, p_target, (1. - p.offset) * length(p)),
+                ] {
+                    let Some(&(network, _)) = tree.get(&node) else {
+                        continue;
+                    };
+                    if !allows(p, entry) {
+                        continue;
+                    }
+                    keep(
+                        b,
+                        Route {
+                            length: cost + network + entry_cost,
+                            exit: Some(exit),
+                            arcs: self.arcs_to(&tree, node),
+                            entry: Some(entry),
+                        },
+                    );
+                }
+            }
+        }
+        flatten(routes)
+    }
+
+    /// Dijkstra from a node up to a length, returning the length and the arc used to reach each node.
+    fn search(&self, source: usize, max: f64) -> SearchTree {
+        let mut reached = HashMap::default();
+        reached.insert(source, (0., None));
+        let mut queue = BinaryHeap::new();
+        queue.push(State {
+            key: 0.,
+            node: source,
+        });
+        while let Some(State { key, node }) = queue.pop() {
+            if key > reached[&node].0 {
+                continue;
+            }
+            for arc in self.graph.outgoing(node, self.router.mode()) {
+                let cost = key + self.router.cost(arc);
+                if cost <= max && reached.get(&arc.node).is_none_or(|&(c, _)| cost < c) {
+                    reached.insert(arc.node, (cost, Some(*arc)));
+                    queue.push(State {
+                        key: cost,
+                        node: arc.node,
+                    });
+                }
+            }
+        }
+        reached
+    }
+
+    /// Rebuilds the arcs of a search tree leading to a node.
+    fn arcs_to(&self, tree: &SearchTree, node: usize) -> Vec<Arc> {
+        let mut arcs = Vec::new();
+        let mut current = node;
+        while let Some(&(_, Some(arc))) = tree.get(&current) {
+            arcs.push(arc);
+            current = self.graph.tail(&arc);
+        }
+        arcs.reverse();
+        arcs
+    }
+}
+
+/// Follows the best previous candidates back from a step, up to the start of its part.
+fn backtrack(
+    last: usize,
+    scores: &[f64],
+    back: &[Vec<Option<Link>>],
+    matched: &mut [Option<usize>],
+) {
+    let best = (0..scores.len()).max_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+    let mut current = best.map(|candidate| (last, candidate));
+    while let Some((step, candidate)) = current {
+        matched[step] = Some(candidate);
+        current = back[step][candidate]
+            .as_ref()
+            .map(|link| (link.step, link.candidate));
+    }
+}
+
+fn flatten(routes: Vec<Option<Route>>) -> Vec<(usize, Route)> {
+    routes
+        .into_iter()
+        .enumerate()
+        .filter_map(|(b, route)| Some((b, route?)))
+        .collect()
+}
+
+#[test]
+fn test_read_gpx() {
+    let gpx = r#"<?xml version="1.0"?>
+<gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk><trkseg>
+    <trkpt lat="48.85" lon="2.35"><ele>35</ele><time>2024-05-01T08:00:00Z</time></trkpt>
+    <trkpt lat="48.86" lon="2.36"/>
+  </trkseg></trk>
+</gpx>"#;
+    let points = read_gpx(gpx.as_bytes()).unwrap();
+    assert_eq!(
+        vec![
+            TracePoint {
+                coord: Coord { x: 2.35, y: 48.85 },
+                time: Some("2024-05-01T08:00:00Z".to_string()),
+            },
+            TracePoint {
+                coord: Coord { x: 2.36, y: 48.86 },
+                time: None,
+            },
+        ],
+        points
+    );
+    assert!(read_gpx(r#"<gpx><trkpt lat="1"/></gpx>"#.as_bytes()).is_err());
+}
+
+#[test]
+fn test_match_trace() {
+    use super::routing::test_graph;
+
+    let graph = test_graph();
+    let point = |x, y, time: &str| TracePoint {
+        coord: Coord { x, y },
+        time: Some(time.to_string()),
+    };
+    let trace = [
+        point(0.001, 0.0001, "t0"),
+        point(0.009, 0.0001, "t1"),
+        point(0.005, 0.01, "far"),
+        point(0.011, -0.0001, "t2"),
+        point(0.019, 0.0001, "t3"),
+    ];
+    let matched = MapMatcher::new(&graph, Mode::Car).match_trace(&trace);
+    assert_eq!(None, matched.points[2]);
+    assert_eq!(
+        vec![Some(0), Some(0), None, Some(1), Some(1)],
+        matched
+            .points
+            .iter()
+            .map(|p| p.map(|p| p.edge))
+            .collect::<Vec<_>>()
+    );
+    let time = |t: &str| Some(t.to_string());
+    assert_eq!(
+        vec![
+            MatchedEdge {
+                edge: 0,
+                direction: Direction::Forward,
+                start: time("t0"),
+                end: time("t1"),
+            },
+            MatchedEdge {
+                edge: 1,
+                direction: Direction::Forward,
+                start: time("t2"),
+                end: time("t3"),
+            },
+        ],
+        matched.edges
+    );
+
+    // Walking back from 3 to 1 along the streets, the one-way street is followed backward
+    let trace: Vec<_> = trace.into_iter().rev().collect();
+    let matched = MapMatcher::new(&graph, Mode::Foot).match_trace(&trace);
+    assert_eq!(
+        vec![(1, Direction::Backward), (0, Direction::Backward)],
+        matched
+            .edges
+            .iter()
+            .map(|e| (e.edge, e.direction))
+            .collect::<Vec<_>>()
+    );
+}
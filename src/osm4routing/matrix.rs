@@ -0,0 +1,323 @@
+//! Cost matrices between arbitrary locations.
+//!
+//! Locations are snapped to the nearest node usable by the mode, then a
+//! [`Matrix`] holds the cost from every origin to every destination, computed
+//! in parallel with one search per origin.
+
+use super::categorize::Mode;
+use super::ch::ContractionHierarchy;
+use super::error::Error;
+use super::graph::Graph;
+use super::models::*;
+use super::routing::Router;
+use rayon::prelude::*;
+use rstar::RTree;
+use rstar::primitives::GeomWithData;
+use std::path::Path;
+
+type Coord = geo_types::Coord<f64>;
+
+/// A named location, such as an origin or a destination.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// Identifier of the location, its position in the file if it has none.
+    pub id: String,
+    /// Coordinates in WGS84.
+    pub coord: Coord,
+}
+
+/// A row of a CSV file of locations.
+#[derive(serde::Deserialize)]
+struct LocationRecord {
+    id: Option<String>,
+    lon: f64,
+    lat: f64,
+}
+
+/// Reads locations from a CSV or GeoJSON file.
+///
+/// Files ending with `.json` or `.geojson` must be a `FeatureCollection` of
+/// `Point` features, whose id is the feature `id` or its `id` property.
+/// Other files are CSV with `lon` and `lat` columns, and an optional `id` column.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or has no coordinates.
+pub fn read_locations<P: AsRef<Path>>(path: P) -> Result<Vec<Location>, Error> {
+    let path = path.as_ref();
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json" | "geojson") => {
+            let file = std::io::BufReader::new(std::fs::File::open(path)?);
+            locations_from_geojson(&serde_json::from_reader(file)?)
+        }
+        _ => {
+            let mut reader = csv::Reader::from_path(path)?;
+            reader
+                .deserialize()
+                .enumerate()
+                .map(|(i, record)| {
+                    let record: LocationRecord = record?;
+                    Ok(Location {
+                        id: record.id.unwrap_or_else(|| i.to_string()),
+                        coord: Coord {
+                            x: record.lon,
+                            y: record.lat,
+                        },
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+fn locations_from_geojson(json: &serde_json::Value) -> Result<Vec<Location>, Error> {
+    let invalid = |reason: &str| Error::InvalidLocations(reason.to_string());
+    let features = json["features"]
+        .as_array()
+        .ok_or_else(|| invalid("expected a GeoJSON FeatureCollection"))?;
+    features
+        .iter()
+        .enumerate()
+        .map(|(i, feature)| {
+            let geometry = &feature["geometry"];
+            if geometry["type"] != "Point" {
+                return Err(invalid("only Point features are supported"));
+            }
+            let position = |axis: usize| {
+                geometry["coordinates"][axis]
+                    .as_f64()
+                    .ok_or_else(|| invalid("a point has no coordinates"))
+            };
+            let id = [&feature["id"], &feature["properties"]["id"]]
+                .into_iter()
+                .find_map(|id| match id {
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    serde_json::Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                })
+                .unwrap_or_else(|| i.to_string());
+            Ok(Location {
+                id,
+                coord: Coord {
+                    x: position(0)?,
+                    y: position(1)?,
+                },
+            })
+        })
+        .collect()
+}
+
+/// A location snapped to the graph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Snap {
+    /// Index of the node in [`Graph::nodes`].
+    pub node: usize,
+    /// Distance in meters between the location and the node.
+    pub distance: f64,
+}
+
+/// Finds the nearest node usable by a mode.
+///
+/// Coordinates are scaled by the cosine of the mean latitude, so that
+/// nearest neighbors in the index are close to nearest neighbors on the ground.
+pub struct Snapper<'a> {
+    graph: &'a Graph,
+    tree: RTree<GeomWithData<[f64; 2], usize>>,
+    scale: f64,
+}
+
+impl<'a> Snapper<'a> {
+    /// Indexes the nodes with at least one arc for the mode.
+    pub fn new(graph: &'a Graph, mode: Mode) -> Snapper<'a> {
+        let nodes: Vec<usize> = (0..graph.nodes.len())
+            .filter(|&node| {
+                !graph.outgoing(node, mode).is_empty() || !graph.incoming(node, mode).is_empty()
+            })
+            .collect();
+        let mean_lat =
+            nodes.iter().map(|&n| graph.nodes[n].coord.y).sum::<f64>() / nodes.len().max(1) as f64;
+        let scale = mean_lat.to_radians().cos();
+        let tree = RTree::bulk_load(
+            nodes
+                .into_iter()
+                .map(|node| {
+                    let coord = graph.nodes[node].coord;
+                    GeomWithData::new([coord.x * scale, coord.y], node)
+                })
+                .collect(),
+        );
+        Snapper { graph, tree, scale }
+    }
+
+    /// Returns the nearest node, `None` if no node is usable by the mode.
+    pub fn snap(&self, coord: Coord) -> Option<Snap> {
+        self.tree
+            .nearest_neighbor([coord.x * self.scale, coord.y])
+            .map(|point| Snap {
+                node: point.data,
+                distance: coord.distance_to(self.graph.nodes[point.data].coord),
+            })
+    }
+}
+
+/// Costs from every origin to every destination.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Matrix {
+    /// Snapped origins, `None` if they could not be snapped.
+    pub origins: Vec<Option<Snap>>,
+    /// Snapped destinations, `None` if they could not be snapped.
+    pub destinations: Vec<Option<Snap>>,
+    /// `costs[i][j]` is the cost from origin `i` to destination `j`,
+    /// in the unit of the weight, `None` if unreachable.
+    pub costs: Vec<Vec<Option<f64>>>,
+}
+
+impl Matrix {
+    /// Computes the matrix with one Dijkstra per origin, in parallel.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::{Matrix, Mode, Router, Weight};
+    ///
+    /// let graph = osm4routing::read_graph("map.osm.pbf").unwrap();
+    /// let router = Router::new(&graph, Mode::Car, Weight::TravelTime);
+    /// let depots = osm4routing::read_locations("depots.csv").unwrap();
+    /// let customers = osm4routing::read_locations("customers.geojson").unwrap();
+    /// let coords = |locations: &[osm4routing::Location]| {
+    ///     locations.iter().map(|l| l.coord).collect::<Vec<_>>()
+    /// };
+    /// let matrix = Matrix::new(&router, &coords(&depots), &coords(&customers));
+    /// ```
+    pub fn new(router: &Router, origins: &[Coord], destinations: &[Coord]) -> Matrix {
+        Matrix::compute(router, origins, destinations, |sources, targets| {
+            sources
+                .par_iter()
+                .map(|&source| router.one_to_many(source, targets))
+                .collect()
+        })
+    }
+
+    /// Computes the matrix with a hierarchy built from the router.
+    ///
+    /// The router is only used to snap the locations.
+    ///
+    /// # Panics
+    /// Panics if the hierarchy was not built from the graph, mode and weight of the router.
+    pub fn with_hierarchy(
+        router: &Router,
+        hierarchy: &ContractionHierarchy,
+        origins: &[Coord],
+        destinations: &[Coord],
+    ) -> Matrix {
+        assert!(
+            hierarchy.is_built_from(router),
+            "the hierarchy was built for another graph, mode or weight"
+        );
+        Matrix::compute(router, origins, destinations, |sources, targets| {
+            hierarchy.many_to_many(sources, targets)
+        })
+    }
+
+    fn compute(
+        router: &Router,
+        origins: &[Coord],
+        destinations: &[Coord],
+        many_to_many: impl FnOnce(&[usize], &[usize]) -> Vec<Vec<Option<f64>>>,
+    ) -> Matrix {
+        let snapper = Snapper::new(router.graph(), router.mode());
+        let snap = |coords: &[Coord]| -> Vec<Option<Snap>> {
+            coords.iter().map(|coord| snapper.snap(*coord)).collect()
+        };
+        let origins = snap(origins);
+        let destinations = snap(destinations);
+        let nodes = |snaps: &[Option<Snap>]| -> Vec<usize> {
+            snaps.iter().flatten().map(|snap| snap.node).collect()
+        };
+        let mut rows = many_to_many(&nodes(&origins), &nodes(&destinations)).into_iter();
+        let costs = origins
+            .iter()
+            .map(|origin| {
+                let mut row = origin
+                    .map(|_| rows.next().expect("one row per snapped origin"))
+                    .unwrap_or_default()
+                    .into_iter();
+                destinations
+                    .iter()
+                    .map(|destination| destination.and_then(|_| row.next().flatten()))
+                    .collect()
+            })
+            .collect();
+        Matrix {
+            origins,
+            destinations,
+            costs,
+        }
+    }
+}
+
+#[test]
+fn test_read_locations() {
+    let dir = std::env::temp_dir();
+    let csv = dir.join("osm4routing_locations.csv");
+    std::fs::write(&csv, "id,lon,lat\ndepot,2.35,48.85\n,2.5,48.9\n").unwrap();
+    let locations = read_locations(&csv).unwrap();
+    std::fs::remove_file(&csv).unwrap();
+    assert_eq!(2, locations.len());
+    assert_eq!("depot", locations[0].id);
+    assert_eq!("1", locations[1].id);
+    assert_eq!(Coord { x: 2.5, y: 48.9 }, locations[1].coord);
+
+    let geojson = dir.join("osm4routing_locations.geojson");
+    std::fs::write(
+        &geojson,
+        r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "id": 7, "properties": {}, "geometry": {"type": "Point", "coordinates": [1.0, 2.0]}},
+            {"type": "Feature", "properties": {"id": "b"}, "geometry": {"type": "Point", "coordinates": [3.0, 4.0]}}
+        ]}"#,
+    )
+    .unwrap();
+    let locations = read_locations(&geojson).unwrap();
+    std::fs::remove_file(&geojson).unwrap();
+    assert_eq!(
+        vec![
+            Location {
+                id: "7".to_string(),
+                coord: Coord { x: 1., y: 2. }
+            },
+            Location {
+                id: "b".to_string(),
+                coord: Coord { x: 3., y: 4. }
+            },
+        ],
+        locations
+    );
+}
+
+#[test]
+fn test_matrix() {
+    use super::routing::{Weight, test_graph};
+
+    let graph = test_graph();
+    let router = Router::new(&graph, Mode::Car, Weight::Length);
+    // Near nodes 1, 3 and the footway node 4, which cars cannot reach
+    let origins = [Coord { x: -0.001, y: 0. }, Coord { x: 0.021, y: 0. }];
+    let destinations = [Coord { x: 0.019, y: 0. }, Coord { x: 0.01, y: -0.002 }];
+    let matrix = Matrix::new(&router, &origins, &destinations);
+    assert_eq!(Some(0), matrix.origins[0].map(|s| s.node));
+    assert!((matrix.origins[0].unwrap().distance - 111.).abs() < 1.);
+    // Node 4 has no arc for cars: the destination snaps to node 2
+    assert_eq!(Some(1), matrix.destinations[1].map(|s| s.node));
+    let costs = router.one_to_many(0, &[2, 1]);
+    assert_eq!(costs, matrix.costs[0]);
+    assert_eq!(vec![Some(0.), None], matrix.costs[1]);
+
+    let hierarchy = ContractionHierarchy::new(&router);
+    let with_hierarchy = Matrix::with_hierarchy(&router, &hierarchy, &origins, &destinations);
+    assert_eq!(matrix.destinations, with_hierarchy.destinations);
+    for (row, expected) in with_hierarchy.costs.iter().zip(&matrix.costs) {
+        for (cost, expected) in row.iter().zip(expected) {
+            assert_eq!(expected.is_some(), cost.is_some());
+            assert!((expected.unwrap_or(0.) - cost.unwrap_or(0.)).abs() < 1e-6);
+        }
+    }
+}
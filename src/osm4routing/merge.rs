@@ -0,0 +1,292 @@
+//! Contraction of chains of edges.
+//!
+//! With [`Reader::merge_ways`](super::reader::Reader::merge_ways), edges that
+//! meet at a node touched by no other edge are merged into a single edge.
+//! Chains are found and merged in a single pass over the edges, in linear time.
+//! Two edges are only merged if they allow the same movements once oriented
+//! in the same direction, and if their tags are compatible according to
+//! their [`TagPolicy`].
+
+use super::categorize::Direction;
+use super::models::*;
+use ahash::{HashMap, HashMapExt, HashSet};
+
+/// How a tag kept with [`Reader::read_tag`](super::reader::Reader::read_tag)
+/// is handled when merging edges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TagPolicy {
+    /// Edges with different values are not merged.
+    #[default]
+    MustMatch,
+    /// The distinct values are joined with `;`, from source to target.
+    Concatenate,
+    /// The value of the first edge having the tag, from the source.
+    KeepFirst,
+    /// The tag is removed from the merged edge if the values differ.
+    Drop,
+}
+
+/// What must match for two edges to be merged.
+pub(crate) struct MergeRules<'a> {
+    /// Policies of the tags, [`TagPolicy::MustMatch`] for the others.
+    pub(crate) policies: &'a HashMap<String, TagPolicy>,
+    /// Whether edges with different structural flags can be merged.
+    pub(crate) across_structures: bool,
+}
+
+impl MergeRules<'_> {
+    fn policy(&self, key: &str) -> TagPolicy {
+        self.policies.get(key).copied().unwrap_or_default()
+    }
+
+    /// Whether `before`, arriving at `node`, and `after`, leaving it, can be merged.
+    fn can_merge(&self, before: &Edge, after: &Edge, node: NodeId) -> bool {
+        let oriented = |edge: &Edge, forward: bool| {
+            let mut properties = edge.properties;
+            let mut lanes = edge.lanes.clone();
+            let mut conditions = edge.conditions.clone();
+            if !forward {
+                properties = properties.reverse();
+                lanes.reverse();
+                for condition in &mut conditions {
+                    condition.direction = condition.direction.map(Direction::reverse);
+                }
+            }
+            (properties, lanes, conditions)
+        };
+        oriented(before, before.target == node) == oriented(after, after.source == node)
+            && (self.across_structures || before.structure() == after.structure())
+            && before.tags.keys().chain(after.tags.keys()).all(|key| {
+                self.policy(key) != TagPolicy::MustMatch
+                    || before.tags.get(key) == after.tags.get(key)
+            })
+    }
+
+    /// Tags of a merged chain.
+    fn merge_tags(&self, chain: &[Edge]) -> HashMap<String, String> {
+        let keys: HashSet<&String> = chain.iter().flat_map(|edge| edge.tags.keys()).collect();
+        let mut tags = HashMap::default();
+        for key in keys {
+            let values: Vec<Option<&String>> =
+                chain.iter().map(|edge| edge.tags.get(key)).collect();
+            let mut distinct: Vec<&String> = Vec::new();
+            for value in values.iter().flatten() {
+                if !distinct.contains(value) {
+                    distinct.push(value);
+                }
+            }
+            let all_equal = distinct.len() == 1 && values.iter().all(Option::is_some);
+            let value = match self.policy(key) {
+                TagPolicy::MustMatch | TagPolicy::KeepFirst => {
+                    distinct.first().map(|v| v.to_string())
+                }
+                TagPolicy::Concatenate => Some(
+                    distinct
+                        .iter()
+                        .map(|v| v.as_str())
+                        .collect::<Vec<_>>()
+                        .join(";"),
+                ),
+                TagPolicy::Drop => all_equal.then(|| distinct[0].clone()),
+            };
+            if let Some(value) = value {
+                tags.insert(key.clone(), value);
+            }
+        }
+        tags
+    }
+}
+
+/// Merges the chains of edges, returning the edges and the nodes merged away.
+///
+/// A node is merged away if exactly two distinct edges touch it and they can be
+/// merged. A closed chain keeps one node, where it was entered. The edges of a
+/// chain are oriented like its first edge, in the order of `edges`.
+pub(crate) fn merge_chains(edges: Vec<Edge>, rules: &MergeRules) -> (Vec<Edge>, Vec<NodeId>) {
+    let mut incident: HashMap<NodeId, Vec<usize>> = HashMap::with_capacity(edges.len());
+    for (i, edge) in edges.iter().enumerate() {
+        incident.entry(edge.source).or_default().push(i);
+        incident.entry(edge.target).or_default().push(i);
+    }
+    // The two edges at each node that can be merged away
+    let joints: HashMap<NodeId, (usize, usize)> = incident
+        .iter()
+        .filter_map(|(&node, incident)| match incident.as_slice() {
+            &[a, b] if a != b => {
+                let (before, after) = (&edges[a], &edges[b]);
+                rules
+                    .can_merge(before, after, node)
+                    .then_some((node, (a, b)))
+            }
+            _ => None,
+        })
+        .collect();
+    let other_end = |edge: usize, node: NodeId| {
+        if edges[edge].source == node {
+            edges[edge].target
+        } else {
+            edges[edge].source
+        }
+    };
+    let next_edge = |edge: usize, node: NodeId| {
+        let (a, b) = joints[&node];
+        if a == edge { b } else { a }
+    };
+
+    let mut chains: Vec<Vec<(usize, bool)>> = Vec::new();
+    let mut removed = Vec::new();
+    let mut visited = vec![false; edges.len()];
+    for first in 0..edges.len() {
+        if visited[first] {
+            continue;
+        }
+        // Walk back to the start of the chain, or around a closed chain
+        let (mut edge, mut node) = (first, edges[first].source);
+        while joints.contains_key(&node) {
+            let previous = next_edge(edge, node);
+            if previous == first {
+                break;
+            }
+            edge = previous;
+            node = other_end(edge, node);
+        }
+        let start = node;
+        let mut chain = Vec::new();
+        loop {
+            visited[edge] = true;
+            let forward = edges[edge].source == node;
+            chain.push((edge, forward));
+            node = other_end(edge, node);
+            if node == start || !joints.contains_key(&node) {
+                break;
+            }
+            removed.push(node);
+            edge = next_edge(edge, node);
+        }
+        chains.push(chain);
+    }
+
+    let mut edges: Vec<Option<Edge>> = edges.into_iter().map(Some).collect();
+    let mut result = Vec::with_capacity(chains.len());
+    for chain in chains {
+        let chain: Vec<Edge> = chain
+            .into_iter()
+            .map(|(edge, forward)| {
+                let edge = edges[edge].take().expect("each edge is in one chain");
+                if forward { edge } else { edge.reverse() }
+            })
+            .collect();
+        let tags = (chain.len() > 1).then(|| rules.merge_tags(&chain));
+        let mut chain = chain.into_iter();
+        let Some(mut merged) = chain.next() else {
+            continue;
+        };
+        for edge in chain {
+            merged.append(edge);
+        }
+        if let Some(tags) = tags {
+            merged.tags = tags;
+        }
+        result.push(merged);
+    }
+    (result, removed)
+}
+
+#[test]
+fn test_merge_chains() {
+    let edge = |id: u64, source: i64, target: i64, car_forward, car_backward| Edge {
+        id,
+        source: NodeId(source),
+        target: NodeId(target),
+        nodes: vec![NodeId(source), NodeId(target)],
+        geometry: vec![geo_types::Coord::default(); 2],
+        properties: super::categorize::EdgeProperties {
+            car_forward,
+            car_backward,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let one_way = super::categorize::CarAccessibility::Primary;
+    let forbidden = super::categorize::CarAccessibility::Forbidden;
+    let policies = HashMap::default();
+    let rules = MergeRules {
+        policies: &policies,
+        across_structures: false,
+    };
+
+    // The middle edge is drawn backwards: it is merged once reversed
+    let chain = vec![
+        edge(1, 0, 1, one_way, forbidden),
+        edge(3, 2, 3, one_way, forbidden),
+        edge(2, 2, 1, forbidden, one_way),
+    ];
+    let (edges, removed) = merge_chains(chain, &rules);
+    assert_eq!(1, edges.len());
+    assert_eq!(vec![1, 2, 3], edges[0].merged_ids);
+    assert_eq!(stable_edge_id(WayId(0), NodeId(0), NodeId(3)), edges[0].id);
+    assert_eq!(
+        vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+        edges[0].nodes
+    );
+    assert_eq!(one_way, edges[0].properties.car_forward);
+    assert_eq!(2, removed.len());
+
+    // Opposite one-ways are not merged
+    let chain = vec![
+        edge(1, 0, 1, one_way, forbidden),
+        edge(2, 2, 1, one_way, forbidden),
+    ];
+    let (edges, removed) = merge_chains(chain, &rules);
+    assert_eq!(2, edges.len());
+    assert!(removed.is_empty());
+}
+
+#[test]
+fn test_tag_policies() {
+    let edge = |id: u64, source: i64, name: &str, ref_: &str| Edge {
+        id,
+        source: NodeId(source),
+        target: NodeId(source + 1),
+        nodes: vec![NodeId(source), NodeId(source + 1)],
+        geometry: vec![geo_types::Coord::default(); 2],
+        tags: [("name", name), ("ref", ref_), ("surface", "asphalt")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        ..Default::default()
+    };
+    let chain = || vec![edge(1, 0, "Main", "A1"), edge(2, 1, "High", "A1")];
+
+    // Different names must match by default
+    let policies = HashMap::default();
+    let rules = MergeRules {
+        policies: &policies,
+        across_structures: false,
+    };
+    assert_eq!(2, merge_chains(chain(), &rules).0.len());
+
+    let policy = |name| {
+        let policies: HashMap<_, _> = [
+            ("name".to_string(), name),
+            ("ref".to_string(), TagPolicy::Drop),
+        ]
+        .into_iter()
+        .collect();
+        let rules = MergeRules {
+            policies: &policies,
+            across_structures: false,
+        };
+        let (edges, _) = merge_chains(chain(), &rules);
+        assert_eq!(1, edges.len());
+        assert_eq!("A1", edges[0].tags["ref"]);
+        assert_eq!("asphalt", edges[0].tags["surface"]);
+        edges[0].tags.get("name").cloned()
+    };
+    assert_eq!(
+        Some("Main;High".to_string()),
+        policy(TagPolicy::Concatenate)
+    );
+    assert_eq!(Some("Main".to_string()), policy(TagPolicy::KeepFirst));
+    assert_eq!(None, policy(TagPolicy::Drop));
+}
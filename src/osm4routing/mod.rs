@@ -1,5 +1,26 @@
 pub mod categorize;
+pub mod ch;
+pub mod components;
+pub mod conditional;
+pub mod country;
+pub mod crs;
+pub mod csr;
+pub mod distance;
+pub mod elevation;
 pub mod error;
+pub mod graph;
+pub mod isochrone;
+pub mod lanes;
+pub mod mapmatch;
+pub mod matrix;
+pub mod merge;
 pub mod models;
+pub mod order;
+pub mod profile;
 pub mod reader;
+pub mod routing;
+pub mod simplify;
+pub mod spatial;
+pub mod structure;
+pub mod validate;
 pub mod writers;
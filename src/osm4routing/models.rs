@@ -3,10 +3,16 @@
 //! This module defines the core data structures used to represent
 //! the routing network extracted from OpenStreetMap data.
 
-use ahash::HashMap;
+use ahash::{HashMap, HashSet, HashSetExt};
 use std::hash::{Hash, Hasher};
 
-use super::categorize::EdgeProperties;
+use super::categorize::{Direction, EdgeProperties};
+use super::conditional::ConditionalRestriction;
+use super::distance::DistanceModel;
+use super::elevation::Climb;
+use super::lanes::Lanes;
+use super::simplify::douglas_peucker;
+use super::structure::*;
 pub use osmpbfreader::objects::{NodeId, WayId};
 
 /// Coordinate type alias for WGS84 coordinates in decimal degrees.
@@ -18,24 +24,14 @@ type Coord = geo_types::Coord<f64>;
 pub trait Distance {
     /// Calculate the great-circle distance to another coordinate in meters.
     ///
-    /// Uses the haversine formula with Earth's mean radius of 6,378,100 meters.
+    /// Uses the haversine formula with Earth's mean radius of 6,371,008.8 meters.
+    /// See [`DistanceModel`] for distances on the WGS84 ellipsoid.
     fn distance_to(&self, end: Coord) -> f64;
 }
 
 impl Distance for Coord {
     fn distance_to(&self, end: Coord) -> f64 {
-        let r: f64 = 6_378_100.0;
-
-        let d_lon: f64 = (end.x - self.x).to_radians();
-        let d_lat: f64 = (end.y - self.y).to_radians();
-        let lat1: f64 = (self.y).to_radians();
-        let lat2: f64 = (end.y).to_radians();
-
-        let a: f64 = ((d_lat / 2.0).sin()) * ((d_lat / 2.0).sin())
-            + ((d_lon / 2.0).sin()) * ((d_lon / 2.0).sin()) * (lat1.cos()) * (lat2.cos());
-        let c: f64 = 2.0 * ((a.sqrt()).atan2((1.0 - a).sqrt()));
-
-        r * c
+        DistanceModel::Haversine.distance(*self, end)
     }
 }
 
@@ -54,6 +50,10 @@ pub struct Node {
     ///
     /// Endpoints of ways are counted twice to ensure dead-ends are preserved.
     pub uses: i16,
+    /// Elevation in meters, if an elevation model covers the node.
+    pub elevation: Option<f64>,
+    /// Connected component, if computed with [`Reader::components`](super::reader::Reader::components).
+    pub component: Option<usize>,
 }
 
 impl Default for Node {
@@ -62,6 +62,8 @@ impl Default for Node {
             id: NodeId(0),
             coord: Default::default(),
             uses: Default::default(),
+            elevation: None,
+            component: None,
         }
     }
 }
@@ -70,11 +72,17 @@ impl Default for Node {
 ///
 /// Edges are created by splitting OpenStreetMap ways at intersection points.
 /// Each edge has a unique ID (different from the OSM way ID, which can be shared
-/// by multiple edges if a way is split).
+/// by multiple edges if a way is split). The ID only depends on the way and the
+/// end nodes of the edge (see [`stable_edge_id`] and [`unique_edge_ids`]), so an
+/// edge keeps it when the data is extracted again, even if the way gained
+/// intersections elsewhere.
 #[derive(Clone, Debug)]
 pub struct Edge {
-    /// Unique identifier for this edge, typically "{way_id}-{index}".
-    pub id: String,
+    /// Unique identifier for this edge, from [`stable_edge_id`] and [`unique_edge_ids`].
+    pub id: u64,
+    /// Identifier built from the way and the position of the edge in it,
+    /// typically "{way_id}-{index}"; merged and split edges extend it.
+    pub composite_id: String,
     /// The original OpenStreetMap way ID.
     pub osm_id: WayId,
     /// The starting node of this edge.
@@ -85,10 +93,31 @@ pub struct Edge {
     pub geometry: Vec<Coord>,
     /// Accessibility properties for different transportation modes.
     pub properties: EdgeProperties,
+    /// Number of lanes per direction, width and turn lanes.
+    pub lanes: Lanes,
     /// The sequence of node IDs along this edge (including source and target).
     pub nodes: Vec<NodeId>,
+    /// Distances in meters from the source to each of [`Edge::nodes`], when
+    /// they no longer match the geometry because it was simplified with
+    /// `keep_nodes` (see [`Edge::simplify`]); empty otherwise.
+    pub node_offsets: Vec<f64>,
     /// Additional OSM tags requested by the user.
     pub tags: HashMap<String, String>,
+    /// Time-limited restrictions from `*:conditional` tags.
+    pub conditions: Vec<ConditionalRestriction>,
+    /// Bridges, tunnels, layers and tolls along the edge.
+    ///
+    /// Ranges cover the whole edge and are measured from its source.
+    /// A merged edge can have multiple ranges.
+    pub structures: Vec<StructureRange>,
+    /// Ascent, descent and grades, if an elevation model covers the edge.
+    pub climb: Option<Climb>,
+    /// Connected component, if computed with [`Reader::components`](super::reader::Reader::components).
+    pub component: Option<usize>,
+    /// How the length of the edge is measured, set with [`Reader::distance_model`](super::reader::Reader::distance_model).
+    pub distance_model: DistanceModel,
+    /// Ids of the edges merged into this one, from source to target; empty if not merged.
+    pub merged_ids: Vec<u64>,
 }
 
 impl Hash for Edge {
@@ -97,6 +126,57 @@ impl Hash for Edge {
     }
 }
 
+/// FNV-1a hash of integers, stable across runs and platforms.
+pub(crate) fn fnv1a(values: impl IntoIterator<Item = i64>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for value in values {
+        for byte in value.to_le_bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Id of a synthetic node: a negative FNV-1a hash, stable across runs and platforms.
+///
+/// `path` is the edge read in a fixed direction, so the same point of an edge
+/// and of its reverse get the same id, while two edges of a way with the same
+/// ends, like the halves of a closed way, get different ones.
+fn synthetic_node_id(way: WayId, path: &[i64], position: usize, pieces: usize) -> NodeId {
+    let mut values = vec![way.0, position as i64, pieces as i64];
+    values.extend_from_slice(path);
+    let hash = fnv1a(values);
+    NodeId(-((hash >> 1) as i64) - 1)
+}
+
+/// Id of the edge of a way from `source` to `target`.
+///
+/// It is a FNV-1a hash of the way and node ids, stable across runs and
+/// platforms, and below 2⁶³ so that it fits in a signed 64 bits integer.
+/// The edges of a way going twice from the same node to the same node,
+/// in the same direction, get the same id; [`unique_edge_ids`] then gives
+/// distinct ids to the following ones.
+pub fn stable_edge_id(way: WayId, source: NodeId, target: NodeId) -> u64 {
+    fnv1a([way.0, source.0, target.0]) >> 1
+}
+
+/// Gives a distinct id to each edge sharing its id with a previous edge.
+///
+/// The first edge keeps its id from [`stable_edge_id`]; the `n`-th one
+/// gets a hash of the way, the node ids and `n`. Edges without collision
+/// are unchanged, so their ids stay stable across extractions.
+pub fn unique_edge_ids(edges: &mut [Edge]) {
+    let mut seen = HashSet::with_capacity(edges.len());
+    for edge in edges {
+        let mut occurrence = 0;
+        while !seen.insert(edge.id) {
+            occurrence += 1;
+            edge.id = fnv1a([edge.osm_id.0, edge.source.0, edge.target.0, occurrence]) >> 1;
+        }
+    }
+}
+
 impl PartialEq for Edge {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
@@ -108,14 +188,23 @@ impl Eq for Edge {}
 impl Default for Edge {
     fn default() -> Self {
         Self {
-            id: "".to_string(),
+            id: 0,
+            composite_id: "".to_string(),
             osm_id: WayId(0),
             source: NodeId(1),
             target: NodeId(1),
             geometry: vec![],
             properties: EdgeProperties::default(),
+            lanes: Lanes::default(),
             nodes: vec![],
+            node_offsets: vec![],
             tags: HashMap::default(),
+            conditions: vec![],
+            structures: vec![],
+            climb: None,
+            component: None,
+            distance_model: DistanceModel::Haversine,
+            merged_ids: vec![],
         }
     }
 }
@@ -151,38 +240,316 @@ impl Edge {
 
     /// Calculate the total length of the edge in meters.
     ///
-    /// Sums the great-circle distances between consecutive coordinates.
+    /// Sums the distances between consecutive coordinates, measured with the
+    /// [`distance_model`](Edge::distance_model) of the edge.
     pub fn length(&self) -> f64 {
-        self.geometry
-            .windows(2)
-            .map(|coords| coords[0].distance_to(coords[1]))
-            .sum()
+        self.distance_model.length(&self.geometry)
     }
 
     /// Calculate the length from the start of the edge to a specific node.
     ///
     /// Returns 0.0 if the node is not found on this edge or is the first node.
+    /// Nodes dropped from a simplified geometry are placed with [`Edge::node_offsets`].
     ///
     /// # Arguments
     /// * `node` - The node ID to measure to.
     pub fn length_until(&self, node: &NodeId) -> f64 {
+        self.nodes
+            .iter()
+            .zip(self.offsets())
+            .skip(1)
+            .find(|(id, _)| *id == node)
+            .map_or(0., |(_, offset)| offset)
+    }
+
+    /// Distances from the source to each node, empty if the nodes are not known.
+    fn offsets(&self) -> Vec<f64> {
+        if !self.node_offsets.is_empty() {
+            return self.node_offsets.clone();
+        }
+        if self.nodes.len() != self.geometry.len() {
+            return vec![];
+        }
+        self.geometry_offsets()
+    }
+
+    /// Distances from the source to each coordinate of the geometry.
+    fn geometry_offsets(&self) -> Vec<f64> {
         let mut length = 0.;
-        for i in 1..(self.nodes.len()) {
-            length += self.geometry[i - 1].distance_to(self.geometry[i]);
-            if &self.nodes[i] == node {
-                return length;
+        let mut offsets = vec![0.];
+        for coords in self.geometry.windows(2) {
+            length += self.distance_model.distance(coords[0], coords[1]);
+            offsets.push(length);
+        }
+        offsets
+    }
+
+    /// Returns the part of the geometry between two distances from the start, in meters.
+    ///
+    /// Points are interpolated linearly where the distances fall inside a segment.
+    /// Distances are clamped to the length of the edge.
+    pub fn slice(&self, start: f64, end: f64) -> Vec<Coord> {
+        let interpolate = |a: Coord, b: Coord, t: f64| Coord {
+            x: a.x + (b.x - a.x) * t,
+            y: a.y + (b.y - a.y) * t,
+        };
+        let mut result = Vec::new();
+        let mut offset = 0.;
+        for segment in self.geometry.windows(2) {
+            let length = self.distance_model.distance(segment[0], segment[1]);
+            let next = offset + length;
+            let ratio = |distance: f64| {
+                if length > 0. {
+                    ((distance - offset) / length).clamp(0., 1.)
+                } else {
+                    0.
+                }
+            };
+            if result.is_empty() && start <= next {
+                result.push(interpolate(segment[0], segment[1], ratio(start)));
+            }
+            if !result.is_empty() {
+                if end <= next {
+                    result.push(interpolate(segment[0], segment[1], ratio(end)));
+                    return result;
+                }
+                result.push(segment[1]);
             }
+            offset = next;
         }
-        0.
+        if result.is_empty() {
+            result.extend(self.geometry.last());
+        }
+        if result.len() == 1 {
+            result.push(result[0]);
+        }
+        result
+    }
+
+    /// Splits the edge at a point of a segment, inserting a new node.
+    ///
+    /// The point is inserted after `geometry[segment]`. Both parts keep the
+    /// OSM id, tags and properties; their composite ids are the composite id of
+    /// the edge followed by `.0` and `.1`. Structure ranges are split at the point, and the climb is
+    /// dropped as it cannot be split without the elevation model.
+    ///
+    /// # Panics
+    /// Panics if `segment` is not a segment of the geometry.
+    pub fn split_at(&self, segment: usize, point: Coord, node: NodeId) -> (Edge, Edge) {
+        assert!(segment + 1 < self.geometry.len());
+        let offset = self.distance_model.length(&self.geometry[..=segment])
+            + self.distance_model.distance(self.geometry[segment], point);
+        let (before, after) = split_ranges(&self.structures, offset);
+        let mut first = Edge {
+            id: stable_edge_id(self.osm_id, self.source, node),
+            composite_id: format!("{}.0", self.composite_id),
+            target: node,
+            geometry: [&self.geometry[..=segment], &[point]].concat(),
+            structures: before,
+            climb: None,
+            ..self.clone()
+        };
+        let mut second = Edge {
+            id: stable_edge_id(self.osm_id, node, self.target),
+            composite_id: format!("{}.1", self.composite_id),
+            source: node,
+            geometry: [&[point], &self.geometry[segment + 1..]].concat(),
+            structures: after,
+            climb: None,
+            ..self.clone()
+        };
+        if !self.node_offsets.is_empty() {
+            let cut = self.node_offsets.partition_point(|&o| o < offset);
+            first.nodes = [&self.nodes[..cut], &[node]].concat();
+            second.nodes = [&[node], &self.nodes[cut..]].concat();
+            first.node_offsets = [&self.node_offsets[..cut], &[offset]].concat();
+            second.node_offsets = std::iter::once(0.)
+                .chain(self.node_offsets[cut..].iter().map(|o| o - offset))
+                .collect();
+        } else if self.nodes.len() == self.geometry.len() {
+            first.nodes = [&self.nodes[..=segment], &[node]].concat();
+            second.nodes = [&[node], &self.nodes[segment + 1..]].concat();
+        }
+        (first, second)
+    }
+
+    /// Splits the edge in pieces no longer than `max_length` meters.
+    ///
+    /// The edge is cut in the fewest pieces of equal length, at synthetic nodes
+    /// interpolated along the geometry. Their ids are negative, so they never
+    /// clash with OSM ids, and only depend on the OSM way, the nodes of the edge
+    /// (or its geometry when they are not known) and their position, so they
+    /// are the same from one run to the next and for the reversed edge.
+    /// Pieces are built with [`Edge::split_at`] and their composite ids are the
+    /// composite id of the edge followed by `.0`, `.1`…
+    ///
+    /// Returns the synthetic nodes and the pieces, or only the edge if it is short enough.
+    pub fn subdivide(&self, max_length: f64) -> (Vec<Node>, Vec<Edge>) {
+        let length = self.length();
+        let pieces = (length / max_length).ceil();
+        if !(max_length > 0. && pieces > 1.) {
+            return (vec![], vec![self.clone()]);
+        }
+        let pieces = pieces as usize;
+        let step = length / pieces as f64;
+        let mut path = self.path();
+        let reversed: Vec<i64> = path.iter().rev().copied().collect();
+        let forward = path <= reversed;
+        if !forward {
+            path = reversed;
+        }
+        let mut nodes = Vec::with_capacity(pieces - 1);
+        let mut edges = Vec::with_capacity(pieces);
+        let mut rest = self.clone();
+        for k in 1..pieces {
+            // Counted along the path, so a reversed edge gets the same ids
+            let position = if forward { k } else { pieces - k };
+            let id = synthetic_node_id(self.osm_id, &path, position, pieces);
+            let (segment, point) = rest.locate(step);
+            nodes.push(Node {
+                id,
+                coord: point,
+                uses: 2,
+                ..Default::default()
+            });
+            let (piece, next) = rest.split_at(segment, point, id);
+            edges.push(Edge {
+                composite_id: format!("{}.{}", self.composite_id, k - 1),
+                ..piece
+            });
+            rest = next;
+        }
+        edges.push(Edge {
+            composite_id: format!("{}.{}", self.composite_id, pieces - 1),
+            ..rest
+        });
+        (nodes, edges)
+    }
+
+    /// Ids of the nodes of the edge, or the bits of its coordinates when they are not known.
+    fn path(&self) -> Vec<i64> {
+        if self.nodes.len() == self.geometry.len() {
+            self.nodes.iter().map(|node| node.0).collect()
+        } else {
+            let coords = self.geometry.iter().flat_map(|c| [c.x, c.y]);
+            coords.map(|value| value.to_bits() as i64).collect()
+        }
+    }
+
+    /// Returns the segment and the point at a distance from the start, in meters.
+    fn locate(&self, offset: f64) -> (usize, Coord) {
+        let mut before = 0.;
+        for (segment, coords) in self.geometry.windows(2).enumerate() {
+            let length = self.distance_model.distance(coords[0], coords[1]);
+            if before + length >= offset && length > 0. {
+                let t = ((offset - before) / length).clamp(0., 1.);
+                let point = Coord {
+                    x: coords[0].x + (coords[1].x - coords[0].x) * t,
+                    y: coords[0].y + (coords[1].y - coords[0].y) * t,
+                };
+                return (segment, point);
+            }
+            before += length;
+        }
+        let last = self.geometry.len() - 1;
+        (last.saturating_sub(1), self.geometry[last])
+    }
+
+    /// Simplifies the geometry with Douglas–Peucker, within `tolerance` meters.
+    ///
+    /// The source and target coordinates stay fixed and the simplified line
+    /// does not cross itself. Structure ranges are scaled to the new length.
+    /// With `keep_nodes`, [`Edge::nodes`] keeps the whole OSM node sequence and
+    /// [`Edge::node_offsets`] places each node on the new geometry, the dropped
+    /// ones in proportion of their distance to the remaining ones; otherwise
+    /// it only keeps the nodes of the remaining coordinates.
+    pub fn simplify(&mut self, tolerance: f64, keep_nodes: bool) {
+        let kept = douglas_peucker(&self.geometry, tolerance);
+        if kept.len() == self.geometry.len() {
+            return;
+        }
+        let length = self.length();
+        let before = self.geometry_offsets();
+        let matching = self.nodes.len() == self.geometry.len();
+        if !keep_nodes && matching {
+            self.nodes = kept.iter().map(|&i| self.nodes[i]).collect();
+        }
+        self.geometry = kept.iter().map(|&i| self.geometry[i]).collect();
+        let ratio = if length > 0. {
+            self.length() / length
+        } else {
+            1.
+        };
+        for range in &mut self.structures {
+            range.start *= ratio;
+            range.end *= ratio;
+        }
+        if keep_nodes && matching {
+            let after = self.geometry_offsets();
+            self.node_offsets = before.clone();
+            for (j, pair) in kept.windows(2).enumerate() {
+                let (from, to) = (pair[0], pair[1]);
+                let span = before[to] - before[from];
+                for i in from..=to {
+                    let t = if span > 0. {
+                        (before[i] - before[from]) / span
+                    } else {
+                        0.
+                    };
+                    self.node_offsets[i] = after[j] + t * (after[j + 1] - after[j]);
+                }
+            }
+        } else {
+            for offset in &mut self.node_offsets {
+                *offset *= ratio;
+            }
+        }
+    }
+
+    /// Returns the structural flags of the edge.
+    ///
+    /// Boolean flags are set if they apply to any part of the edge,
+    /// and the layer is the one of the longest range.
+    pub fn structure(&self) -> Structure {
+        let mut result = self
+            .structures
+            .iter()
+            .max_by(|a, b| (a.end - a.start).total_cmp(&(b.end - b.start)))
+            .map(|range| range.structure)
+            .unwrap_or_default();
+        for range in &self.structures {
+            result.bridge |= range.structure.bridge;
+            result.tunnel |= range.structure.tunnel;
+            result.covered |= range.structure.covered;
+            result.toll |= range.structure.toll;
+        }
+        result
     }
 
     /// Returns a new edge with reversed direction.
     ///
-    /// The source and target are swapped, and the geometry and node sequence
-    /// are reversed.
+    /// The source and target are swapped, and the geometry, node sequence,
+    /// structure ranges and climb are reversed. Directional properties, lanes
+    /// and conditional restrictions are swapped, so that the reversed edge
+    /// allows the same movements.
     pub fn reverse(mut self) -> Self {
         self.nodes.reverse();
+        let length = self.length();
+        self.node_offsets.reverse();
+        for offset in &mut self.node_offsets {
+            *offset = length - *offset;
+        }
         self.geometry.reverse();
+        self.merged_ids.reverse();
+        self.properties = self.properties.reverse();
+        self.lanes.reverse();
+        for condition in &mut self.conditions {
+            condition.direction = condition.direction.map(Direction::reverse);
+        }
+        reverse_ranges(&mut self.structures);
+        if let Some(climb) = &mut self.climb {
+            climb.reverse();
+        }
         std::mem::swap(&mut self.target, &mut self.source);
         self
     }
@@ -192,12 +559,51 @@ impl Edge {
     /// # Panics
     /// Panics if `self.target != other.source`.
     fn unsafe_merge(mut self, other: Self) -> Self {
+        self.append(other);
+        self
+    }
+
+    /// Appends an edge starting at the target of this one, in place.
+    ///
+    /// The composite id becomes both composite ids joined by `-`, the id is
+    /// computed from the new ends, and both ids are recorded in [`Edge::merged_ids`]. Vectors are extended, so merging a chain edge by
+    /// edge takes linear time.
+    ///
+    /// # Panics
+    /// Panics if `self.target != other.source`.
+    pub(crate) fn append(&mut self, other: Self) {
         assert!(self.target == other.source);
-        self.id = format!("{}-{}", self.id, other.id);
+        if !self.node_offsets.is_empty() || !other.node_offsets.is_empty() {
+            let length = self.length();
+            self.node_offsets = self.offsets();
+            let next = other.offsets();
+            let next = next.get(1..).unwrap_or_default();
+            self.node_offsets.extend(next.iter().map(|o| o + length));
+        }
+        if self.merged_ids.is_empty() {
+            self.merged_ids.push(self.id);
+        }
+        if other.merged_ids.is_empty() {
+            self.merged_ids.push(other.id);
+        } else {
+            self.merged_ids.extend(other.merged_ids);
+        }
+        self.composite_id.push('-');
+        self.composite_id.push_str(&other.composite_id);
         self.target = other.target;
-        self.nodes = [&self.nodes, &other.nodes[1..]].concat();
-        self.geometry = [&self.geometry, &other.geometry[1..]].concat();
-        self
+        self.id = stable_edge_id(self.osm_id, self.source, self.target);
+        self.nodes
+            .extend_from_slice(other.nodes.get(1..).unwrap_or_default());
+        self.geometry
+            .extend_from_slice(other.geometry.get(1..).unwrap_or_default());
+        concat_ranges(&mut self.structures, &other.structures);
+        self.climb = match (self.climb, other.climb) {
+            (Some(mut climb), Some(other)) => {
+                climb.concat(&other);
+                Some(climb)
+            }
+            _ => None,
+        };
     }
 
     /// Creates a new edge by stitching together two edges at a common node.
@@ -266,3 +672,248 @@ fn test_length_until() {
     assert_eq!(e.length_until(&NodeId(0)), 0.);
     assert!((1. - e.length_until(&NodeId(2)) / (2. * 1853. * 60.)).abs() < 0.01);
 }
+
+#[test]
+fn test_unique_edge_ids() {
+    // A way going twice from node 1 to node 2
+    let edge = |source: i64, target: i64| Edge {
+        id: stable_edge_id(WayId(1), NodeId(source), NodeId(target)),
+        osm_id: WayId(1),
+        source: NodeId(source),
+        target: NodeId(target),
+        ..Default::default()
+    };
+    let mut edges = vec![edge(1, 2), edge(2, 1), edge(1, 2), edge(1, 2)];
+    unique_edge_ids(&mut edges);
+    assert_eq!(stable_edge_id(WayId(1), NodeId(1), NodeId(2)), edges[0].id);
+    assert_eq!(stable_edge_id(WayId(1), NodeId(2), NodeId(1)), edges[1].id);
+    let ids: HashSet<u64> = edges.iter().map(|edge| edge.id).collect();
+    assert_eq!(4, ids.len());
+}
+
+#[test]
+fn test_split_at() {
+    let e = Edge {
+        composite_id: "1-0".to_string(),
+        source: NodeId(0),
+        target: NodeId(2),
+        nodes: vec![NodeId(0), NodeId(1), NodeId(2)],
+        geometry: vec![
+            Coord { x: 0., y: 0. },
+            Coord { x: 1., y: 0. },
+            Coord { x: 1., y: 1. },
+        ],
+        ..Default::default()
+    };
+    let point = Coord { x: 1., y: 0.5 };
+    let (first, second) = e.split_at(1, point, NodeId(-1));
+    assert_eq!("1-0.0", first.composite_id);
+    assert_eq!((NodeId(0), NodeId(-1)), (first.source, first.target));
+    assert_eq!(vec![NodeId(0), NodeId(1), NodeId(-1)], first.nodes);
+    assert_eq!(vec![e.geometry[0], e.geometry[1], point], first.geometry);
+    assert_eq!((NodeId(-1), NodeId(2)), (second.source, second.target));
+    assert_eq!(vec![point, e.geometry[2]], second.geometry);
+    assert!((first.length() + second.length() - e.length()).abs() < 1e-6);
+}
+
+#[test]
+fn test_slice() {
+    let e = Edge {
+        geometry: vec![
+            Coord { x: 0., y: 0. },
+            Coord { x: 1., y: 0. },
+            Coord { x: 1., y: 1. },
+        ],
+        ..Default::default()
+    };
+    let half = e.length() / 2.;
+    assert_eq!(e.geometry, e.slice(0., e.length()));
+    assert_eq!(e.geometry[..2], e.slice(0., half));
+
+    let middle = e.slice(half / 2., half * 1.5);
+    assert_eq!(3, middle.len());
+    assert!((middle[0].x - 0.5).abs() < 1e-9);
+    assert_eq!(Coord { x: 1., y: 0. }, middle[1]);
+    assert!((middle[2].y - 0.5).abs() < 1e-3);
+
+    assert_eq!(vec![e.geometry[2]; 2], e.slice(3. * half, 4. * half));
+}
+
+#[test]
+fn test_merge_structures() {
+    let tunnel = Structure {
+        tunnel: true,
+        ..Default::default()
+    };
+    let e1 = Edge {
+        composite_id: "1-0".to_string(),
+        source: NodeId(0),
+        target: NodeId(1),
+        nodes: vec![NodeId(0), NodeId(1)],
+        geometry: vec![Coord { x: 0., y: 0. }, Coord { x: 1., y: 0. }],
+        structures: vec![StructureRange {
+            start: 0.,
+            end: 100.,
+            structure: Structure::default(),
+        }],
+        ..Default::default()
+    };
+    let e2 = Edge {
+        composite_id: "2-0".to_string(),
+        source: NodeId(2),
+        target: NodeId(1),
+        nodes: vec![NodeId(2), NodeId(1)],
+        geometry: vec![Coord { x: 2., y: 0. }, Coord { x: 1., y: 0. }],
+        structures: vec![
+            StructureRange {
+                start: 0.,
+                end: 20.,
+                structure: Structure::default(),
+            },
+            StructureRange {
+                start: 20.,
+                end: 100.,
+                structure: tunnel,
+            },
+        ],
+        ..Default::default()
+    };
+    let merged = Edge::merge(&e1, &e2, NodeId(1));
+    assert_eq!(NodeId(2), merged.target);
+    assert_eq!(3, merged.structures.len());
+    assert_eq!(tunnel, merged.structures[1].structure);
+    assert_eq!(
+        (100., 180.),
+        (merged.structures[1].start, merged.structures[1].end)
+    );
+    assert!(merged.structure().tunnel);
+}
+
+#[test]
+fn test_simplify() {
+    let mut edge = Edge {
+        nodes: vec![NodeId(1), NodeId(2), NodeId(3)],
+        geometry: vec![
+            Coord { x: 0., y: 0. },
+            Coord {
+                x: 0.001,
+                y: 0.000_001,
+            },
+            Coord { x: 0.002, y: 0. },
+        ],
+        structures: vec![StructureRange {
+            start: 0.,
+            end: 0.,
+            structure: Structure::default(),
+        }],
+        ..Default::default()
+    };
+    edge.structures[0].end = edge.length();
+
+    let mut simplified = edge.clone();
+    simplified.simplify(1., false);
+    assert_eq!(vec![NodeId(1), NodeId(3)], simplified.nodes);
+    assert_eq!(simplified.length(), simplified.length_until(&NodeId(3)));
+    assert_eq!(
+        vec![Coord { x: 0., y: 0. }, Coord { x: 0.002, y: 0. }],
+        simplified.geometry
+    );
+    assert!((simplified.structures[0].end - simplified.length()).abs() < 1e-6);
+
+    let mut kept = edge.clone();
+    kept.simplify(1., true);
+    assert_eq!(edge.nodes, kept.nodes);
+    assert_eq!(2, kept.geometry.len());
+    assert_eq!(kept.length(), kept.length_until(&NodeId(3)));
+    assert!((kept.length_until(&NodeId(2)) - kept.length() / 2.).abs() < 0.01);
+    let reversed = kept.clone().reverse();
+    assert!((reversed.length_until(&NodeId(2)) - kept.length() / 2.).abs() < 0.01);
+    assert_eq!(kept.length(), reversed.length_until(&NodeId(1)));
+    let (first, second) = kept.split_at(0, Coord { x: 0.0015, y: 0. }, NodeId(-1));
+    assert_eq!(vec![NodeId(1), NodeId(2), NodeId(-1)], first.nodes);
+    assert_eq!(vec![NodeId(-1), NodeId(3)], second.nodes);
+    assert!((second.length_until(&NodeId(3)) - second.length()).abs() < 1e-6);
+
+    let mut unchanged = edge.clone();
+    unchanged.simplify(0.01, false);
+    assert_eq!(edge.geometry, unchanged.geometry);
+}
+
+#[test]
+fn test_subdivide() {
+    let edge = Edge {
+        composite_id: "12-0".to_string(),
+        osm_id: WayId(12),
+        source: NodeId(1),
+        target: NodeId(2),
+        nodes: vec![NodeId(1), NodeId(3), NodeId(2)],
+        geometry: vec![
+            Coord { x: 0., y: 0. },
+            Coord { x: 0.0015, y: 0. },
+            Coord { x: 0.003, y: 0. },
+        ],
+        ..Default::default()
+    };
+    let length = edge.length();
+
+    let (nodes, pieces) = edge.subdivide(120.);
+    assert_eq!(3, pieces.len());
+    assert_eq!(2, nodes.len());
+    assert!(nodes.iter().all(|node| node.id.0 < 0));
+    assert_ne!(nodes[0].id, nodes[1].id);
+    assert_eq!(
+        vec!["12-0.0", "12-0.1", "12-0.2"],
+        pieces
+            .iter()
+            .map(|e| e.composite_id.as_str())
+            .collect::<Vec<_>>()
+    );
+    for piece in &pieces {
+        assert_eq!(WayId(12), piece.osm_id);
+        assert!((piece.length() - length / 3.).abs() < 1e-6);
+    }
+    assert_eq!(
+        (NodeId(1), nodes[0].id, nodes[1].id),
+        (pieces[0].source, pieces[1].source, pieces[2].source)
+    );
+    assert_eq!(vec![nodes[0].id, NodeId(3), nodes[1].id], pieces[1].nodes);
+    assert_eq!(
+        stable_edge_id(WayId(12), nodes[0].id, nodes[1].id),
+        pieces[1].id
+    );
+
+    // Same ids for the reversed edge, and from one run to the next
+    let (reversed, _) = edge.clone().reverse().subdivide(120.);
+    assert_eq!(
+        vec![nodes[1].id, nodes[0].id],
+        reversed.iter().map(|n| n.id).collect::<Vec<_>>()
+    );
+
+    // The other half of a closed way has the same ends but its own nodes
+    let other_half = Edge {
+        composite_id: "12-1".to_string(),
+        source: NodeId(2),
+        target: NodeId(1),
+        nodes: vec![NodeId(2), NodeId(4), NodeId(1)],
+        geometry: vec![
+            Coord { x: 0.003, y: 0. },
+            Coord {
+                x: 0.0015,
+                y: 0.001,
+            },
+            Coord { x: 0., y: 0. },
+        ],
+        ..edge.clone()
+    };
+    let (others, _) = other_half.subdivide(120.);
+    assert!(!others.is_empty());
+    assert!(
+        others
+            .iter()
+            .all(|other| nodes.iter().all(|n| n.id != other.id))
+    );
+
+    let (nodes, pieces) = edge.subdivide(1_000.);
+    assert!(nodes.is_empty());
+    assert_eq!(vec![edge], pieces);
+}
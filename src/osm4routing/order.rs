@@ -0,0 +1,188 @@
+//! Order of the nodes and edges in the output.
+//!
+//! By default, nodes are sorted by id and edges by OSM way, in the order of
+//! the way, so that two extractions of the same data give the same files.
+//! The Hilbert curve instead keeps close elements close in the output, which
+//! speeds up loading them into databases or cutting them into tiles.
+
+use super::models::*;
+use std::str::FromStr;
+
+type Coord = geo_types::Coord<f64>;
+
+/// Number of cells of the Hilbert grid along each axis, as a power of two.
+const HILBERT_BITS: u32 = 31;
+
+/// How the nodes and edges returned by the [`Reader`](super::reader::Reader) are ordered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Order {
+    /// Nodes by id, edges by OSM way id and then from the start of the way,
+    /// by the indices of their composite id, and by id when they are equal.
+    #[default]
+    Id,
+    /// Along a Hilbert curve covering the world, nodes by their coordinates
+    /// and edges by the middle of their ends; ties are ordered like [`Order::Id`].
+    Hilbert,
+}
+
+impl Order {
+    /// Sorts the nodes and edges.
+    ///
+    /// The order does not depend on the order of the input, which merging
+    /// and splitting edges do not keep.
+    pub fn sort(self, nodes: &mut [Node], edges: &mut [Edge]) {
+        match self {
+            Order::Id => {
+                nodes.sort_by_key(|node| node.id);
+                edges.sort_by_cached_key(way_position);
+            }
+            Order::Hilbert => {
+                nodes.sort_by_cached_key(|node| (hilbert_index(node.coord), node.id));
+                edges.sort_by_cached_key(|edge| {
+                    let (first, last) = match (edge.geometry.first(), edge.geometry.last()) {
+                        (Some(first), Some(last)) => (*first, *last),
+                        _ => (Coord::default(), Coord::default()),
+                    };
+                    let middle = Coord {
+                        x: (first.x + last.x) / 2.,
+                        y: (first.y + last.y) / 2.,
+                    };
+                    (hilbert_index(middle), way_position(edge))
+                });
+            }
+        }
+    }
+}
+
+/// The way of an edge, its position in the way and its id.
+///
+/// The position is read from the numbers of the composite id: `12-3` is
+/// the fourth edge of way 12, `12-3.1` the second piece of it, and `12-3-12-4`
+/// the merge of the fourth and fifth edges, which all sort in this order.
+fn way_position(edge: &Edge) -> (WayId, Vec<u64>, u64) {
+    let position = edge
+        .composite_id
+        .split(['-', '.'])
+        .filter_map(|number| number.parse().ok())
+        .collect();
+    (edge.osm_id, position, edge.id)
+}
+
+impl FromStr for Order {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(Order::Id),
+            "hilbert" => Ok(Order::Hilbert),
+            _ => Err(format!("unknown order {s}, expected id or hilbert")),
+        }
+    }
+}
+
+/// Position of a WGS84 coordinate along a Hilbert curve covering the world.
+///
+/// The world is cut in a grid of 2³¹ by 2³¹ cells, about 2 cm wide at the
+/// equator, so the position does not depend on the extent of the data.
+pub fn hilbert_index(coord: Coord) -> u64 {
+    let side = 1u64 << HILBERT_BITS;
+    let cell = |value: f64, min: f64, max: f64| {
+        let ratio = ((value - min) / (max - min)).clamp(0., 1.);
+        ((ratio * side as f64) as u64).min(side - 1)
+    };
+    let (mut x, mut y) = (cell(coord.x, -180., 180.), cell(coord.y, -90., 90.));
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = u64::from(x & s > 0);
+        let ry = u64::from(y & s > 0);
+        index += s * s * ((3 * rx) ^ ry);
+        // Rotates the quadrant so that the curve is continuous
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+#[test]
+fn test_hilbert_index() {
+    let c = |x: f64, y: f64| Coord { x, y };
+    // The curve starts in the south-west corner and ends in the south-east one
+    assert_eq!(0, hilbert_index(c(-180., -90.)));
+    assert_eq!((1 << 62) - 1, hilbert_index(c(180., -90.)));
+    // Neighbouring points are close on the curve
+    let a = hilbert_index(c(2.35, 48.85));
+    let b = hilbert_index(c(2.35001, 48.85001));
+    let far = hilbert_index(c(-73.98, 40.75));
+    assert!(a.abs_diff(b) < a.abs_diff(far));
+}
+
+#[test]
+fn test_sort() {
+    let node = |id: i64, x: f64| Node {
+        id: NodeId(id),
+        coord: Coord { x, y: 0. },
+        ..Default::default()
+    };
+    let edge = |composite_id: &str, way: i64, x: f64| Edge {
+        composite_id: composite_id.to_string(),
+        osm_id: WayId(way),
+        geometry: vec![Coord { x, y: 0. }, Coord { x, y: 0. }],
+        ..Default::default()
+    };
+    let mut nodes = vec![node(3, 0.), node(1, 10.), node(2, -10.)];
+    let mut edges = vec![
+        edge("2-1", 2, -10.),
+        edge("2-0", 2, 0.),
+        edge("1-0", 1, 10.),
+    ];
+
+    Order::Id.sort(&mut nodes, &mut edges);
+    assert_eq!(
+        vec![NodeId(1), NodeId(2), NodeId(3)],
+        nodes.iter().map(|n| n.id).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["1-0", "2-0", "2-1"],
+        edges
+            .iter()
+            .map(|e| e.composite_id.as_str())
+            .collect::<Vec<_>>()
+    );
+
+    Order::Hilbert.sort(&mut nodes, &mut edges);
+    assert_eq!(
+        vec![NodeId(2), NodeId(3), NodeId(1)],
+        nodes.iter().map(|n| n.id).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["2-1", "2-0", "1-0"],
+        edges
+            .iter()
+            .map(|e| e.composite_id.as_str())
+            .collect::<Vec<_>>()
+    );
+
+    // Pieces and merged edges follow the way, whatever the input order
+    let mut edges = vec![
+        edge("2-10", 2, 0.),
+        edge("2-1.1", 2, 0.),
+        edge("2-1-2-2", 2, 0.),
+        edge("2-1.0", 2, 0.),
+        edge("2-9", 2, 0.),
+    ];
+    Order::Id.sort(&mut nodes, &mut edges);
+    assert_eq!(
+        vec!["2-1.0", "2-1.1", "2-1-2-2", "2-9", "2-10"],
+        edges
+            .iter()
+            .map(|e| e.composite_id.as_str())
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,238 @@
+//! Surface-aware routing profiles.
+//!
+//! A profile describes which surfaces a given user (e.g. a road bike or a
+//! wheelchair) can travel on, and how much less desirable the others are.
+
+use super::categorize::*;
+use ahash::{HashMap, HashSet};
+
+/// Surface constraints and penalties for one transportation mode.
+///
+/// A profile can be passed to [`crate::Reader::profile`] to forbid the mode on
+/// ways it rejects, and to [`crate::Weight::Profile`] to avoid them and
+/// down-rank the remaining ones by their [`Profile::factor`] when routing.
+/// A profile of only some users of a mode, like [`Profile::wheelchair`], is
+/// [`Profile::routing_only`]: it never forbids the mode.
+///
+/// # Example
+///
+/// ```
+/// use osm4routing::{Mode, Profile, Smoothness, Surface};
+///
+/// let profile = Profile::new(Mode::Bike)
+///     .reject_surface(Surface::Sand)
+///     .max_smoothness(Smoothness::Bad)
+///     .surface_factor(Surface::Gravel, 1.5);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Profile {
+    /// The transportation mode this profile applies to.
+    pub mode: Mode,
+    /// Surfaces that cannot be used at all.
+    rejected_surfaces: HashSet<Surface>,
+    /// Worst acceptable smoothness.
+    max_smoothness: Option<Smoothness>,
+    /// Worst acceptable track grade.
+    max_tracktype: Option<TrackType>,
+    /// Hardest acceptable mountain bike difficulty.
+    max_mtb_scale: Option<MtbScale>,
+    /// Cost multipliers per surface.
+    surface_factors: HashMap<Surface, f64>,
+    /// Cost multipliers per smoothness.
+    smoothness_factors: HashMap<Smoothness, f64>,
+    /// Whether the profile is only used by routing weights.
+    routing_only: bool,
+}
+
+impl Profile {
+    /// Creates a profile that accepts every surface without penalty.
+    pub fn new(mode: Mode) -> Profile {
+        Profile {
+            mode,
+            rejected_surfaces: HashSet::default(),
+            max_smoothness: None,
+            max_tracktype: None,
+            max_mtb_scale: None,
+            surface_factors: HashMap::default(),
+            smoothness_factors: HashMap::default(),
+            routing_only: false,
+        }
+    }
+
+    /// A general purpose bike profile.
+    ///
+    /// Rejects ways that are only usable by off-road vehicles and
+    /// penalizes unpaved surfaces.
+    pub fn bike() -> Profile {
+        Profile::new(Mode::Bike)
+            .reject_surface(Surface::Sand)
+            .max_smoothness(Smoothness::VeryBad)
+            .max_tracktype(TrackType::Grade4)
+            .max_mtb_scale(MtbScale::S1)
+            .surface_factor(Surface::Sett, 1.3)
+            .surface_factor(Surface::Compacted, 1.2)
+            .surface_factor(Surface::FineGravel, 1.3)
+            .surface_factor(Surface::Unpaved, 1.5)
+            .surface_factor(Surface::Gravel, 1.8)
+            .surface_factor(Surface::Ground, 2.0)
+            .surface_factor(Surface::Grass, 2.5)
+            .smoothness_factor(Smoothness::Bad, 1.3)
+            .smoothness_factor(Smoothness::VeryBad, 1.8)
+    }
+
+    /// A wheelchair profile, for routing on pedestrian ways.
+    ///
+    /// Only accepts ways that are at least of intermediate smoothness
+    /// and rejects loose surfaces. As other pedestrians can use the ways it
+    /// rejects, it is [`Profile::routing_only`]: use it with
+    /// [`crate::Weight::Profile`], as [`crate::Reader::profile`] ignores it.
+    pub fn wheelchair() -> Profile {
+        Profile::new(Mode::Foot)
+            .routing_only()
+            .reject_surface(Surface::Gravel)
+            .reject_surface(Surface::Ground)
+            .reject_surface(Surface::Grass)
+            .reject_surface(Surface::Sand)
+            .max_smoothness(Smoothness::Intermediate)
+            .max_tracktype(TrackType::Grade2)
+            .max_mtb_scale(MtbScale::S0)
+            .surface_factor(Surface::Sett, 2.0)
+            .surface_factor(Surface::Unpaved, 1.5)
+            .surface_factor(Surface::Compacted, 1.3)
+            .surface_factor(Surface::FineGravel, 1.5)
+    }
+
+    /// Only uses the profile in routing weights, for a profile that only
+    /// covers some of the users of its mode.
+    ///
+    /// [`Profile::apply`], hence [`crate::Reader::profile`], then leaves the
+    /// mode allowed on the ways the profile rejects, while
+    /// [`crate::Weight::Profile`] still avoids them.
+    pub fn routing_only(mut self) -> Self {
+        self.routing_only = true;
+        self
+    }
+
+    /// Rejects ways with the given surface.
+    pub fn reject_surface(mut self, surface: Surface) -> Self {
+        self.rejected_surfaces.insert(surface);
+        self
+    }
+
+    /// Rejects ways whose smoothness is worse than the given one.
+    pub fn max_smoothness(mut self, smoothness: Smoothness) -> Self {
+        self.max_smoothness = Some(smoothness);
+        self
+    }
+
+    /// Rejects tracks whose grade is softer than the given one.
+    pub fn max_tracktype(mut self, tracktype: TrackType) -> Self {
+        self.max_tracktype = Some(tracktype);
+        self
+    }
+
+    /// Rejects ways whose mountain bike difficulty is above the given one.
+    pub fn max_mtb_scale(mut self, mtb_scale: MtbScale) -> Self {
+        self.max_mtb_scale = Some(mtb_scale);
+        self
+    }
+
+    /// Multiplies the cost of ways with the given surface.
+    pub fn surface_factor(mut self, surface: Surface, factor: f64) -> Self {
+        self.surface_factors.insert(surface, factor);
+        self
+    }
+
+    /// Multiplies the cost of ways with the given smoothness.
+    pub fn smoothness_factor(mut self, smoothness: Smoothness, factor: f64) -> Self {
+        self.smoothness_factors.insert(smoothness, factor);
+        self
+    }
+
+    /// Returns true if the way can be used with this profile.
+    ///
+    /// Unknown values are always accepted, as they sort before any other value.
+    pub fn accepts(&self, properties: &EdgeProperties) -> bool {
+        !self.rejected_surfaces.contains(&properties.surface)
+            && self
+                .max_smoothness
+                .is_none_or(|max| properties.smoothness <= max)
+            && self
+                .max_tracktype
+                .is_none_or(|max| properties.tracktype <= max)
+            && self
+                .max_mtb_scale
+                .is_none_or(|max| properties.mtb_scale <= max)
+    }
+
+    /// Cost multiplier of the way for this profile (1.0 means no penalty).
+    pub fn factor(&self, properties: &EdgeProperties) -> f64 {
+        self.surface_factors
+            .get(&properties.surface)
+            .copied()
+            .unwrap_or(1.0)
+            * self
+                .smoothness_factors
+                .get(&properties.smoothness)
+                .copied()
+                .unwrap_or(1.0)
+    }
+
+    /// Forbids the profile's mode on the way if it is not accepted,
+    /// unless the profile is [`Profile::routing_only`].
+    pub fn apply(&self, properties: &mut EdgeProperties) {
+        if !self.routing_only && !self.accepts(properties) {
+            properties.forbid(self.mode);
+        }
+    }
+}
+
+#[test]
+fn test_accepts() {
+    let profile = Profile::wheelchair();
+    let mut p = EdgeProperties::default();
+    assert!(profile.accepts(&p));
+
+    p.update_with_str("surface", "asphalt");
+    p.update_with_str("smoothness", "good");
+    assert!(profile.accepts(&p));
+
+    p.update_with_str("smoothness", "bad");
+    assert!(!profile.accepts(&p));
+
+    p.update_with_str("smoothness", "good");
+    p.update_with_str("surface", "dirt");
+    assert!(!profile.accepts(&p));
+}
+
+#[test]
+fn test_factor() {
+    let profile = Profile::bike();
+    let mut p = EdgeProperties::default();
+    assert_eq!(1.0, profile.factor(&p));
+
+    p.update_with_str("surface", "gravel");
+    p.update_with_str("smoothness", "bad");
+    assert!((profile.factor(&p) - 1.8 * 1.3).abs() < 1e-9);
+}
+
+#[test]
+fn test_apply() {
+    let mut p = EdgeProperties::default();
+    p.update_with_str("highway", "track");
+    p.update_with_str("tracktype", "grade5");
+    p.normalize();
+    Profile::bike().apply(&mut p);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_forward);
+    assert_eq!(BikeAccessibility::Forbidden, p.bike_backward);
+    assert_eq!(CarAccessibility::Residential, p.car_forward);
+
+    // Pedestrians can still walk where wheelchairs cannot go
+    let mut p = EdgeProperties::default();
+    p.update_with_str("highway", "footway");
+    p.update_with_str("surface", "gravel");
+    p.normalize();
+    Profile::wheelchair().apply(&mut p);
+    assert_eq!(FootAccessibility::Allowed, p.foot);
+    assert!(!Profile::wheelchair().accepts(&p));
+}
@@ -4,11 +4,23 @@
 //! and convert them into a routing graph structure (nodes and edges).
 
 use super::categorize::*;
+use super::components::{Components, Connectivity};
+use super::conditional::{ConditionalRestriction, LocalTime};
+use super::country::*;
+use super::csr::{CsrBuilder, CsrGraph};
+use super::distance::DistanceModel;
+use super::elevation::Dem;
 use super::error::Error;
+use super::graph::Graph;
+use super::lanes::Lanes;
+use super::merge::{MergeRules, TagPolicy, merge_chains};
 use super::models::*;
-use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use super::order::Order;
+use super::profile::Profile;
+use super::structure::{Structure, StructureRange};
+use ahash::{HashMap, HashMapExt, HashSet};
 use osmpbfreader::objects::{NodeId, WayId};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Internal representation of an OpenStreetMap way during processing.
 ///
@@ -20,8 +32,18 @@ struct Way {
     nodes: Vec<NodeId>,
     /// Computed accessibility properties from OSM tags.
     properties: EdgeProperties,
+    /// Properties read from OSM tags, before defaults are applied.
+    tagged: EdgeProperties,
+    /// Tags of the way that country default access depends on.
+    access: DefaultAccess,
+    /// Lanes per direction computed from OSM tags.
+    lanes: Lanes,
+    /// Bridge, tunnel, layer and toll flags.
+    structure: Structure,
     /// Tags requested to be preserved (via `read_tag`).
     tags: HashMap<String, String>,
+    /// Restrictions parsed from `*:conditional` tags.
+    conditions: Vec<ConditionalRestriction>,
 }
 
 impl Default for Way {
@@ -30,7 +52,12 @@ impl Default for Way {
             id: WayId(0),
             nodes: vec![],
             properties: EdgeProperties::default(),
+            tagged: EdgeProperties::default(),
+            access: DefaultAccess::default(),
+            lanes: Lanes::default(),
+            structure: Structure::default(),
             tags: HashMap::default(),
+            conditions: vec![],
         }
     }
 }
@@ -66,6 +93,49 @@ pub struct Reader {
     tags_to_read: HashSet<String>,
     /// Whether to merge consecutive edges from different ways at non-intersections.
     should_merge_ways: bool,
+    /// Whether every node of a way is a vertex, with one edge per pair of consecutive nodes.
+    should_keep_all_nodes: bool,
+    /// Surface profiles that can forbid a mode on some ways.
+    profiles: Vec<Profile>,
+    /// Local time at which conditional restrictions are evaluated.
+    time: Option<LocalTime>,
+    /// Whether ways that only a conditional restriction opens are kept.
+    should_keep_conditional_ways: bool,
+    /// Whether merging can join edges with different structural flags.
+    should_merge_across_structures: bool,
+    /// How tags are merged, [`TagPolicy::MustMatch`] for the tags without a policy.
+    tag_policies: HashMap<String, TagPolicy>,
+    /// Default access rules of the country, worldwide defaults if `None`.
+    access_rules: Option<&'static AccessRules>,
+    /// Whether the country of each way is derived from national boundaries.
+    should_detect_country: bool,
+    /// Member ways of the national boundaries, by country.
+    country_boundaries: Vec<(&'static str, Vec<WayId>)>,
+    /// Nodes of the ways that are part of a national boundary.
+    boundary_ways: HashMap<WayId, Vec<NodeId>>,
+    /// Elevation tile or directory of tiles.
+    elevation: Option<PathBuf>,
+    /// Mode (every mode if `None`) and connectivity of the connected components.
+    components: Option<(Option<Mode>, Connectivity)>,
+    /// Which connected components are kept.
+    component_filter: Option<ComponentFilter>,
+    /// How the lengths of the edges are measured.
+    distance_model: DistanceModel,
+    /// Tolerance in meters of the simplification, and whether edges keep all their nodes.
+    simplification: Option<(f64, bool)>,
+    /// Length in meters above which edges are split.
+    max_edge_length: Option<f64>,
+    /// Order of the nodes and edges in the output.
+    order: Order,
+}
+
+/// Connected components kept by the [`Reader`].
+#[derive(Clone, Copy, Debug)]
+enum ComponentFilter {
+    /// Only the component with the most nodes.
+    Largest,
+    /// Components with at least this many nodes and meters.
+    AtLeast { nodes: usize, length: f64 },
 }
 
 impl Reader {
@@ -171,6 +241,487 @@ impl Reader {
         self
     }
 
+    /// Sets how a tag kept with [`Reader::read_tag`] is handled by [`Reader::merge_ways`].
+    ///
+    /// By default, edges with different values of a tag are not merged
+    /// ([`TagPolicy::MustMatch`]). The merged edge lists the ids of the original
+    /// edges in [`Edge::merged_ids`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::{Reader, TagPolicy};
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .read_tag("name")
+    ///     .read_tag("maxspeed")
+    ///     .merge_ways()
+    ///     .merge_policy("name", TagPolicy::Concatenate)
+    ///     .merge_policy("maxspeed", TagPolicy::Drop)
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn merge_policy(mut self, key: &str, policy: TagPolicy) -> Self {
+        self.tag_policies.insert(key.to_string(), policy);
+        self
+    }
+
+    /// Keeps every OSM node of the ways as a vertex of the graph.
+    ///
+    /// Ways are split at each node instead of at intersections only, giving one
+    /// edge per pair of consecutive OSM nodes, and all the nodes of the ways
+    /// are returned. [`Reader::merge_ways`] is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .keep_all_nodes()
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    /// assert!(edges.iter().all(|edge| edge.geometry.len() == 2));
+    /// ```
+    pub fn keep_all_nodes(mut self) -> Self {
+        self.should_keep_all_nodes = true;
+        self
+    }
+
+    /// Allows merging edges whose bridge, tunnel, layer, covered or toll flags differ.
+    ///
+    /// Only meaningful with [`Reader::merge_ways`]. By default, edges are not
+    /// merged across a change of those flags. With this option, the merged
+    /// edge keeps one [`StructureRange`] per stretch in [`Edge::structures`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .merge_ways()
+    ///     .merge_across_structures()
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    ///
+    /// for range in &edges[0].structures {
+    ///     if range.structure.tunnel {
+    ///         println!("Tunnel from {}m to {}m", range.start, range.end);
+    ///     }
+    /// }
+    /// ```
+    pub fn merge_across_structures(mut self) -> Self {
+        self.should_merge_across_structures = true;
+        self
+    }
+
+    /// Applies a surface profile to the extracted ways.
+    ///
+    /// Ways rejected by the profile are forbidden for the profile's mode.
+    /// Other modes are unaffected. Can be chained to apply multiple profiles.
+    /// A [`Profile::routing_only`] profile, like [`Profile::wheelchair`],
+    /// changes nothing here: pass it to [`Weight::Profile`](super::routing::Weight::Profile)
+    /// instead.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::{Profile, Reader};
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .profile(Profile::bike())
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profiles.push(profile);
+        self
+    }
+
+    /// Evaluates conditional restrictions at the given local time.
+    ///
+    /// By default, `*:conditional` tags are only parsed and stored in
+    /// [`Edge::conditions`]. With this option, the restrictions active at the
+    /// given time are also applied to the edge properties.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::{LocalTime, Reader};
+    ///
+    /// // A Monday of May, at 8:00
+    /// let (nodes, edges) = Reader::new()
+    ///     .at_time(LocalTime::new(5, 0, 8, 0).unwrap())
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn at_time(mut self, time: LocalTime) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Keeps the ways that only a conditional restriction opens.
+    ///
+    /// By default, such ways are dropped, like any inaccessible way; with
+    /// [`Reader::at_time`], they are only kept while their restriction is
+    /// active. With this option, they are always kept, so that their
+    /// [`Edge::conditions`] can be evaluated later: when the restriction is
+    /// not applied, every mode is forbidden in the edge properties, which is
+    /// all that the CSV writer gets.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .keep_conditional_ways()
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn keep_conditional_ways(mut self) -> Self {
+        self.should_keep_conditional_ways = true;
+        self
+    }
+
+    /// Uses the default access rules of a country.
+    ///
+    /// For instance, bikes are allowed on `highway=trunk` in Germany but not
+    /// in the Netherlands. Explicit `foot` and `bicycle` tags always win.
+    /// Unknown country codes keep the worldwide defaults.
+    ///
+    /// # Arguments
+    /// * `country` - ISO 3166-1 alpha-2 code (e.g. `"DE"`).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .country("NL")
+    ///     .read("netherlands.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn country(mut self, country: &str) -> Self {
+        self.access_rules = access_rules(country);
+        self
+    }
+
+    /// Derives the default access rules of each way from the national boundary it falls in.
+    ///
+    /// Boundaries are `admin_level=2` relations of the extract; the file is read
+    /// one more time to find them. Ways outside of any boundary use the rules set
+    /// by [`Reader::country`], or the worldwide defaults.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .country_from_boundaries()
+    ///     .read("benelux.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn country_from_boundaries(mut self) -> Self {
+        self.should_detect_country = true;
+        self
+    }
+
+    /// Samples elevations from local SRTM `.hgt` or GeoTIFF tiles.
+    ///
+    /// Sets [`Node::elevation`] and [`Edge::climb`] (ascent, descent and
+    /// max grade per direction) where the tiles cover the graph.
+    ///
+    /// # Arguments
+    /// * `path` - A tile, or a directory containing the tiles.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .elevation("srtm/")
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    ///
+    /// if let Some(climb) = edges[0].climb {
+    ///     println!("Ascent: {}m, max grade: {}%", climb.ascent, climb.max_grade_forward);
+    /// }
+    /// ```
+    pub fn elevation<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.elevation = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Computes the connected components and sets [`Node::component`] and [`Edge::component`].
+    ///
+    /// Components are numbered by decreasing number of nodes. Nodes and edges
+    /// that cannot be used by the mode have no component.
+    ///
+    /// # Arguments
+    /// * `mode` - The mode whose arcs link the nodes, every mode if `None`.
+    /// * `connectivity` - Weak (edges in any direction) or strong (reachable both ways).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::{Connectivity, Mode, Reader};
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .components(Some(Mode::Car), Connectivity::Strong)
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    /// let islands = nodes.iter().filter(|n| n.component.is_some_and(|c| c > 0)).count();
+    /// ```
+    pub fn components(mut self, mode: Option<Mode>, connectivity: Connectivity) -> Self {
+        self.components = Some((mode, connectivity));
+        self
+    }
+
+    /// Keeps only the largest connected component.
+    ///
+    /// Components are the ones set by [`Reader::components`], or the weak
+    /// components over every mode. Nodes outside of the component, and edges
+    /// touching them, are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .keep_largest_component()
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn keep_largest_component(mut self) -> Self {
+        self.component_filter = Some(ComponentFilter::Largest);
+        self
+    }
+
+    /// Drops the connected components with fewer than `min_nodes` nodes or
+    /// shorter than `min_length` meters.
+    ///
+    /// Components are chosen as with [`Reader::keep_largest_component`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// // Drop parking lots and clipped fragments
+    /// let (nodes, edges) = Reader::new()
+    ///     .drop_small_components(20, 500.)
+    ///     .read("data.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn drop_small_components(mut self, min_nodes: usize, min_length: f64) -> Self {
+        self.component_filter = Some(ComponentFilter::AtLeast {
+            nodes: min_nodes,
+            length: min_length,
+        });
+        self
+    }
+
+    /// Sets how the lengths of the edges are measured.
+    ///
+    /// The default, [`DistanceModel::Haversine`], is the fastest; the ellipsoidal
+    /// models are more accurate, by up to 0.5%. The model is stored in each
+    /// [`Edge`], so that [`Edge::length`] and the writers use it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::{DistanceModel, Reader};
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .distance_model(DistanceModel::Geodesic)
+    ///     .read("map.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn distance_model(mut self, model: DistanceModel) -> Self {
+        self.distance_model = model;
+        self
+    }
+
+    /// Sets the order of the nodes and edges returned by [`Reader::read`].
+    ///
+    /// By default ([`Order::Id`]), nodes are sorted by id and edges by OSM way,
+    /// so that the output does not change from one run to the next.
+    /// [`Order::Hilbert`] keeps close elements close in the output.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::{Order, Reader};
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .order(Order::Hilbert)
+    ///     .read("map.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Simplifies the geometry of the edges, within `tolerance` meters.
+    ///
+    /// Uses Douglas–Peucker, with the ends of the edges fixed so that the graph
+    /// is unchanged, and without making an edge cross itself. Lengths are the
+    /// ones of the simplified geometries. With `keep_nodes`, [`Edge::nodes`]
+    /// keeps every OSM node of the edge, placed by [`Edge::node_offsets`];
+    /// otherwise it only keeps the nodes of the remaining coordinates.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .simplify(2., false)
+    ///     .read("map.osm.pbf")
+    ///     .unwrap();
+    /// ```
+    pub fn simplify(mut self, tolerance: f64, keep_nodes: bool) -> Self {
+        self.simplification = Some((tolerance, keep_nodes));
+        self
+    }
+
+    /// Splits the edges longer than `max_length` meters in pieces of equal length.
+    ///
+    /// The synthetic nodes between the pieces have negative ids, derived from
+    /// the way and the nodes of the edge, see [`Edge::subdivide`]. Pieces keep
+    /// the OSM id, tags and properties of the edge.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let (nodes, edges) = Reader::new()
+    ///     .max_edge_length(100.)
+    ///     .read("map.osm.pbf")
+    ///     .unwrap();
+    /// assert!(edges.iter().all(|edge| edge.length() <= 100. + 1e-6));
+    /// ```
+    pub fn max_edge_length(mut self, max_length: f64) -> Self {
+        self.max_edge_length = Some(max_length);
+        self
+    }
+
+    /// Sets the connected component of nodes and edges, and drops the filtered ones.
+    fn apply_components(&self, nodes: Vec<Node>, edges: Vec<Edge>) -> (Vec<Node>, Vec<Edge>) {
+        let (mode, connectivity) = self.components.unwrap_or((None, Connectivity::Weak));
+        let graph = Graph::new(nodes, edges);
+        let components = Components::new(&graph, mode, connectivity);
+        let kept = |component: Option<usize>| match (self.component_filter, component) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(ComponentFilter::Largest), Some(c)) => c == 0,
+            (Some(ComponentFilter::AtLeast { nodes, length }), Some(c)) => {
+                components.nodes_count(c) >= nodes && components.length(c) >= length
+            }
+        };
+        let kept_nodes: Vec<bool> = (0..graph.nodes.len())
+            .map(|node| kept(components.node(node)))
+            .collect();
+        let kept_edges: Vec<bool> = (0..graph.edges.len())
+            .map(|edge| {
+                self.component_filter.is_none()
+                    || graph
+                        .ends(edge)
+                        .is_some_and(|(source, target)| kept_nodes[source] && kept_nodes[target])
+            })
+            .collect();
+
+        let (mut nodes, mut edges) = graph.into_parts();
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.component = components.node(i);
+        }
+        for (i, edge) in edges.iter_mut().enumerate() {
+            edge.component = components.edge(i);
+        }
+        let mut kept_nodes = kept_nodes.into_iter();
+        nodes.retain(|_| kept_nodes.next().unwrap_or(false));
+        let mut kept_edges = kept_edges.into_iter();
+        edges.retain(|_| kept_edges.next().unwrap_or(false));
+        (nodes, edges)
+    }
+
+    /// Computes the properties of a way from its tags.
+    ///
+    /// Applies, in order, the country defaults, the active conditional
+    /// restrictions and the profiles.
+    fn finalize(
+        &self,
+        tagged: EdgeProperties,
+        access: &DefaultAccess,
+        conditions: &[ConditionalRestriction],
+        rules: Option<&AccessRules>,
+    ) -> EdgeProperties {
+        let active = conditions
+            .iter()
+            .filter(|c| self.time.is_some_and(|time| c.is_active(time)));
+        self.finalize_with(tagged, access, active, rules)
+    }
+
+    /// Computes the properties of a way with the given conditional restrictions applied.
+    fn finalize_with<'a>(
+        &self,
+        tagged: EdgeProperties,
+        access: &DefaultAccess,
+        conditions: impl Iterator<Item = &'a ConditionalRestriction>,
+        rules: Option<&AccessRules>,
+    ) -> EdgeProperties {
+        let mut properties = tagged;
+        if let Some(rules) = rules {
+            rules.apply(access, &mut properties);
+        }
+        properties.normalize();
+        let mut conditions = conditions.peekable();
+        if conditions.peek().is_some() {
+            for condition in conditions {
+                condition.apply(&mut properties);
+            }
+            properties.normalize();
+        }
+        for profile in &self.profiles {
+            profile.apply(&mut properties);
+        }
+        properties
+    }
+
+    /// Returns true if a way is accessible to some mode.
+    ///
+    /// With [`Reader::keep_conditional_ways`], a way is also accessible if the
+    /// restrictions granting access would open it, so that its edges keep
+    /// their [`Edge::conditions`].
+    fn is_accessible(
+        &self,
+        tagged: EdgeProperties,
+        access: &DefaultAccess,
+        conditions: &[ConditionalRestriction],
+        rules: Option<&AccessRules>,
+    ) -> bool {
+        self.finalize(tagged, access, conditions, rules)
+            .accessible()
+            || (self.should_keep_conditional_ways
+                && conditions.iter().any(|c| c.allowed)
+                && self
+                    .finalize_with(
+                        tagged,
+                        access,
+                        conditions.iter().filter(|c| c.allowed),
+                        rules,
+                    )
+                    .accessible())
+    }
+
     /// Counts how many times each node is referenced by ways.
     ///
     /// Endpoint nodes are counted twice to ensure dead-end roads are
@@ -198,8 +749,10 @@ impl Reader {
 
     /// Splits an OSM way into multiple edges at intersection points.
     ///
-    /// A way is split at every node where `uses > 1` (intersection).
-    /// Creates edges with unique IDs in the format "{way_id}-{index}".
+    /// A way is split at every node where `uses > 1` (intersection), or at
+    /// every node with [`Reader::keep_all_nodes`].
+    /// Edge ids come from [`stable_edge_id`], and composite ids have the
+    /// format "{way_id}-{index}".
     ///
     /// # Arguments
     /// * `way` - The way to split.
@@ -215,17 +768,32 @@ impl Reader {
             nodes.push(node.id);
             if i == 0 {
                 source = node_id;
-            } else if node.uses > 1 {
-                result.push(Edge {
-                    id: format!("{}-{}", way.id.0, result.len()),
+            } else if self.is_vertex(&node) {
+                let mut edge = Edge {
+                    id: stable_edge_id(way.id, source, node.id),
+                    composite_id: format!("{}-{}", way.id.0, result.len()),
                     osm_id: way.id,
                     source,
                     target: node_id,
                     geometry,
                     properties: way.properties,
+                    lanes: way.lanes.clone(),
                     nodes,
+                    node_offsets: vec![],
                     tags: way.tags.clone(),
-                });
+                    conditions: way.conditions.clone(),
+                    structures: vec![],
+                    climb: None,
+                    component: None,
+                    distance_model: self.distance_model,
+                    merged_ids: vec![],
+                };
+                edge.structures = vec![StructureRange {
+                    start: 0.,
+                    end: edge.length(),
+                    structure: way.structure,
+                }];
+                result.push(edge);
 
                 source = node_id;
                 geometry = vec![node.coord];
@@ -235,76 +803,33 @@ impl Reader {
         result
     }
 
-    /// Recursively merges consecutive edges at degree-2 nodes.
+    /// Merges chains of consecutive edges at degree-2 nodes.
     ///
     /// OSM ways can be split by tag changes (e.g., bridge=yes) even when
     /// there is no topological intersection. This function merges such
-    /// edges to simplify the routing graph.
+    /// edges to simplify the routing graph, in a single linear pass.
     ///
     /// Two edges are merged when:
     /// - They meet at a node with exactly 2 edge connections (degree 2)
-    /// - They have identical accessibility properties
-    /// - They have identical tags (if tags_to_read is used)
-    /// - They haven't been merged in a previous iteration
+    /// - They have identical accessibility properties, lanes and conditional
+    ///   restrictions once oriented in the same direction
+    /// - Their tags (if tags_to_read is used) are compatible with their [`TagPolicy`]
+    /// - They have identical structural flags, unless `merge_across_structures` is used
+    ///
+    /// The merged away nodes are removed.
     ///
     /// # Arguments
     /// * `edges` - The edges to potentially merge.
     fn do_merge_edges(&mut self, edges: Vec<Edge>) -> Vec<Edge> {
-        let initial_edges_count = edges.len();
-
-        // We build an adjacency map for every node that might have exactly two edges
-        let mut neighbors: HashMap<NodeId, Vec<_>> = HashMap::new();
-        for edge in edges.iter() {
-            // Extremities of a way in `count_nodes_uses` are counted twice to avoid pruning deadends.
-            // We want to look at nodes with at two extremities, hence 4 uses
-            if !self.nodes.contains_key(&edge.source) {
-                println!("Problem with node {}, edge {}", &edge.source.0, edge.id);
-            }
-            if self.nodes.get(&edge.source).unwrap().uses == 4 {
-                neighbors.entry(edge.source).or_default().push(edge);
-            }
-            if self.nodes.get(&edge.target).unwrap().uses == 4 {
-                neighbors.entry(edge.target).or_default().push(edge);
-            }
-        }
-
-        let mut result = Vec::new();
-        let mut already_merged = HashSet::new();
-        for (node, edges) in neighbors.drain() {
-            // We merge two edges at the node if there are only two edges
-            // The edges must have the same accessibility properties
-            // If the consummer asked to store the tags, the tags must be the same for both edges
-            // The edges must be from different ways (no surface)
-            // The edges must not have been merged this iteration (they might be re-merged through a recurive call)
-            if edges.len() == 2
-                && edges[0].properties == edges[1].properties
-                && edges[0].tags == edges[1].tags
-                && edges[0].id != edges[1].id
-                && !already_merged.contains(&edges[0].id)
-                && !already_merged.contains(&edges[1].id)
-            {
-                let edge1 = edges[0];
-                let edge2 = edges[1];
-                result.push(Edge::merge(edge1, edge2, node));
-                already_merged.insert(edge1.id.clone());
-                already_merged.insert(edge2.id.clone());
-                self.nodes.remove(&node);
-            }
-        }
-
-        for edge in edges {
-            if !already_merged.contains(&edge.id) {
-                result.push(edge);
-            }
-        }
-
-        // If we reduced the number of edges, that means that we merged edges
-        // They might need to be merged again, recursively
-        if initial_edges_count > result.len() {
-            self.do_merge_edges(result)
-        } else {
-            result
+        let rules = MergeRules {
+            policies: &self.tag_policies,
+            across_structures: self.should_merge_across_structures,
+        };
+        let (edges, removed) = merge_chains(edges, &rules);
+        for node in removed {
+            self.nodes.remove(&node);
         }
+        edges
     }
 
     /// Checks if a way should be rejected based on user-specified filters.
@@ -334,10 +859,14 @@ impl Reader {
     /// Reads all ways from the PBF file and populates `ways` and `nodes_to_keep`.
     ///
     /// Processes each way in the file:
-    /// 1. Computes accessibility properties from OSM tags
+    /// 1. Computes accessibility properties from OSM tags, applying the
+    ///    country defaults and the conditional restrictions active at `time` if set
     /// 2. Filters by accessibility and user-specified rules
     /// 3. Stores way data and marks referenced nodes for loading
     ///
+    /// When the country is derived from boundaries, ways accessible under the
+    /// rules of any country are kept, and the nodes of boundary ways are loaded.
+    ///
     /// # Arguments
     /// * `file` - Open file handle to the PBF file.
     fn read_ways(&mut self, file: std::fs::File) {
@@ -346,14 +875,36 @@ impl Reader {
             if let osmpbfreader::OsmObj::Way(way) = obj {
                 let mut properties = EdgeProperties::default();
                 let mut tags = HashMap::new();
+                let mut conditions = Vec::new();
+                let mut lanes = Lanes::default();
+                let mut structure = Structure::default();
                 for (key, val) in way.tags.iter() {
                     properties.update(key.to_string(), val.to_string());
+                    lanes.update_with_str(key, val);
+                    structure.update_with_str(key, val);
                     if self.tags_to_read.contains(key.as_str()) {
                         tags.insert(key.to_string(), val.to_string());
                     }
+                    conditions.extend(ConditionalRestriction::parse(key, val));
                 }
-                properties.normalize();
-                if properties.accessible() && !self.is_user_rejected(&way) {
+                let keys = way.tags.iter().map(|(key, _)| key.as_str());
+                ConditionalRestriction::prioritize(&mut conditions, keys);
+                let tagged = properties;
+                let access = DefaultAccess::from_tags(&way.tags);
+                let mut properties = self.finalize(tagged, &access, &conditions, self.access_rules);
+                lanes.normalize(&properties);
+                properties.update_lts(&lanes);
+                if let Some(nodes) = self.boundary_ways.get_mut(&way.id) {
+                    self.nodes_to_keep.extend(way.nodes.iter().copied());
+                    nodes.clone_from(&way.nodes);
+                }
+                let accessible =
+                    self.is_accessible(tagged, &access, &conditions, self.access_rules)
+                        || (self.should_detect_country
+                            && all_access_rules().iter().any(|rules| {
+                                self.is_accessible(tagged, &access, &conditions, Some(rules))
+                            }));
+                if accessible && !self.is_user_rejected(&way) {
                     for node in &way.nodes {
                         self.nodes_to_keep.insert(*node);
                     }
@@ -361,13 +912,84 @@ impl Reader {
                         id: way.id,
                         nodes: way.nodes,
                         properties,
+                        tagged,
+                        access,
+                        lanes,
+                        structure,
                         tags,
+                        conditions,
                     });
                 }
             }
         }
     }
 
+    /// Reads the national boundaries having built-in access rules.
+    ///
+    /// Only the member ways are stored; their nodes are read with the other ways.
+    ///
+    /// # Arguments
+    /// * `file` - Open file handle to the PBF file.
+    fn read_boundaries(&mut self, file: std::fs::File) {
+        let mut pbf = osmpbfreader::OsmPbfReader::new(file);
+        for obj in pbf.par_iter().flatten() {
+            if let osmpbfreader::OsmObj::Relation(relation) = obj
+                && let Some(country) = boundary_country(&relation)
+            {
+                let ways: Vec<_> = relation
+                    .refs
+                    .iter()
+                    .filter(|r| matches!(r.role.as_str(), "outer" | "inner" | ""))
+                    .filter_map(|r| r.member.way())
+                    .collect();
+                for way in &ways {
+                    self.boundary_ways.insert(*way, vec![]);
+                }
+                self.country_boundaries.push((country, ways));
+            }
+        }
+    }
+
+    /// Applies the default access rules of the country each way falls in.
+    ///
+    /// The country is the one containing the middle node of the way. Ways that
+    /// are not accessible under the rules of their country are dropped.
+    fn apply_country_rules(&mut self) {
+        let mut boundaries = Boundaries::default();
+        for (country, ways) in &self.country_boundaries {
+            let ways = ways
+                .iter()
+                .filter_map(|way| self.boundary_ways.get(way))
+                .cloned()
+                .collect();
+            let rings: Vec<_> = assemble_rings(ways)
+                .iter()
+                .filter_map(|ring| {
+                    ring.iter()
+                        .map(|node| self.nodes.get(node).map(|node| node.coord))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .collect();
+            boundaries.add(country, &rings);
+        }
+
+        for mut way in std::mem::take(&mut self.ways) {
+            let rules = way
+                .nodes
+                .get(way.nodes.len() / 2)
+                .and_then(|node| self.nodes.get(node))
+                .and_then(|node| boundaries.country_at(node.coord))
+                .and_then(access_rules)
+                .or(self.access_rules);
+            way.properties = self.finalize(way.tagged, &way.access, &way.conditions, rules);
+            way.lanes.normalize(&way.properties);
+            way.properties.update_lts(&way.lanes);
+            if self.is_accessible(way.tagged, &way.access, &way.conditions, rules) {
+                self.ways.push(way);
+            }
+        }
+    }
+
     /// Reads all nodes from the PBF file that are referenced by ways.
     ///
     /// Only loads nodes that are in `nodes_to_keep` (populated by `read_ways`).
@@ -392,6 +1014,8 @@ impl Reader {
                             y: node.lat(),
                         },
                         uses: 0,
+                        elevation: None,
+                        component: None,
                     },
                 );
             }
@@ -400,13 +1024,24 @@ impl Reader {
 
     /// Returns all nodes that are part of the routing graph.
     ///
-    /// Filters out nodes that are not used by any edge (uses <= 1).
+    /// Filters out nodes that are not used by any edge (uses <= 1),
+    /// unless every node is kept. Nodes are sorted by id.
     fn nodes(&self) -> Vec<Node> {
-        self.nodes
+        let mut nodes: Vec<Node> = self
+            .nodes
             .values()
-            .filter(|node| node.uses > 1)
+            .filter(|node| self.is_vertex(node))
             .copied()
-            .collect()
+            .collect();
+        nodes.sort_by_key(|node| node.id);
+        nodes
+    }
+
+    /// Whether a node of a way is a vertex of the graph.
+    ///
+    /// Nodes used by no kept way, like the nodes of boundaries, are never vertices.
+    fn is_vertex(&self, node: &Node) -> bool {
+        node.uses > 1 || (self.should_keep_all_nodes && node.uses > 0)
     }
 
     /// Converts all ways to edges by splitting at intersections.
@@ -420,7 +1055,8 @@ impl Reader {
     /// Reads the PBF file and constructs the routing graph.
     ///
     /// This is the main entry point for extracting routing data.
-    /// The file is read twice: once for ways, then for nodes.
+    /// The file is read twice: once for ways, then for nodes (three times
+    /// with [`Reader::country_from_boundaries`], relations being read first).
     ///
     /// # Arguments
     /// * `filename` - Path to the OSM PBF file.
@@ -432,6 +1068,7 @@ impl Reader {
     /// Returns an error if:
     /// - The file cannot be opened
     /// - A way references a node not present in the file
+    /// - An elevation tile cannot be read
     ///
     /// # Example
     ///
@@ -445,18 +1082,134 @@ impl Reader {
     /// println!("Loaded {} nodes and {} edges", nodes.len(), edges.len());
     /// ```
     pub fn read<P: AsRef<Path>>(&mut self, filename: P) -> Result<(Vec<Node>, Vec<Edge>), Error> {
+        let dem = self.elevation.as_ref().map(Dem::open).transpose()?;
+        self.load(filename)?;
+
+        let mut edges = if self.should_merge_ways && !self.should_keep_all_nodes {
+            self.do_merge_edges(self.edges())
+        } else {
+            self.edges()
+        };
+        let mut nodes = self.nodes();
+        if let Some(max_length) = self.max_edge_length {
+            let mut pieces = Vec::with_capacity(edges.len());
+            for edge in &edges {
+                let (synthetic, split) = edge.subdivide(max_length);
+                nodes.extend(synthetic);
+                pieces.extend(split);
+            }
+            edges = pieces;
+        }
+        if let Some(dem) = dem {
+            for node in &mut nodes {
+                node.elevation = dem.elevation(node.coord)?;
+            }
+            for edge in &mut edges {
+                edge.climb = dem.climb(&edge.geometry, edge.distance_model)?;
+            }
+        }
+        if self.components.is_some() || self.component_filter.is_some() {
+            (nodes, edges) = self.apply_components(nodes, edges);
+        }
+        if let Some((tolerance, keep_nodes)) = self.simplification {
+            for edge in &mut edges {
+                edge.simplify(tolerance, keep_nodes);
+            }
+        }
+        self.order.sort(&mut nodes, &mut edges);
+        unique_edge_ids(&mut edges);
+        Ok((nodes, edges))
+    }
+
+    /// Reads ways and nodes, and counts the uses of each node.
+    fn load<P: AsRef<Path>>(&mut self, filename: P) -> Result<(), Error> {
+        if self.should_detect_country {
+            let file_relations = std::fs::File::open(filename.as_ref())?;
+            self.read_boundaries(file_relations);
+        }
         let file = std::fs::File::open(filename.as_ref())?;
         self.read_ways(file);
         let file_nodes = std::fs::File::open(filename.as_ref())?;
         self.read_nodes(file_nodes);
-        self.count_nodes_uses()?;
+        if self.should_detect_country {
+            self.apply_country_rules();
+        }
+        self.count_nodes_uses()
+    }
 
-        let edges = if self.should_merge_ways {
-            self.do_merge_edges(self.edges())
+    /// Reads the PBF file and constructs a [`Graph`] with adjacency per mode.
+    ///
+    /// Same as [`Reader::read`], with nodes and edges indexed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::{Mode, Reader};
+    ///
+    /// let graph = Reader::new().read_graph("map.osm.pbf").unwrap();
+    /// for arc in graph.outgoing(0, Mode::Bike) {
+    ///     println!("Edge {} leads to node {}", graph.edges[arc.edge].id, arc.node);
+    /// }
+    /// ```
+    pub fn read_graph<P: AsRef<Path>>(&mut self, filename: P) -> Result<Graph, Error> {
+        let (nodes, edges) = self.read(filename)?;
+        Ok(Graph::new(nodes, edges))
+    }
+
+    /// Reads the PBF file into a compact [`CsrGraph`].
+    ///
+    /// Ways are split into edges one at a time, without building the
+    /// `Vec<Edge>` of [`Reader::read`]. Edges only keep their OSM id, ends,
+    /// length and geometry; the accessibility becomes per-mode travel times.
+    /// With [`Reader::merge_ways`], edges are merged before the conversion.
+    /// Elevations and connected components are not computed, and geometries
+    /// are neither simplified nor split.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use osm4routing::Reader;
+    ///
+    /// let graph = Reader::new().read_csr("europe.osm.pbf").unwrap();
+    /// graph.save("europe.csr").unwrap();
+    /// ```
+    pub fn read_csr<P: AsRef<Path>>(&mut self, filename: P) -> Result<CsrGraph, Error> {
+        self.load(filename)?;
+        if self.should_merge_ways && !self.should_keep_all_nodes {
+            // Merging removes the merged away nodes, so they are listed afterwards
+            let edges = self.do_merge_edges(self.edges());
+            let mut builder = CsrBuilder::new(self.nodes()).distance_model(self.distance_model);
+            for edge in edges {
+                builder.push(
+                    edge.osm_id,
+                    edge.source,
+                    edge.target,
+                    &edge.properties,
+                    &edge.geometry,
+                );
+            }
+            Ok(builder.build())
         } else {
-            self.edges()
-        };
-        Ok((self.nodes(), edges))
+            let mut builder = CsrBuilder::new(self.nodes()).distance_model(self.distance_model);
+            let mut geometry = Vec::new();
+            for way in &self.ways {
+                geometry.clear();
+                let mut source = NodeId(0);
+                for (i, node_id) in way.nodes.iter().enumerate() {
+                    let node = self.nodes[node_id];
+                    geometry.push(node.coord);
+                    if i == 0 {
+                        source = node.id;
+                    } else if self.is_vertex(&node) {
+                        builder.push(way.id, source, node.id, &way.properties, &geometry);
+                        source = node.id;
+                        geometry.clear();
+                        geometry.push(node.coord);
+                    }
+                }
+            }
+            Ok(builder.build())
+        }
     }
 }
 
@@ -478,6 +1231,14 @@ pub fn read<P: AsRef<Path>>(filename: P) -> Result<(Vec<Node>, Vec<Edge>), Error
     Reader::new().read(filename)
 }
 
+/// Convenience function to read a PBF file as a [`Graph`] with default settings.
+///
+/// # Arguments
+/// * `filename` - Path to the OSM PBF file.
+pub fn read_graph<P: AsRef<Path>>(filename: P) -> Result<Graph, Error> {
+    Reader::new().read_graph(filename)
+}
+
 #[test]
 fn test_real_all() {
     let (nodes, ways) = read("src/osm4routing/test_data/minimal.osm.pbf").unwrap();
@@ -516,6 +1277,8 @@ fn test_split() {
     nodes.insert(NodeId(3), Node::default());
     nodes.insert(NodeId(4), Node::default());
     nodes.insert(NodeId(5), Node::default());
+    // Not used by any way, like the node of a boundary
+    nodes.insert(NodeId(6), Node::default());
     let ways = vec![
         Way {
             nodes: vec![NodeId(1), NodeId(2), NodeId(3)],
@@ -534,6 +1297,12 @@ fn test_split() {
     r.count_nodes_uses().unwrap();
     let edges = r.edges();
     assert_eq!(3, edges.len());
+
+    r.should_keep_all_nodes = true;
+    let edges = r.edges();
+    assert_eq!(4, edges.len());
+    assert!(edges.iter().all(|edge| edge.nodes.len() == 2));
+    assert_eq!(5, r.nodes().len());
 }
 
 #[test]
@@ -546,7 +1315,7 @@ fn test_wrong_file() {
 fn forbidden_values() {
     let (_, ways) = Reader::new()
         .reject("highway", "secondary")
-        .read(&"src/osm4routing/test_data/minimal.osm.pbf")
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
         .unwrap();
     assert_eq!(0, ways.len());
 }
@@ -555,7 +1324,7 @@ fn forbidden_values() {
 fn forbidden_wildcard() {
     let (_, ways) = Reader::new()
         .reject("highway", "*")
-        .read(&"src/osm4routing/test_data/minimal.osm.pbf")
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
         .unwrap();
     assert_eq!(0, ways.len());
 }
@@ -563,9 +1332,7 @@ fn forbidden_wildcard() {
 #[test]
 fn way_of_node() {
     let mut r = Reader::new();
-    let (_nodes, edges) = r
-        .read(&"src/osm4routing/test_data/minimal.osm.pbf")
-        .unwrap();
+    let (_nodes, edges) = r.read("src/osm4routing/test_data/minimal.osm.pbf").unwrap();
 
     assert_eq!(2, edges[0].nodes.len());
 }
@@ -574,7 +1341,7 @@ fn way_of_node() {
 fn read_tags() {
     let (_nodes, edges) = Reader::new()
         .read_tag("highway")
-        .read(&"src/osm4routing/test_data/minimal.osm.pbf")
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
         .unwrap();
 
     assert_eq!("secondary", edges[0].tags.get("highway").unwrap());
@@ -584,7 +1351,7 @@ fn read_tags() {
 fn require_value_ok() {
     let (_, ways) = Reader::new()
         .require("highway", "secondary")
-        .read(&"src/osm4routing/test_data/minimal.osm.pbf")
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
         .unwrap();
     assert_eq!(1, ways.len());
 }
@@ -593,7 +1360,7 @@ fn require_value_ok() {
 fn require_value_missing() {
     let (_, ways) = Reader::new()
         .require("highway", "primary")
-        .read(&"src/osm4routing/test_data/minimal.osm.pbf")
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
         .unwrap();
     assert_eq!(0, ways.len());
 }
@@ -602,7 +1369,7 @@ fn require_value_missing() {
 fn require_wildcart() {
     let (_, ways) = Reader::new()
         .require("highway", "*")
-        .read(&"src/osm4routing/test_data/minimal.osm.pbf")
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
         .unwrap();
     assert_eq!(1, ways.len());
 }
@@ -612,21 +1379,216 @@ fn require_multiple_tags() {
     let (_, ways) = Reader::new()
         .require("highway", "primary")
         .require("highway", "secondary")
-        .read(&"src/osm4routing/test_data/minimal.osm.pbf")
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
         .unwrap();
     assert_eq!(1, ways.len());
 }
 
+#[test]
+fn graph() {
+    let graph = read_graph("src/osm4routing/test_data/minimal.osm.pbf").unwrap();
+    let source = graph.node_by_osm_id(NodeId(8)).unwrap();
+    let target = graph.node_by_osm_id(NodeId(10)).unwrap();
+    assert_eq!(vec![target], graph.neighbors(source, Mode::Car));
+    assert_eq!(vec![0], graph.edges_of_node(target));
+}
+
+#[test]
+fn csr() {
+    let graph = Reader::new()
+        .read_csr("src/osm4routing/test_data/ways_to_merge.osm.pbf")
+        .unwrap();
+    assert_eq!(3, graph.nodes_count());
+    assert_eq!(2, graph.edges_count());
+    assert_eq!(4, graph.adjacency(Mode::Car).heads.len());
+
+    let graph = Reader::new()
+        .merge_ways()
+        .read_csr("src/osm4routing/test_data/ways_to_merge.osm.pbf")
+        .unwrap();
+    assert_eq!(2, graph.nodes_count());
+    assert_eq!(1, graph.edges_count());
+    assert_eq!(5, graph.geometry(0).len());
+}
+
+#[test]
+fn country_rules() {
+    let (_, edges) = Reader::new()
+        .country("de")
+        .country_from_boundaries()
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
+        .unwrap();
+    assert_eq!(1, edges.len());
+    assert_eq!(BikeAccessibility::Allowed, edges[0].properties.bike_forward);
+
+    // Trunks in Germany, in the Netherlands and outside of both boundaries
+    let (nodes, edges) = Reader::new()
+        .country("de")
+        .country_from_boundaries()
+        .read("src/osm4routing/test_data/boundaries.osm.pbf")
+        .unwrap();
+    assert_eq!(6, nodes.len());
+    assert_eq!(3, edges.len());
+    assert_eq!(BikeAccessibility::Allowed, edges[0].properties.bike_forward);
+    assert_eq!(
+        BikeAccessibility::Forbidden,
+        edges[1].properties.bike_forward
+    );
+    assert_eq!(BikeAccessibility::Allowed, edges[2].properties.bike_forward);
+
+    let (_, edges) = Reader::new()
+        .country_from_boundaries()
+        .read("src/osm4routing/test_data/boundaries.osm.pbf")
+        .unwrap();
+    assert_eq!(BikeAccessibility::Allowed, edges[0].properties.bike_forward);
+    assert_eq!(
+        BikeAccessibility::Forbidden,
+        edges[2].properties.bike_forward
+    );
+}
+
+#[test]
+fn conditional_restrictions() {
+    let file = "src/osm4routing/test_data/conditional.osm.pbf";
+    // Without a time, the busway only open to bikes by a condition is dropped
+    let (_, edges) = Reader::new().read(file).unwrap();
+    assert_eq!(1, edges.len());
+    assert_eq!(WayId(21), edges[0].osm_id);
+    assert_eq!(CarAccessibility::Secondary, edges[0].properties.car_forward);
+
+    // Unless asked to, then it is kept with its restriction
+    let (nodes, edges) = Reader::new().keep_conditional_ways().read(file).unwrap();
+    assert_eq!(2, edges.len());
+    assert_eq!(WayId(20), edges[0].osm_id);
+    assert!(!edges[0].properties.accessible());
+    assert_eq!(1, edges[0].conditions.len());
+    assert_eq!(CarAccessibility::Secondary, edges[1].properties.car_forward);
+
+    // The writers only get the properties, with every mode forbidden
+    let dir = std::env::temp_dir().join(format!("osm4routing_conditional_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (nodes_file, edges_file) = (dir.join("nodes.csv"), dir.join("edges.csv"));
+    let (nodes_file, edges_file) = (nodes_file.to_str().unwrap(), edges_file.to_str().unwrap());
+    super::writers::csv(nodes, edges, nodes_file, edges_file).unwrap();
+    let mut reader = csv::Reader::from_path(edges_file).unwrap();
+    let header = reader.headers().unwrap().clone();
+    let row = reader.records().next().unwrap().unwrap();
+    let column = |name: &str| &row[header.iter().position(|h| h == name).unwrap()];
+    assert_eq!("20", column("osm_id"));
+    for name in [
+        "foot",
+        "car_forward",
+        "car_backward",
+        "bike_forward",
+        "bike_backward",
+    ] {
+        assert_eq!("Forbidden", column(name));
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // A Monday at 8:00
+    let (_, edges) = Reader::new()
+        .at_time(LocalTime::new(5, 0, 8, 0).unwrap())
+        .read(file)
+        .unwrap();
+    assert_eq!(2, edges.len());
+    assert_eq!(BikeAccessibility::Allowed, edges[0].properties.bike_forward);
+    assert_eq!(CarAccessibility::Secondary, edges[1].properties.car_forward);
+
+    // A Saturday at 8:00
+    let (_, edges) = Reader::new()
+        .at_time(LocalTime::new(5, 5, 8, 0).unwrap())
+        .read(file)
+        .unwrap();
+    assert_eq!(1, edges.len());
+    assert_eq!(WayId(21), edges[0].osm_id);
+    assert_eq!(CarAccessibility::Forbidden, edges[0].properties.car_forward);
+    assert!(edges[0].properties.allows(Mode::Bike, Direction::Forward));
+}
+
 #[test]
 fn merging_edges() {
     let (_nodes, edges) = Reader::new()
-        .read(&"src/osm4routing/test_data/ways_to_merge.osm.pbf")
+        .read("src/osm4routing/test_data/ways_to_merge.osm.pbf")
         .unwrap();
     assert_eq!(2, edges.len());
 
     let (_nodes, edges) = Reader::new()
         .merge_ways()
-        .read(&"src/osm4routing/test_data/ways_to_merge.osm.pbf")
+        .read("src/osm4routing/test_data/ways_to_merge.osm.pbf")
         .unwrap();
     assert_eq!(1, edges.len());
 }
+
+#[test]
+fn elevation() {
+    let dir = std::env::temp_dir().join(format!("osm4routing_elevation_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // Two flat tiles, the eastern one 100 m higher
+    for (name, ele) in [("S02E001.hgt", 100i16), ("S02E002.hgt", 200)] {
+        std::fs::write(dir.join(name), ele.to_be_bytes().repeat(9)).unwrap();
+    }
+    let (nodes, edges) = Reader::new()
+        .elevation(&dir)
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let source = nodes.iter().find(|n| n.id == edges[0].source).unwrap();
+    assert!((source.elevation.unwrap() - 100.).abs() < 1e-6);
+    let climb = edges[0].climb.unwrap();
+    assert!((climb.ascent - 100.).abs() < 1e-6);
+    assert!(climb.descent.abs() < 1e-6);
+}
+
+#[test]
+fn components() {
+    let (nodes, edges) = Reader::new()
+        .components(None, Connectivity::Weak)
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
+        .unwrap();
+    assert!(nodes.iter().all(|node| node.component == Some(0)));
+    assert_eq!(Some(0), edges[0].component);
+
+    let (nodes, edges) = Reader::new()
+        .keep_largest_component()
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
+        .unwrap();
+    assert_eq!(2, nodes.len());
+    assert_eq!(1, edges.len());
+
+    let (nodes, edges) = Reader::new()
+        .drop_small_components(3, 0.)
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
+        .unwrap();
+    assert!(nodes.is_empty());
+    assert!(edges.is_empty());
+}
+
+#[test]
+fn distance_model() {
+    let (_, haversine) = read("src/osm4routing/test_data/minimal.osm.pbf").unwrap();
+    let (_, geodesic) = Reader::new()
+        .distance_model(DistanceModel::Geodesic)
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
+        .unwrap();
+    assert_eq!(DistanceModel::Geodesic, geodesic[0].distance_model);
+    let ratio = geodesic[0].length() / haversine[0].length();
+    assert!(ratio != 1. && (ratio - 1.).abs() < 0.005);
+}
+
+#[test]
+fn max_edge_length() {
+    let (nodes, edges) = Reader::new()
+        .max_edge_length(10_000.)
+        .read("src/osm4routing/test_data/minimal.osm.pbf")
+        .unwrap();
+    assert_eq!(8, edges.len());
+    assert_eq!(9, nodes.len());
+    assert!(edges.iter().all(|edge| edge.length() <= 10_000.));
+    assert!(edges.iter().all(|edge| edge.osm_id == edges[0].osm_id));
+    for edge in &edges {
+        assert!(nodes.iter().any(|node| node.id == edge.source));
+        assert!(nodes.iter().any(|node| node.id == edge.target));
+    }
+}
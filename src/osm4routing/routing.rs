@@ -0,0 +1,466 @@
+//! Shortest path search on a [`Graph`].
+//!
+//! A [`Router`] computes the cost of every edge for one transportation mode
+//! and one [`Weight`], then answers point to point queries with Dijkstra or A*.
+//! Directionality comes from the adjacency of the graph: a car cannot go
+//! against a `car_backward: Forbidden` edge.
+
+use super::categorize::{Direction, Mode};
+use super::distance::HAVERSINE_LOWER_BOUND;
+use super::graph::{Arc, Graph};
+use super::models::*;
+use super::profile::Profile;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+type Coord = geo_types::Coord<f64>;
+
+/// A custom cost function, see [`Weight::Custom`].
+pub type CostFn<'a> = dyn Fn(&Edge, Direction) -> Option<f64> + 'a;
+
+/// Cost of traversing an edge.
+///
+/// Costs must not be negative.
+pub enum Weight<'a> {
+    /// Length in meters.
+    Length,
+    /// Travel time in seconds, at the speed given by [`EdgeProperties::speed`](super::categorize::EdgeProperties::speed).
+    TravelTime,
+    /// Travel time in seconds, multiplied by the [`Profile::factor`] of the edge
+    /// for the mode of the profile, which cannot use the edges it rejects.
+    Profile(Profile),
+    /// A custom cost; `None` forbids the edge in that direction.
+    Custom(Box<CostFn<'a>>),
+}
+
+impl<'a> Weight<'a> {
+    /// Creates a custom weight from a closure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use osm4routing::{Direction, Weight};
+    ///
+    /// // Avoid steps by making them ten times longer
+    /// let weight = Weight::custom(|edge, _direction: Direction| {
+    ///     let factor = if edge.tags.get("highway").map(String::as_str) == Some("steps") { 10. } else { 1. };
+    ///     Some(edge.length() * factor)
+    /// });
+    /// ```
+    pub fn custom(cost: impl Fn(&Edge, Direction) -> Option<f64> + 'a) -> Weight<'a> {
+        Weight::Custom(Box::new(cost))
+    }
+
+    /// Short name of the weight: `length`, `travel_time`, `profile` or `custom`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weight::Length => "length",
+            Weight::TravelTime => "travel_time",
+            Weight::Profile(_) => "profile",
+            Weight::Custom(_) => "custom",
+        }
+    }
+
+    /// Returns the cost of an edge, `None` if it cannot be used.
+    fn cost(&self, edge: &Edge, mode: Mode, direction: Direction) -> Option<f64> {
+        match self {
+            Weight::Length => Some(edge.length()),
+            Weight::TravelTime => edge
+                .properties
+                .speed(mode, direction)
+                .map(|speed| edge.length() / (speed / 3.6)),
+            Weight::Profile(profile) if profile.mode == mode => {
+                let time = Weight::TravelTime.cost(edge, mode, direction)?;
+                profile
+                    .accepts(&edge.properties)
+                    .then(|| time * profile.factor(&edge.properties))
+            }
+            Weight::Profile(_) => Weight::TravelTime.cost(edge, mode, direction),
+            Weight::Custom(cost) => cost(edge, direction),
+        }
+    }
+}
+
+/// A path found by a [`Router`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    /// Total cost, in the unit of the weight.
+    pub cost: f64,
+    /// Arcs followed from the source to the target.
+    pub arcs: Vec<Arc>,
+    /// Geometry from the source to the target.
+    pub geometry: Vec<Coord>,
+}
+
+/// An entry of the priority queue, ordered by smallest key first.
+#[derive(Clone, Copy)]
+pub(crate) struct State {
+    pub(crate) key: f64,
+    pub(crate) node: usize,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .key
+            .total_cmp(&self.key)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+/// Shortest path search for one mode and one weight.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{Mode, NodeId, Router, Weight};
+///
+/// let graph = osm4routing::read_graph("map.osm.pbf").unwrap();
+/// let router = Router::new(&graph, Mode::Bike, Weight::TravelTime);
+/// let source = graph.node_by_osm_id(NodeId(1)).unwrap();
+/// let target = graph.node_by_osm_id(NodeId(2)).unwrap();
+/// if let Some(path) = router.astar(source, target) {
+///     println!("{} seconds through {} edges", path.cost, path.arcs.len());
+/// }
+/// ```
+pub struct Router<'a> {
+    graph: &'a Graph,
+    mode: Mode,
+    /// Name of the weight, see [`Weight::name`].
+    weight: &'static str,
+    /// Forward and backward cost of each edge, infinite if forbidden.
+    costs: Vec<[f64; 2]>,
+    /// Lower bound of the cost of a meter, used by the A* heuristic.
+    cost_per_meter: f64,
+}
+
+fn direction_index(direction: Direction) -> usize {
+    match direction {
+        Direction::Forward => 0,
+        Direction::Backward => 1,
+    }
+}
+
+impl<'a> Router<'a> {
+    /// Computes the cost of every edge of the graph.
+    pub fn new(graph: &'a Graph, mode: Mode, weight: Weight) -> Router<'a> {
+        let mut cost_per_meter = f64::INFINITY;
+        let costs = graph
+            .edges
+            .iter()
+            .map(|edge| {
+                let length = edge.length();
+                [Direction::Forward, Direction::Backward].map(|direction| {
+                    match weight.cost(edge, mode, direction) {
+                        Some(cost) if cost >= 0. && edge.properties.allows(mode, direction) => {
+                            if length > 0. {
+                                cost_per_meter = cost_per_meter.min(cost / length);
+                            }
+                            cost
+                        }
+                        _ => f64::INFINITY,
+                    }
+                })
+            })
+            .collect();
+        Router {
+            graph,
+            mode,
+            weight: weight.name(),
+            costs,
+            cost_per_meter: if cost_per_meter.is_finite() {
+                cost_per_meter
+            } else {
+                0.
+            },
+        }
+    }
+
+    /// The graph searched by this router.
+    pub fn graph(&self) -> &'a Graph {
+        self.graph
+    }
+
+    /// The mode of this router.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The name of the weight of this router, see [`Weight::name`].
+    pub fn weight(&self) -> &'static str {
+        self.weight
+    }
+
+    /// Returns the cost of an arc, infinite if it cannot be used.
+    pub fn cost(&self, arc: &Arc) -> f64 {
+        self.costs[arc.edge][direction_index(arc.direction)]
+    }
+
+    /// Finds the shortest path with Dijkstra's algorithm.
+    ///
+    /// Returns `None` if the target cannot be reached.
+    pub fn dijkstra(&self, source: usize, target: usize) -> Option<Path> {
+        self.search(source, target, |_| 0.)
+    }
+
+    /// Finds the shortest path with A*.
+    ///
+    /// The heuristic is a lower bound of the distance to the target, whatever
+    /// the [`DistanceModel`](super::distance::DistanceModel), times the lowest
+    /// cost of a meter over the graph, so the path is optimal.
+    /// Returns `None` if the target cannot be reached.
+    pub fn astar(&self, source: usize, target: usize) -> Option<Path> {
+        let goal = self.graph.nodes[target].coord;
+        self.search(source, target, |node| {
+            self.graph.nodes[node].coord.distance_to(goal)
+                * HAVERSINE_LOWER_BOUND
+                * self.cost_per_meter
+        })
+    }
+
+    /// Returns the cost from a source to each target, `None` if unreachable.
+    ///
+    /// Runs a single Dijkstra that stops once every target is settled.
+    pub fn one_to_many(&self, source: usize, targets: &[usize]) -> Vec<Option<f64>> {
+        let nodes_count = self.graph.nodes.len();
+        let mut is_target = vec![false; nodes_count];
+        for &target in targets {
+            is_target[target] = true;
+        }
+        let mut remaining = is_target.iter().filter(|t| **t).count();
+        let mut costs = vec![f64::INFINITY; nodes_count];
+        let mut queue = BinaryHeap::new();
+        costs[source] = 0.;
+        queue.push(State {
+            key: 0.,
+            node: source,
+        });
+        while let Some(State { key, node }) = queue.pop() {
+            if key > costs[node] {
+                continue;
+            }
+            if is_target[node] {
+                is_target[node] = false;
+                remaining -= 1;
+                if remaining == 0 {
+                    break;
+                }
+            }
+            for arc in self.graph.outgoing(node, self.mode) {
+                let cost = key + self.cost(arc);
+                if cost < costs[arc.node] {
+                    costs[arc.node] = cost;
+                    queue.push(State {
+                        key: cost,
+                        node: arc.node,
+                    });
+                }
+            }
+        }
+        targets
+            .iter()
+            .map(|&target| costs[target].is_finite().then_some(costs[target]))
+            .collect()
+    }
+
+    /// Best-first search guided by a heuristic.
+    fn search(
+        &self,
+        source: usize,
+        target: usize,
+        heuristic: impl Fn(usize) -> f64,
+    ) -> Option<Path> {
+        let nodes_count = self.graph.nodes.len();
+        let mut costs = vec![f64::INFINITY; nodes_count];
+        let mut parents: Vec<Option<Arc>> = vec![None; nodes_count];
+        let mut settled = vec![false; nodes_count];
+        let mut queue = BinaryHeap::new();
+        costs[source] = 0.;
+        queue.push(State {
+            key: heuristic(source),
+            node: source,
+        });
+        while let Some(State { node, .. }) = queue.pop() {
+            if settled[node] {
+                continue;
+            }
+            if node == target {
+                return Some(self.path(source, target, costs[target], &parents));
+            }
+            settled[node] = true;
+            for arc in self.graph.outgoing(node, self.mode) {
+                let cost = costs[node] + self.cost(arc);
+                if cost < costs[arc.node] {
+                    costs[arc.node] = cost;
+                    parents[arc.node] = Some(*arc);
+                    queue.push(State {
+                        key: cost + heuristic(arc.node),
+                        node: arc.node,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Rebuilds the path to a target from the arcs used to reach each node.
+    pub(crate) fn path(
+        &self,
+        source: usize,
+        target: usize,
+        cost: f64,
+        parents: &[Option<Arc>],
+    ) -> Path {
+        let mut arcs = Vec::new();
+        let mut node = target;
+        while node != source {
+            let Some(arc) = parents[node] else { break };
+            arcs.push(arc);
+            node = self.graph.tail(&arc);
+        }
+        arcs.reverse();
+        Path {
+            cost,
+            geometry: self.graph.merge_geometry(source, &arcs),
+            arcs,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_graph() -> Graph {
+    // 1 --- 2 --- 3
+    //  \         /
+    //   `-- 4 --´    2→3 is one-way, 1-4-3 is a footway
+    use super::categorize::EdgeProperties;
+
+    let properties = |tags: &[(&str, &str)]| {
+        let mut p = EdgeProperties::default();
+        for (key, val) in tags {
+            p.update_with_str(key, val);
+        }
+        p.normalize();
+        p
+    };
+    let coord = |id: i64| match id {
+        1 => Coord { x: 0., y: 0. },
+        2 => Coord { x: 0.01, y: 0. },
+        3 => Coord { x: 0.02, y: 0. },
+        _ => Coord { x: 0.01, y: -0.001 },
+    };
+    let node = |id| Node {
+        id: NodeId(id),
+        coord: coord(id),
+        ..Default::default()
+    };
+    let edge = |id: &str, source, target, properties| Edge {
+        composite_id: id.to_string(),
+        source: NodeId(source),
+        target: NodeId(target),
+        geometry: vec![coord(source), coord(target)],
+        nodes: vec![NodeId(source), NodeId(target)],
+        properties,
+        ..Default::default()
+    };
+    let street = properties(&[("highway", "residential")]);
+    let oneway = properties(&[("highway", "residential"), ("oneway", "yes")]);
+    let footway = properties(&[("highway", "footway")]);
+    Graph::new(
+        vec![node(1), node(2), node(3), node(4)],
+        vec![
+            edge("a", 1, 2, street),
+            edge("b", 2, 3, oneway),
+            edge("c", 1, 4, footway),
+            edge("d", 3, 4, footway),
+        ],
+    )
+}
+
+#[test]
+fn test_shortest_path() {
+    let g = test_graph();
+    let router = Router::new(&g, Mode::Car, Weight::Length);
+    let path = router.dijkstra(0, 2).unwrap();
+    assert_eq!(
+        vec![0, 1],
+        path.arcs.iter().map(|a| a.edge).collect::<Vec<_>>()
+    );
+    assert_eq!(3, path.geometry.len());
+    assert!((path.cost - 2_223.9).abs() < 1.);
+    assert_eq!(Some(path), router.astar(0, 2));
+
+    // The one-way street cannot be used backward
+    assert_eq!(None, router.dijkstra(2, 0));
+
+    // Pedestrians can walk the one-way street backward
+    let router = Router::new(&g, Mode::Foot, Weight::Length);
+    let path = router.astar(2, 0).unwrap();
+    assert_eq!(
+        vec![1, 0],
+        path.arcs.iter().map(|a| a.edge).collect::<Vec<_>>()
+    );
+    assert_eq!(Direction::Backward, path.arcs[1].direction);
+    assert_eq!(Coord { x: 0., y: 0. }, *path.geometry.last().unwrap());
+
+    let path = router.dijkstra(1, 1).unwrap();
+    assert_eq!(0., path.cost);
+    assert!(path.arcs.is_empty());
+
+    let costs = router.one_to_many(2, &[0, 2, 3]);
+    assert_eq!(router.dijkstra(2, 0).map(|p| p.cost), costs[0]);
+    assert_eq!(Some(0.), costs[1]);
+    assert_eq!(router.dijkstra(2, 3).map(|p| p.cost), costs[2]);
+    let router = Router::new(&g, Mode::Car, Weight::Length);
+    assert_eq!(vec![None, Some(0.)], router.one_to_many(2, &[0, 2]));
+}
+
+#[test]
+fn test_weights() {
+    let g = test_graph();
+    let router = Router::new(&g, Mode::Car, Weight::TravelTime);
+    // 2.2 km at 40 km/h
+    assert!((router.astar(0, 2).unwrap().cost - 200.2).abs() < 1.);
+
+    // Forbid the street between 1 and 2 for pedestrians
+    let weight = Weight::custom(|edge, _| (edge.composite_id != "a").then(|| edge.length()));
+    let router = Router::new(&g, Mode::Foot, weight);
+    let path = router.astar(0, 1).unwrap();
+    assert_eq!(
+        vec![2, 3, 1],
+        path.arcs.iter().map(|a| a.edge).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_profile_weight() {
+    use super::categorize::Surface;
+
+    let mut g = test_graph();
+    g.edges[0].properties.surface = Surface::Gravel;
+    let edges = |weight| {
+        let router = Router::new(&g, Mode::Foot, weight);
+        let path = router.astar(0, 1).unwrap();
+        path.arcs.iter().map(|a| a.edge).collect::<Vec<_>>()
+    };
+    assert_eq!(vec![0], edges(Weight::TravelTime));
+    // The gravel street becomes longer than the detour through the footways
+    let profile = Profile::new(Mode::Foot).surface_factor(Surface::Gravel, 4.);
+    assert_eq!(vec![2, 3, 1], edges(Weight::Profile(profile)));
+    assert_eq!(vec![2, 3, 1], edges(Weight::Profile(Profile::wheelchair())));
+    // A profile of another mode has no effect
+    assert_eq!(vec![0], edges(Weight::Profile(Profile::bike())));
+}
@@ -0,0 +1,177 @@
+//! Douglas–Peucker simplification of line strings.
+//!
+//! The tolerance is in meters: coordinates are projected on a local
+//! equirectangular plane around each line. Endpoints are always kept, and
+//! segments that would cross another part of the simplified line are split
+//! again, so that simplification never introduces a self-intersection.
+
+type Coord = geo_types::Coord<f64>;
+
+/// Meters per degree of latitude on the mean sphere.
+const METERS_PER_DEGREE: f64 = 111_195.08;
+
+/// Simplifies a line string, returning the indices of the kept coordinates.
+///
+/// The first and last coordinates are always kept, and no coordinate is
+/// farther than `tolerance` meters from the simplified line. A closed line
+/// keeps at least three distinct coordinates.
+///
+/// # Example
+///
+/// ```
+/// use osm4routing::douglas_peucker;
+/// use geo_types::Coord;
+///
+/// let line = [
+///     Coord { x: 0., y: 0. },
+///     Coord { x: 0.001, y: 0.000_001 },
+///     Coord { x: 0.002, y: 0. },
+/// ];
+/// assert_eq!(vec![0, 2], douglas_peucker(&line, 1.));
+/// ```
+pub fn douglas_peucker(geometry: &[Coord], tolerance: f64) -> Vec<usize> {
+    if geometry.len() <= 2 {
+        return (0..geometry.len()).collect();
+    }
+    let points = local_plane(geometry);
+    let last = points.len() - 1;
+    let mut kept = vec![false; points.len()];
+    kept[0] = true;
+    kept[last] = true;
+
+    let mut ranges = vec![(0, last)];
+    // A ring needs two more vertices to keep an area
+    let closed = geometry[0] == geometry[last];
+    while let Some((start, end)) = ranges.pop() {
+        if let Some((farthest, distance)) = farthest(&points, start, end) {
+            let ring = closed && kept.iter().filter(|k| **k).count() < 4;
+            if distance > tolerance || ring {
+                kept[farthest] = true;
+                ranges.push((start, farthest));
+                ranges.push((farthest, end));
+            }
+        }
+    }
+
+    // Split the segments that cross another one, until none does
+    loop {
+        let indices: Vec<usize> = (0..points.len()).filter(|i| kept[*i]).collect();
+        let mut split = false;
+        for (a, b) in crossing_segments(&points, &indices, closed) {
+            for segment in [a, b] {
+                let (start, end) = (indices[segment], indices[segment + 1]);
+                if let Some((farthest, _)) = farthest(&points, start, end) {
+                    split |= !kept[farthest];
+                    kept[farthest] = true;
+                }
+            }
+        }
+        if !split {
+            return indices;
+        }
+    }
+}
+
+/// Projects coordinates in meters on a plane tangent at their first coordinate.
+fn local_plane(geometry: &[Coord]) -> Vec<Coord> {
+    let origin = geometry[0];
+    let scale = origin.y.to_radians().cos();
+    geometry
+        .iter()
+        .map(|coord| Coord {
+            x: (coord.x - origin.x) * scale * METERS_PER_DEGREE,
+            y: (coord.y - origin.y) * METERS_PER_DEGREE,
+        })
+        .collect()
+}
+
+/// The coordinate strictly between `start` and `end` farthest from their segment.
+fn farthest(points: &[Coord], start: usize, end: usize) -> Option<(usize, f64)> {
+    (start + 1..end)
+        .map(|i| (i, segment_distance(points[i], points[start], points[end])))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+fn segment_distance(point: Coord, a: Coord, b: Coord) -> f64 {
+    let ab = b - a;
+    let length_sq = ab.x * ab.x + ab.y * ab.y;
+    let t = if length_sq > 0. {
+        (((point - a).x * ab.x + (point - a).y * ab.y) / length_sq).clamp(0., 1.)
+    } else {
+        0.
+    };
+    let projected = a + ab * t;
+    (point - projected).x.hypot((point - projected).y)
+}
+
+/// Pairs of non-adjacent segments of the simplified line that intersect.
+fn crossing_segments(points: &[Coord], indices: &[usize], closed: bool) -> Vec<(usize, usize)> {
+    let segments = indices.len() - 1;
+    let mut result = Vec::new();
+    for a in 0..segments {
+        for b in a + 2..segments {
+            // The first and last segments of a ring share their outer coordinate
+            if closed && a == 0 && b == segments - 1 {
+                continue;
+            }
+            let (p1, p2) = (points[indices[a]], points[indices[a + 1]]);
+            let (q1, q2) = (points[indices[b]], points[indices[b + 1]]);
+            if intersects(p1, p2, q1, q2) {
+                result.push((a, b));
+            }
+        }
+    }
+    result
+}
+
+fn intersects(p1: Coord, p2: Coord, q1: Coord, q2: Coord) -> bool {
+    let orientation = |a: Coord, b: Coord, c: Coord| {
+        let cross = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        if cross.abs() < 1e-9 {
+            0.
+        } else {
+            cross.signum()
+        }
+    };
+    let on_segment = |a: Coord, b: Coord, c: Coord| {
+        c.x >= a.x.min(b.x) && c.x <= a.x.max(b.x) && c.y >= a.y.min(b.y) && c.y <= a.y.max(b.y)
+    };
+    let (o1, o2) = (orientation(p1, p2, q1), orientation(p1, p2, q2));
+    let (o3, o4) = (orientation(q1, q2, p1), orientation(q1, q2, p2));
+    (o1 != o2 && o3 != o4)
+        || (o1 == 0. && on_segment(p1, p2, q1))
+        || (o2 == 0. && on_segment(p1, p2, q2))
+        || (o3 == 0. && on_segment(q1, q2, p1))
+        || (o4 == 0. && on_segment(q1, q2, p2))
+}
+
+#[test]
+fn test_douglas_peucker() {
+    let c = |x: f64, y: f64| Coord {
+        x: x * 0.001,
+        y: y * 0.001,
+    };
+
+    // A zigzag of about 11 m is kept with a tolerance of 5 m, not 20 m
+    let zigzag = [c(0., 0.), c(1., 0.1), c(2., 0.), c(3., 0.1), c(4., 0.)];
+    assert_eq!(vec![0, 1, 2, 3, 4], douglas_peucker(&zigzag, 5.));
+    assert_eq!(vec![0, 4], douglas_peucker(&zigzag, 20.));
+
+    // Keeping 0, 2, 3 and 5 is within 200 m, but the last segment crosses the first one
+    let hook = [
+        c(7., 4.),
+        c(2., 1.),
+        c(1., 2.),
+        c(8., 8.),
+        c(9., 6.),
+        c(5., 1.),
+    ];
+    let kept = douglas_peucker(&hook, 200.);
+    assert_ne!(vec![0, 2, 3, 5], kept);
+    assert!(crossing_segments(&local_plane(&hook), &kept, false).is_empty());
+    assert_eq!((Some(&0), Some(&5)), (kept.first(), kept.last()));
+
+    // A small ring keeps its area
+    let ring = [c(0., 0.), c(1., 0.), c(1., 1.), c(0., 1.), c(0., 0.)];
+    assert_eq!(4, douglas_peucker(&ring, 1_000.).len());
+}
@@ -0,0 +1,280 @@
+//! Spatial index over edge geometries.
+//!
+//! [`EdgeIndex`] stores every segment of every edge in an R-tree, to find the
+//! edge a GPS point or an address lies on. Coordinates are scaled by the cosine
+//! of the mean latitude, so that nearest segments in the index are nearest
+//! segments on the ground.
+
+use super::categorize::{Direction, Mode};
+use super::models::*;
+use rstar::primitives::{GeomWithData, Line};
+use rstar::{PointDistance, RTree};
+
+type Coord = geo_types::Coord<f64>;
+
+/// Shortest length in meters of a degree of latitude, at the equator.
+const MIN_METERS_PER_DEGREE: f64 = 110_574.;
+
+/// A segment of an edge: its index in the edge list and in the geometry.
+type Segment = GeomWithData<Line<[f64; 2]>, (usize, usize)>;
+
+/// A point projected on an edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Projection {
+    /// Index of the edge in the indexed edge list.
+    pub edge: usize,
+    /// Index of the segment of the geometry the point is projected on.
+    pub segment: usize,
+    /// The projected point, on the edge.
+    pub point: Coord,
+    /// Distance in meters between the point and its projection.
+    pub distance: f64,
+    /// Position of the projection along the edge, from 0 at the source to 1 at the target.
+    pub offset: f64,
+}
+
+/// R-tree of the segments of the edges.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{EdgeIndex, Mode};
+///
+/// let graph = osm4routing::read_graph("map.osm.pbf").unwrap();
+/// let index = EdgeIndex::new(&graph.edges);
+/// let gps = geo_types::Coord { x: 2.3522, y: 48.8566 };
+/// if let Some(projection) = index.nearest_edge(gps, Mode::Car) {
+///     let edge = &graph.edges[projection.edge];
+///     println!("{} m from {}, at {:.0}%", projection.distance, edge.id, projection.offset * 100.);
+/// }
+/// ```
+pub struct EdgeIndex<'a> {
+    edges: &'a [Edge],
+    tree: RTree<Segment>,
+    scale: f64,
+}
+
+impl<'a> EdgeIndex<'a> {
+    /// Indexes the segments of the edges.
+    pub fn new(edges: &'a [Edge]) -> EdgeIndex<'a> {
+        let coords_count = edges.iter().map(|edge| edge.geometry.len()).sum::<usize>();
+        let mean_lat = edges
+            .iter()
+            .flat_map(|edge| &edge.geometry)
+            .map(|coord| coord.y)
+            .sum::<f64>()
+            / coords_count.max(1) as f64;
+        let scale = mean_lat.to_radians().cos();
+        let segments = edges
+            .iter()
+            .enumerate()
+            .flat_map(|(e, edge)| {
+                edge.geometry
+                    .windows(2)
+                    .enumerate()
+                    .map(move |(s, coords)| {
+                        let line = Line::new(
+                            [coords[0].x * scale, coords[0].y],
+                            [coords[1].x * scale, coords[1].y],
+                        );
+                        GeomWithData::new(line, (e, s))
+                    })
+            })
+            .collect();
+        EdgeIndex {
+            edges,
+            tree: RTree::bulk_load(segments),
+            scale,
+        }
+    }
+
+    /// Returns the nearest edge usable by a mode in at least one direction.
+    ///
+    /// Returns `None` if no edge is usable by the mode.
+    pub fn nearest_edge(&self, coord: Coord, mode: Mode) -> Option<Projection> {
+        self.nearest_edge_where(coord, |edge| {
+            edge.properties.allows(mode, Direction::Forward)
+                || edge.properties.allows(mode, Direction::Backward)
+        })
+    }
+
+    /// Returns the nearest edge matching a predicate.
+    pub fn nearest_edge_where(
+        &self,
+        coord: Coord,
+        predicate: impl Fn(&Edge) -> bool,
+    ) -> Option<Projection> {
+        let query = [coord.x * self.scale, coord.y];
+        let segment = self
+            .tree
+            .nearest_neighbor_iter(query)
+            .find(|segment| predicate(&self.edges[segment.data.0]))?;
+        Some(self.project(coord, segment))
+    }
+
+    /// Returns the edges usable by a mode within a distance, nearest first.
+    ///
+    /// Each edge is returned once, projected on its nearest segment.
+    pub fn edges_within(&self, coord: Coord, mode: Mode, max_distance: f64) -> Vec<Projection> {
+        let query = [coord.x * self.scale, coord.y];
+        // Farthest a point within max_distance can be in the index: a degree of
+        // longitude is shortest at the latitude nearest to the pole.
+        let dy = max_distance / MIN_METERS_PER_DEGREE;
+        let widest = (coord.y.abs() + dy).min(90.).to_radians().cos();
+        let dx = (dy / widest.max(f64::EPSILON)).min(360.) * self.scale;
+        let bound = (dx * dx + dy * dy) * 1.01;
+        let mut projections: Vec<Projection> = Vec::new();
+        for segment in self.tree.nearest_neighbor_iter(query) {
+            if segment.distance_2(&query) > bound {
+                break;
+            }
+            let projection = self.project(coord, segment);
+            let edge = &self.edges[projection.edge];
+            if projection.distance <= max_distance
+                && (edge.properties.allows(mode, Direction::Forward)
+                    || edge.properties.allows(mode, Direction::Backward))
+                && projections.iter().all(|p| p.edge != projection.edge)
+            {
+                projections.push(projection);
+            }
+        }
+        projections.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        projections
+    }
+
+    fn project(&self, coord: Coord, segment: &Segment) -> Projection {
+        let (edge, index) = segment.data;
+        let nearest = segment
+            .geom()
+            .nearest_point(&[coord.x * self.scale, coord.y]);
+        let point = Coord {
+            x: nearest[0] / self.scale,
+            y: nearest[1],
+        };
+        let geometry = &self.edges[edge].geometry;
+        let model = self.edges[edge].distance_model;
+        let before = model.length(&geometry[..=index]) + model.distance(geometry[index], point);
+        let length = self.edges[edge].length();
+        Projection {
+            edge,
+            segment: index,
+            point,
+            distance: coord.distance_to(point),
+            offset: if length > 0. {
+                (before / length).clamp(0., 1.)
+            } else {
+                0.
+            },
+        }
+    }
+}
+
+/// Splits an edge at a projection, inserting a virtual node there.
+///
+/// Returns the virtual node and the two edges replacing the projected one,
+/// from its source to the node and from the node to its target.
+/// Routing from an arbitrary point can then start at the virtual node.
+pub fn split_edge(edges: &[Edge], projection: &Projection, id: NodeId) -> (Node, Edge, Edge) {
+    let node = Node {
+        id,
+        coord: projection.point,
+        uses: 2,
+        elevation: None,
+        component: None,
+    };
+    let (first, second) = edges[projection.edge].split_at(projection.segment, projection.point, id);
+    (node, first, second)
+}
+
+#[test]
+fn test_nearest_edge() {
+    use super::routing::test_graph;
+
+    let graph = test_graph();
+    let index = EdgeIndex::new(&graph.edges);
+
+    let projection = index
+        .nearest_edge(
+            Coord {
+                x: 0.004,
+                y: 0.0005,
+            },
+            Mode::Car,
+        )
+        .unwrap();
+    assert_eq!(0, projection.edge);
+    assert!((projection.point.x - 0.004).abs() < 1e-9);
+    assert!(projection.point.y.abs() < 1e-9);
+    assert!((projection.distance - 55.6).abs() < 0.1);
+    assert!((projection.offset - 0.4).abs() < 1e-6);
+
+    // Near the footway, which cars cannot use
+    let coord = Coord {
+        x: 0.005,
+        y: -0.0006,
+    };
+    assert_eq!(2, index.nearest_edge(coord, Mode::Foot).unwrap().edge);
+    assert_eq!(0, index.nearest_edge(coord, Mode::Car).unwrap().edge);
+
+    let within = index.edges_within(coord, Mode::Foot, 100.);
+    assert_eq!(
+        vec![2, 0],
+        within.iter().map(|p| p.edge).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_split_edge() {
+    use super::graph::Graph;
+    use super::routing::{Router, Weight, test_graph};
+
+    let graph = test_graph();
+    let index = EdgeIndex::new(&graph.edges);
+    let projection = index
+        .nearest_edge(
+            Coord {
+                x: 0.004,
+                y: 0.0005,
+            },
+            Mode::Car,
+        )
+        .unwrap();
+    let (node, first, second) = split_edge(&graph.edges, &projection, NodeId(-1));
+
+    let (mut nodes, mut edges) = graph.into_parts();
+    edges.remove(projection.edge);
+    nodes.push(node);
+    edges.push(first);
+    edges.push(second);
+    let graph = Graph::new(nodes, edges);
+    let router = Router::new(&graph, Mode::Car, Weight::Length);
+    let from = graph.node_by_osm_id(NodeId(-1)).unwrap();
+    let to = graph.node_by_osm_id(NodeId(3)).unwrap();
+    let path = router.dijkstra(from, to).unwrap();
+    assert_eq!(2, path.arcs.len());
+    assert!((path.cost - 1_779.1).abs() < 1.);
+}
+
+#[test]
+fn test_edges_within_wide_latitudes() {
+    use super::categorize::EdgeProperties;
+
+    let edge = |id: u64, coords: &[(f64, f64)]| Edge {
+        id,
+        geometry: coords.iter().map(|&(x, y)| Coord { x, y }).collect(),
+        properties: EdgeProperties {
+            foot: super::categorize::FootAccessibility::Allowed,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    // The mean latitude is about 50°N, where a degree of longitude is longer than at 60°N
+    let edges = vec![
+        edge(0, &[(-0.01, 61.05), (0.01, 61.05)]),
+        edge(1, &[(1.7, 59.99), (1.7, 60.01)]),
+        edge(2, &[(0., 40.), (0.1, 40.), (0.2, 40.), (0.3, 40.)]),
+    ];
+    let index = EdgeIndex::new(&edges);
+    let within = index.edges_within(Coord { x: 0., y: 60. }, Mode::Foot, 100_000.);
+    assert_eq!(vec![1], within.iter().map(|p| p.edge).collect::<Vec<_>>());
+}
@@ -0,0 +1,163 @@
+//! Bridges, tunnels, layers, covered ways and tolls.
+//!
+//! Those flags often change along a road without any intersection. They are
+//! stored on edges as ranges measured from the source of the edge, so that
+//! a merged edge still knows which stretch is a tunnel.
+
+/// Structural flags of a way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Structure {
+    /// The way is on a bridge (`bridge=*` except `no`).
+    pub bridge: bool,
+    /// The way is in a tunnel (`tunnel=*` except `no`).
+    pub tunnel: bool,
+    /// The way is covered by a building or a roof (`covered=*` except `no`).
+    pub covered: bool,
+    /// A toll must be paid (`toll=yes`).
+    pub toll: bool,
+    /// Vertical ordering relative to other features (`layer`), 0 by default.
+    pub layer: i8,
+}
+
+impl Structure {
+    /// Update flags based on an OSM tag key-value pair.
+    ///
+    /// Recognizes `bridge`, `tunnel`, `covered`, `toll` and `layer`.
+    pub fn update_with_str(&mut self, key: &str, val: &str) {
+        match key {
+            "bridge" => self.bridge = val != "no",
+            "tunnel" => self.tunnel = val != "no",
+            "covered" => self.covered = val != "no",
+            "toll" => self.toll = val == "yes",
+            "layer" => self.layer = val.trim().parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+}
+
+/// Structural flags along a part of an edge.
+///
+/// Offsets are in meters from the source of the edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StructureRange {
+    /// Start offset in meters.
+    pub start: f64,
+    /// End offset in meters.
+    pub end: f64,
+    /// Flags over this range.
+    pub structure: Structure,
+}
+
+/// Reverses ranges so that offsets are measured from the other end.
+pub(crate) fn reverse_ranges(ranges: &mut [StructureRange]) {
+    let length = ranges.last().map(|range| range.end).unwrap_or(0.);
+    for range in ranges.iter_mut() {
+        (range.start, range.end) = (length - range.end, length - range.start);
+    }
+    ranges.reverse();
+}
+
+/// Appends the ranges of a following edge, shifting their offsets.
+///
+/// Consecutive ranges with the same flags are coalesced.
+pub(crate) fn concat_ranges(ranges: &mut Vec<StructureRange>, other: &[StructureRange]) {
+    let offset = ranges.last().map(|range| range.end).unwrap_or(0.);
+    for range in other {
+        let range = StructureRange {
+            start: range.start + offset,
+            end: range.end + offset,
+            structure: range.structure,
+        };
+        match ranges.last_mut() {
+            Some(last) if last.structure == range.structure => last.end = range.end,
+            _ => ranges.push(range),
+        }
+    }
+}
+
+/// Splits ranges at an offset, the second part being measured from the offset.
+pub(crate) fn split_ranges(
+    ranges: &[StructureRange],
+    at: f64,
+) -> (Vec<StructureRange>, Vec<StructureRange>) {
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    for range in ranges {
+        if range.start < at {
+            before.push(StructureRange {
+                end: range.end.min(at),
+                ..*range
+            });
+        }
+        if range.end > at {
+            after.push(StructureRange {
+                start: (range.start - at).max(0.),
+                end: range.end - at,
+                structure: range.structure,
+            });
+        }
+    }
+    (before, after)
+}
+
+#[test]
+fn test_update() {
+    let mut s = Structure::default();
+    s.update_with_str("bridge", "viaduct");
+    s.update_with_str("layer", "2");
+    s.update_with_str("toll", "no");
+    assert!(s.bridge);
+    assert!(!s.tunnel);
+    assert!(!s.toll);
+    assert_eq!(2, s.layer);
+
+    s.update_with_str("bridge", "no");
+    s.update_with_str("tunnel", "building_passage");
+    s.update_with_str("layer", "-1");
+    assert!(!s.bridge);
+    assert!(s.tunnel);
+    assert_eq!(-1, s.layer);
+}
+
+#[test]
+fn test_ranges() {
+    let tunnel = Structure {
+        tunnel: true,
+        layer: -1,
+        ..Default::default()
+    };
+    let mut ranges = vec![StructureRange {
+        start: 0.,
+        end: 10.,
+        structure: Structure::default(),
+    }];
+    concat_ranges(
+        &mut ranges,
+        &[
+            StructureRange {
+                start: 0.,
+                end: 5.,
+                structure: Structure::default(),
+            },
+            StructureRange {
+                start: 5.,
+                end: 8.,
+                structure: tunnel,
+            },
+        ],
+    );
+    assert_eq!(2, ranges.len());
+    assert_eq!((0., 15.), (ranges[0].start, ranges[0].end));
+    assert_eq!((15., 18.), (ranges[1].start, ranges[1].end));
+
+    reverse_ranges(&mut ranges);
+    assert_eq!(tunnel, ranges[0].structure);
+    assert_eq!((0., 3.), (ranges[0].start, ranges[0].end));
+    assert_eq!((3., 18.), (ranges[1].start, ranges[1].end));
+
+    let (before, after) = split_ranges(&ranges, 10.);
+    assert_eq!(2, before.len());
+    assert_eq!((3., 10.), (before[1].start, before[1].end));
+    assert_eq!(1, after.len());
+    assert_eq!((0., 8.), (after[0].start, after[0].end));
+}
@@ -0,0 +1,467 @@
+//! Quality checks of a routing graph.
+//!
+//! [`Report::new`] looks for edges that are likely to be mapping or
+//! extraction errors: loops, duplicates, empty edges, edges whose ends are
+//! missing, one-way streets leading nowhere, and crossings or overlaps
+//! without a node.
+//! Each [`Issue`] has a location, so the report can be opened in a GIS.
+
+use super::categorize::{Direction, Mode};
+use super::models::*;
+use ahash::{HashMap, HashMapExt, HashSet};
+use geo::line_intersection::{LineIntersection, line_intersection};
+use rstar::RTree;
+use rstar::primitives::{GeomWithData, Line};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+type Coord = geo_types::Coord<f64>;
+
+/// A segment of an edge: its index in the edge list and in the geometry.
+type Segment = GeomWithData<Line<[f64; 2]>, (usize, usize)>;
+
+/// Modes that can be restricted to one direction.
+const ONEWAY_MODES: [Mode; 2] = [Mode::Car, Mode::Bike];
+
+/// What is wrong.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// An edge starts and ends at the same node.
+    SelfLoop,
+    /// Two edges join the same nodes with the same geometry.
+    ParallelEdge,
+    /// An edge has a length of 0 meters.
+    ZeroLength,
+    /// An edge ends at a node that is not in the node list.
+    MissingNode,
+    /// A mode can reach a node but not leave it, or leave it but not reach it.
+    OnewayDeadEnd,
+    /// Two edges on the same level cross without sharing a node.
+    Crossing,
+    /// Two edges on the same level run along each other without being duplicates.
+    Overlap,
+}
+
+impl IssueKind {
+    /// All the kinds, in the order of the report.
+    pub const ALL: [IssueKind; 7] = [
+        IssueKind::SelfLoop,
+        IssueKind::ParallelEdge,
+        IssueKind::ZeroLength,
+        IssueKind::MissingNode,
+        IssueKind::OnewayDeadEnd,
+        IssueKind::Crossing,
+        IssueKind::Overlap,
+    ];
+
+    /// Name of the kind in the reports, like `self_loop`.
+    pub fn name(self) -> &'static str {
+        match self {
+            IssueKind::SelfLoop => "self_loop",
+            IssueKind::ParallelEdge => "parallel_edge",
+            IssueKind::ZeroLength => "zero_length",
+            IssueKind::MissingNode => "missing_node",
+            IssueKind::OnewayDeadEnd => "oneway_dead_end",
+            IssueKind::Crossing => "crossing",
+            IssueKind::Overlap => "overlap",
+        }
+    }
+}
+
+/// A problem found in the graph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    /// What is wrong.
+    pub kind: IssueKind,
+    /// Ids of the edges involved.
+    pub edges: Vec<u64>,
+    /// The node involved, for missing nodes and dead-ends.
+    pub node: Option<NodeId>,
+    /// The mode that is stuck, for dead-ends.
+    pub mode: Option<Mode>,
+    /// Where the problem is.
+    pub coord: Coord,
+}
+
+/// Issues found in a graph.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::{IssueKind, Report};
+///
+/// let (nodes, edges) = osm4routing::read("map.osm.pbf").unwrap();
+/// let report = Report::new(&nodes, &edges);
+/// println!("{} crossings", report.count(IssueKind::Crossing));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Issues, grouped by kind in the order of [`IssueKind::ALL`].
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    /// Checks the nodes and edges.
+    ///
+    /// Crossings and overlaps of edges on different layers, on a bridge or in
+    /// a tunnel are not reported.
+    pub fn new(nodes: &[Node], edges: &[Edge]) -> Report {
+        let mut issues = Vec::new();
+        issues.extend(self_loops(edges));
+        issues.extend(parallel_edges(edges));
+        issues.extend(zero_lengths(edges));
+        issues.extend(missing_nodes(nodes, edges));
+        issues.extend(oneway_dead_ends(nodes, edges));
+        issues.extend(crossings(edges));
+        Report { issues }
+    }
+
+    /// Number of issues of a kind.
+    pub fn count(&self, kind: IssueKind) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.kind == kind)
+            .count()
+    }
+
+    /// Whether no issue was found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns the counts per kind and the issues as JSON.
+    ///
+    /// Each issue has its `kind`, `edges`, `node`, `mode`, `lon` and `lat`.
+    pub fn to_json(&self) -> Value {
+        let issues: Vec<Value> = self
+            .issues
+            .iter()
+            .map(|issue| {
+                let mut properties = properties(issue);
+                properties["lon"] = json!(issue.coord.x);
+                properties["lat"] = json!(issue.coord.y);
+                properties
+            })
+            .collect();
+        json!({"counts": self.counts(), "issues": issues})
+    }
+
+    /// Returns the issues as a GeoJSON `FeatureCollection` of points.
+    ///
+    /// Features have the `kind`, `edges`, `node` and `mode` of the issue as
+    /// properties; the collection also has the `counts` per kind.
+    pub fn to_geojson(&self) -> Value {
+        let features: Vec<Value> = self
+            .issues
+            .iter()
+            .map(|issue| {
+                json!({
+                    "type": "Feature",
+                    "properties": properties(issue),
+                    "geometry": {"type": "Point", "coordinates": [issue.coord.x, issue.coord.y]},
+                })
+            })
+            .collect();
+        json!({"type": "FeatureCollection", "counts": self.counts(), "features": features})
+    }
+
+    fn counts(&self) -> Value {
+        IssueKind::ALL
+            .iter()
+            .map(|kind| (kind.name().to_string(), json!(self.count(*kind))))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+}
+
+fn properties(issue: &Issue) -> Value {
+    json!({
+        "kind": issue.kind,
+        "edges": issue.edges,
+        "node": issue.node.map(|node| node.0),
+        "mode": issue.mode,
+    })
+}
+
+fn edge_issue(kind: IssueKind, edges: &[&Edge]) -> Issue {
+    Issue {
+        kind,
+        edges: edges.iter().map(|edge| edge.id).collect(),
+        node: None,
+        mode: None,
+        coord: edges[0].geometry.first().copied().unwrap_or_default(),
+    }
+}
+
+fn self_loops(edges: &[Edge]) -> impl Iterator<Item = Issue> + '_ {
+    edges
+        .iter()
+        .filter(|edge| edge.source == edge.target)
+        .map(|edge| edge_issue(IssueKind::SelfLoop, &[edge]))
+}
+
+fn parallel_edges(edges: &[Edge]) -> Vec<Issue> {
+    let mut by_ends: HashMap<(NodeId, NodeId), Vec<&Edge>> = HashMap::new();
+    for edge in edges {
+        let ends = (edge.source.min(edge.target), edge.source.max(edge.target));
+        by_ends.entry(ends).or_default().push(edge);
+    }
+    let mut issues = Vec::new();
+    for edge in edges {
+        let ends = (edge.source.min(edge.target), edge.source.max(edge.target));
+        // Each pair is reported once, from its first edge
+        let mut others = by_ends[&ends]
+            .iter()
+            .skip_while(|other| !std::ptr::eq(**other, edge));
+        others.next();
+        for other in others {
+            if same_geometry(edge, other) {
+                issues.push(edge_issue(IssueKind::ParallelEdge, &[edge, other]));
+            }
+        }
+    }
+    issues
+}
+
+fn zero_lengths(edges: &[Edge]) -> impl Iterator<Item = Issue> + '_ {
+    edges
+        .iter()
+        .filter(|edge| edge.length() == 0.)
+        .map(|edge| edge_issue(IssueKind::ZeroLength, &[edge]))
+}
+
+fn missing_nodes(nodes: &[Node], edges: &[Edge]) -> Vec<Issue> {
+    let known: HashSet<NodeId> = nodes.iter().map(|node| node.id).collect();
+    let mut issues = Vec::new();
+    for edge in edges {
+        let ends = [
+            (edge.source, edge.geometry.first()),
+            (edge.target, edge.geometry.last()),
+        ];
+        for (node, coord) in ends {
+            if !known.contains(&node) {
+                issues.push(Issue {
+                    node: Some(node),
+                    coord: coord.copied().unwrap_or_default(),
+                    ..edge_issue(IssueKind::MissingNode, &[edge])
+                });
+            }
+        }
+    }
+    issues
+}
+
+fn oneway_dead_ends(nodes: &[Node], edges: &[Edge]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for mode in ONEWAY_MODES {
+        // Edges arriving at and leaving each node
+        let mut arcs: HashMap<NodeId, (Vec<u64>, Vec<u64>)> = HashMap::new();
+        for edge in edges {
+            if edge.properties.allows(mode, Direction::Forward) {
+                arcs.entry(edge.source).or_default().1.push(edge.id);
+                arcs.entry(edge.target).or_default().0.push(edge.id);
+            }
+            if edge.properties.allows(mode, Direction::Backward) {
+                arcs.entry(edge.target).or_default().1.push(edge.id);
+                arcs.entry(edge.source).or_default().0.push(edge.id);
+            }
+        }
+        for node in nodes {
+            if let Some((incoming, outgoing)) = arcs.remove(&node.id)
+                && (incoming.is_empty() || outgoing.is_empty())
+            {
+                issues.push(Issue {
+                    kind: IssueKind::OnewayDeadEnd,
+                    edges: [incoming, outgoing].concat(),
+                    node: Some(node.id),
+                    mode: Some(mode),
+                    coord: node.coord,
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// Whether two edges are drawn the same, in either direction.
+fn same_geometry(edge: &Edge, other: &Edge) -> bool {
+    edge.geometry == other.geometry || edge.geometry.iter().eq(other.geometry.iter().rev())
+}
+
+fn crossings(edges: &[Edge]) -> Vec<Issue> {
+    let structures: Vec<_> = edges.iter().map(Edge::structure).collect();
+    let segments: Vec<Segment> = edges
+        .iter()
+        .enumerate()
+        .flat_map(|(e, edge)| {
+            edge.geometry
+                .windows(2)
+                .enumerate()
+                .map(move |(s, coords)| {
+                    let line = Line::new([coords[0].x, coords[0].y], [coords[1].x, coords[1].y]);
+                    GeomWithData::new(line, (e, s))
+                })
+        })
+        .collect();
+    let tree = RTree::bulk_load(segments.clone());
+
+    let mut issues = Vec::new();
+    let mut reported = HashSet::default();
+    for segment in &segments {
+        let (e, _) = segment.data;
+        let envelope = rstar::RTreeObject::envelope(segment);
+        for other in tree.locate_in_envelope_intersecting(envelope) {
+            let (f, _) = other.data;
+            let (a, b) = (structures[e], structures[f]);
+            if f <= e || a.bridge || a.tunnel || b.bridge || b.tunnel || a.layer != b.layer {
+                continue;
+            }
+            let line = |segment: &Segment| {
+                let [from, to] = [segment.geom().from, segment.geom().to];
+                geo_types::Line::new(
+                    Coord {
+                        x: from[0],
+                        y: from[1],
+                    },
+                    Coord { x: to[0], y: to[1] },
+                )
+            };
+            let (first, second) = (line(segment), line(other));
+            let (kind, coord) = match line_intersection(first, second) {
+                Some(LineIntersection::SinglePoint { intersection, .. }) => {
+                    // Segments meeting at a common end share a node
+                    let is_end = |line: &geo_types::Line<f64>| {
+                        intersection == line.start || intersection == line.end
+                    };
+                    if is_end(&first) && is_end(&second) {
+                        continue;
+                    }
+                    (IssueKind::Crossing, intersection)
+                }
+                // Duplicates are already reported as parallel edges
+                Some(LineIntersection::Collinear { intersection })
+                    if intersection.start != intersection.end
+                        && !same_geometry(&edges[e], &edges[f]) =>
+                {
+                    let middle = Coord {
+                        x: (intersection.start.x + intersection.end.x) / 2.,
+                        y: (intersection.start.y + intersection.end.y) / 2.,
+                    };
+                    (IssueKind::Overlap, middle)
+                }
+                _ => continue,
+            };
+            if reported.insert((kind, e, f)) {
+                issues.push(Issue {
+                    coord,
+                    ..edge_issue(kind, &[&edges[e], &edges[f]])
+                });
+            }
+        }
+    }
+    issues.sort_by_key(|issue| issue.kind);
+    issues
+}
+
+#[test]
+fn test_report() {
+    use super::categorize::EdgeProperties;
+
+    let c = |x: f64, y: f64| Coord {
+        x: x * 0.001,
+        y: y * 0.001,
+    };
+    let node = |id: i64, coord: Coord| Node {
+        id: NodeId(id),
+        coord,
+        ..Default::default()
+    };
+    let mut street = EdgeProperties::default();
+    street.update_with_str("highway", "residential");
+    street.normalize();
+    let mut oneway = street;
+    oneway.update_with_str("oneway", "yes");
+    oneway.normalize();
+    let edge = |id: u64, source: i64, target: i64, geometry: Vec<Coord>, properties| Edge {
+        id,
+        source: NodeId(source),
+        target: NodeId(target),
+        geometry,
+        properties,
+        ..Default::default()
+    };
+    let nodes = vec![
+        node(1, c(0., 0.)),
+        node(2, c(2., 0.)),
+        node(3, c(1., -1.)),
+        node(4, c(1., 1.)),
+        node(5, c(3., 0.)),
+        node(7, c(1., 0.5)),
+        node(8, c(1., 1.5)),
+    ];
+    let edges = vec![
+        edge(10, 1, 2, vec![c(0., 0.), c(2., 0.)], street),
+        // Crosses the first edge without a node
+        edge(11, 3, 4, vec![c(1., -1.), c(1., 1.)], street),
+        // Duplicate of the first edge, drawn backwards
+        edge(12, 2, 1, vec![c(2., 0.), c(0., 0.)], street),
+        // Leads to node 5, that cars cannot leave
+        edge(13, 2, 5, vec![c(2., 0.), c(3., 0.)], oneway),
+        edge(14, 3, 3, vec![c(1., -1.), c(1., -1.)], street),
+        edge(
+            15,
+            5,
+            6,
+            vec![c(3., 0.), c(4., 0.)],
+            EdgeProperties::default(),
+        ),
+        // Runs along the second edge without sharing its nodes
+        edge(16, 7, 8, vec![c(1., 0.5), c(1., 1.5)], street),
+    ];
+
+    let report = Report::new(&nodes, &edges);
+    assert_eq!(1, report.count(IssueKind::SelfLoop));
+    assert_eq!(1, report.count(IssueKind::ParallelEdge));
+    assert_eq!(1, report.count(IssueKind::ZeroLength));
+    assert_eq!(1, report.count(IssueKind::MissingNode));
+    assert_eq!(2, report.count(IssueKind::Crossing));
+    assert_eq!(1, report.count(IssueKind::Overlap));
+    assert!(
+        report
+            .issues
+            .iter()
+            .any(|issue| issue.kind == IssueKind::OnewayDeadEnd
+                && issue.node == Some(NodeId(5))
+                && issue.mode == Some(Mode::Car))
+    );
+    let missing = report
+        .issues
+        .iter()
+        .find(|issue| issue.kind == IssueKind::MissingNode)
+        .unwrap();
+    assert_eq!((Some(NodeId(6)), c(4., 0.)), (missing.node, missing.coord));
+    let crossing = report
+        .issues
+        .iter()
+        .find(|issue| issue.kind == IssueKind::Crossing)
+        .unwrap();
+    assert_eq!(vec![10, 11], crossing.edges);
+    assert!((crossing.coord.x - 0.001).abs() < 1e-12 && crossing.coord.y.abs() < 1e-12);
+    let overlap = report
+        .issues
+        .iter()
+        .find(|issue| issue.kind == IssueKind::Overlap)
+        .unwrap();
+    assert_eq!(vec![11, 16], overlap.edges);
+    assert!((overlap.coord.x - 0.001).abs() < 1e-12 && (overlap.coord.y - 0.00075).abs() < 1e-12);
+
+    let json = report.to_json();
+    assert_eq!(2, json["counts"]["crossing"]);
+    let geojson = report.to_geojson();
+    assert_eq!(
+        report.issues.len(),
+        geojson["features"].as_array().unwrap().len()
+    );
+    assert_eq!("self_loop", geojson["features"][0]["properties"]["kind"]);
+}
@@ -2,33 +2,145 @@
 //!
 //! This module provides functions to export the routing graph to various formats.
 
+use super::categorize::*;
+use super::crs::{Crs, Reprojection};
 use super::error::Error;
+use super::graph::Graph;
+use super::isochrone::Isochrone;
+use super::lanes::format_turn_lanes;
+use super::mapmatch::MatchedTrace;
+use super::matrix::{Location, Matrix};
 use super::models::*;
+use super::validate::Report;
+use serde::Serialize;
+
+/// Columns of the edges CSV file, in the order of the fields of [`EdgeRecord`].
+const EDGE_COLUMNS: [&str; 35] = [
+    "id",
+    "composite_id",
+    "osm_id",
+    "source",
+    "target",
+    "length",
+    "foot",
+    "car_forward",
+    "car_backward",
+    "bike_forward",
+    "bike_backward",
+    "train",
+    "moped",
+    "surface",
+    "smoothness",
+    "tracktype",
+    "mtb_scale",
+    "lts_forward",
+    "lts_backward",
+    "lanes_forward",
+    "lanes_backward",
+    "width",
+    "turn_lanes_forward",
+    "turn_lanes_backward",
+    "bridge",
+    "tunnel",
+    "covered",
+    "toll",
+    "layer",
+    "ascent",
+    "descent",
+    "max_grade_forward",
+    "max_grade_backward",
+    "component",
+    "wkt",
+];
+
+/// A row of the edges CSV file, with the columns of [`EDGE_COLUMNS`].
+#[derive(Serialize)]
+struct EdgeRecord<'a> {
+    id: u64,
+    composite_id: &'a str,
+    osm_id: i64,
+    source: i64,
+    target: i64,
+    length: f64,
+    foot: FootAccessibility,
+    car_forward: CarAccessibility,
+    car_backward: CarAccessibility,
+    bike_forward: BikeAccessibility,
+    bike_backward: BikeAccessibility,
+    train: TrainAccessibility,
+    moped: MopedAccessibility,
+    surface: Surface,
+    smoothness: Smoothness,
+    tracktype: TrackType,
+    mtb_scale: MtbScale,
+    lts_forward: Lts,
+    lts_backward: Lts,
+    lanes_forward: u8,
+    lanes_backward: u8,
+    width: Option<f64>,
+    turn_lanes_forward: String,
+    turn_lanes_backward: String,
+    bridge: bool,
+    tunnel: bool,
+    covered: bool,
+    toll: bool,
+    layer: i8,
+    ascent: Option<f64>,
+    descent: Option<f64>,
+    max_grade_forward: Option<f64>,
+    max_grade_backward: Option<f64>,
+    component: Option<usize>,
+    wkt: String,
+}
 
 /// Writes nodes and edges to CSV files.
 ///
 /// Creates two CSV files: one for nodes and one for edges.
 ///
 /// # Node CSV Format
-/// Columns: `id`, `lon`, `lat`
+/// Columns: `id`, `lon`, `lat`, `ele`, `component`
 /// - `id`: The OSM node ID
 /// - `lon`: Longitude in decimal degrees (WGS84)
 /// - `lat`: Latitude in decimal degrees (WGS84)
+/// - `ele`: Elevation in meters (empty without elevation model)
+/// - `component`: Connected component, 0 being the largest (empty if not computed)
 ///
 /// # Edge CSV Format
-/// Columns: `id`, `osm_id`, `source`, `target`, `length`, `foot`, `car_forward`,
-/// `car_backward`, `bike_forward`, `bike_backward`, `train`, `wkt`
-/// - `id`: Unique edge identifier (format: "{way_id}-{index}")
+/// Columns: `id`, `composite_id`, `osm_id`, `source`, `target`, `length`, `foot`,
+/// `car_forward`, `car_backward`, `bike_forward`, `bike_backward`, `train`, `moped`, `surface`,
+/// `smoothness`, `tracktype`, `mtb_scale`, `lts_forward`, `lts_backward`, `lanes_forward`, `lanes_backward`,
+/// `width`, `turn_lanes_forward`, `turn_lanes_backward`, `bridge`, `tunnel`, `covered`, `toll`,
+/// `layer`, `ascent`, `descent`, `max_grade_forward`, `max_grade_backward`, `component`, `wkt`
+/// - `id`: Unique edge identifier, stable across extractions (see [`stable_edge_id`])
+/// - `composite_id`: Edge identifier from the position in the way (format: "{way_id}-{index}")
 /// - `osm_id`: The original OSM way ID
 /// - `source`: ID of the starting node
 /// - `target`: ID of the ending node
-/// - `length`: Length in meters
+/// - `length`: Length in meters, measured with the distance model of the edge
 /// - `foot`: Pedestrian accessibility (`Allowed`/`Forbidden`)
 /// - `car_forward`: Car accessibility in forward direction
 /// - `car_backward`: Car accessibility in backward direction
 /// - `bike_forward`: Bike accessibility in forward direction
 /// - `bike_backward`: Bike accessibility in backward direction
 /// - `train`: Train accessibility
+/// - `moped`: Moped accessibility
+/// - `surface`: Normalized road surface
+/// - `smoothness`: Usability for wheeled vehicles
+/// - `tracktype`: Firmness of tracks
+/// - `mtb_scale`: Mountain bike difficulty
+/// - `lts_forward`: Bike Level of Traffic Stress (1 to 4) in forward direction
+/// - `lts_backward`: Bike Level of Traffic Stress (1 to 4) in backward direction
+/// - `lanes_forward`: Number of lanes in forward direction
+/// - `lanes_backward`: Number of lanes in backward direction
+/// - `width`: Width in meters (empty if not tagged)
+/// - `turn_lanes_forward`: Turn lanes in forward direction, in the `turn:lanes` notation
+/// - `turn_lanes_backward`: Turn lanes in backward direction, in the `turn:lanes` notation
+/// - `bridge`, `tunnel`, `covered`, `toll`: `true` if it applies to any part of the edge
+/// - `layer`: Layer of the longest part of the edge
+/// - `ascent`, `descent`: Elevation gain and loss in meters from source to target
+///   (empty without elevation model)
+/// - `max_grade_forward`, `max_grade_backward`: Steepest uphill grade in percent in each direction
+/// - `component`: Connected component, 0 being the largest (empty if not computed)
 /// - `wkt`: Geometry as WKT LINESTRING
 ///
 /// # Arguments
@@ -54,45 +166,225 @@ pub fn csv(
     nodes_file: &str,
     edges_file: &str,
 ) -> Result<(), Error> {
+    csv_in_crs(nodes, edges, nodes_file, edges_file, &Crs::Wgs84)
+}
+
+/// Writes nodes and edges to CSV files, with coordinates in another system.
+///
+/// Same as [`csv`], with the node coordinates and the WKT geometries reprojected.
+/// In a projected system, the `lon` and `lat` columns become `x` and `y`, and
+/// the WKT has 3 decimals. [`Crs::UtmAuto`] takes the zone of the mean coordinate
+/// of the nodes. Lengths are not affected: they are measured on the ellipsoid.
+///
+/// # Errors
+/// Returns an error if the coordinate system is invalid, if a coordinate
+/// cannot be reprojected, or if file creation or CSV serialization fails.
+///
+/// # Example
+///
+/// ```no_run
+/// use osm4routing::Crs;
+///
+/// let (nodes, edges) = osm4routing::read("map.osm.pbf").unwrap();
+/// osm4routing::writers::csv_in_crs(nodes, edges, "nodes.csv", "edges.csv", &Crs::UtmAuto).unwrap();
+/// ```
+pub fn csv_in_crs(
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    nodes_file: &str,
+    edges_file: &str,
+    crs: &Crs,
+) -> Result<(), Error> {
+    let center = geo_types::Coord {
+        x: nodes.iter().map(|node| node.coord.x).sum::<f64>() / nodes.len().max(1) as f64,
+        y: nodes.iter().map(|node| node.coord.y).sum::<f64>() / nodes.len().max(1) as f64,
+    };
+    let reprojection = Reprojection::new(crs, center)?;
+    let decimals = if reprojection.is_geographic() { 7 } else { 3 };
+
     let edges_path = std::path::Path::new(edges_file);
-    let mut edges_csv = csv::Writer::from_path(edges_path)?;
-    edges_csv.serialize(vec![
-        "id",
-        "osm_id",
-        "source",
-        "target",
-        "length",
-        "foot",
-        "car_forward",
-        "car_backward",
-        "bike_forward",
-        "bike_backward",
-        "train",
-        "wkt",
-    ])?;
+    // The header is written even without any edge
+    let mut edges_csv = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_path(edges_path)?;
+    edges_csv.write_record(EDGE_COLUMNS)?;
     for edge in edges {
-        edges_csv.serialize((
-            &edge.id,
-            edge.osm_id.0,
-            edge.source.0,
-            edge.target.0,
-            edge.length(),
-            edge.properties.foot,
-            edge.properties.car_forward,
-            edge.properties.car_backward,
-            edge.properties.bike_forward,
-            edge.properties.bike_backward,
-            edge.properties.train,
-            edge.as_wkt(),
-        ))?;
+        let structure = edge.structure();
+        edges_csv.serialize(EdgeRecord {
+            id: edge.id,
+            composite_id: &edge.composite_id,
+            osm_id: edge.osm_id.0,
+            source: edge.source.0,
+            target: edge.target.0,
+            length: edge.length(),
+            foot: edge.properties.foot,
+            car_forward: edge.properties.car_forward,
+            car_backward: edge.properties.car_backward,
+            bike_forward: edge.properties.bike_forward,
+            bike_backward: edge.properties.bike_backward,
+            train: edge.properties.train,
+            moped: edge.properties.moped,
+            surface: edge.properties.surface,
+            smoothness: edge.properties.smoothness,
+            tracktype: edge.properties.tracktype,
+            mtb_scale: edge.properties.mtb_scale,
+            lts_forward: edge.properties.lts_forward,
+            lts_backward: edge.properties.lts_backward,
+            lanes_forward: edge.lanes.forward,
+            lanes_backward: edge.lanes.backward,
+            width: edge.lanes.width,
+            turn_lanes_forward: format_turn_lanes(&edge.lanes.turn_forward),
+            turn_lanes_backward: format_turn_lanes(&edge.lanes.turn_backward),
+            bridge: structure.bridge,
+            tunnel: structure.tunnel,
+            covered: structure.covered,
+            toll: structure.toll,
+            layer: structure.layer,
+            ascent: edge.climb.map(|climb| climb.ascent),
+            descent: edge.climb.map(|climb| climb.descent),
+            max_grade_forward: edge.climb.map(|climb| climb.max_grade_forward),
+            max_grade_backward: edge.climb.map(|climb| climb.max_grade_backward),
+            component: edge.component,
+            wkt: if crs == &Crs::Wgs84 {
+                edge.as_wkt()
+            } else {
+                let coords = edge
+                    .geometry
+                    .iter()
+                    .map(|coord| {
+                        let coord = reprojection.project(*coord)?;
+                        Ok(format!("{:.*} {:.*}", decimals, coord.x, decimals, coord.y))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                format!("LINESTRING({})", coords.join(", "))
+            },
+        })?;
     }
 
     let nodes_path = std::path::Path::new(nodes_file);
     let mut nodes_csv = csv::Writer::from_path(nodes_path)?;
-    nodes_csv.serialize(vec!["id", "lon", "lat"])?;
+    if reprojection.is_geographic() {
+        nodes_csv.serialize(vec!["id", "lon", "lat", "ele", "component"])?;
+    } else {
+        nodes_csv.serialize(vec!["id", "x", "y", "ele", "component"])?;
+    }
     for node in nodes {
-        nodes_csv.serialize((node.id.0, node.coord.x, node.coord.y))?;
+        let coord = reprojection.project(node.coord)?;
+        nodes_csv.serialize((node.id.0, coord.x, coord.y, node.elevation, node.component))?;
+    }
+
+    Ok(())
+}
+
+/// Writes a cost matrix to a CSV file, one row per origin and destination.
+///
+/// # Matrix CSV Format
+/// Columns: `origin`, `destination`, `cost`
+/// - `origin`: Id of the origin location
+/// - `destination`: Id of the destination location
+/// - `cost`: Cost in the unit of the weight (empty if unreachable or not snapped)
+///
+/// # Errors
+/// Returns an error if file creation or CSV serialization fails.
+pub fn matrix_csv(
+    origins: &[Location],
+    destinations: &[Location],
+    matrix: &Matrix,
+    matrix_file: &str,
+) -> Result<(), Error> {
+    let mut matrix_csv = csv::Writer::from_path(std::path::Path::new(matrix_file))?;
+    matrix_csv.serialize(vec!["origin", "destination", "cost"])?;
+    for (origin, costs) in origins.iter().zip(&matrix.costs) {
+        for (destination, cost) in destinations.iter().zip(costs) {
+            matrix_csv.serialize((&origin.id, &destination.id, cost))?;
+        }
     }
+    Ok(())
+}
+
+/// Writes an isochrone to a GeoJSON file.
+///
+/// See [`Isochrone::to_geojson`] for the features.
+///
+/// # Errors
+/// Returns an error if file creation or JSON serialization fails.
+pub fn isochrone_geojson(graph: &Graph, isochrone: &Isochrone, path: &str) -> Result<(), Error> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer(file, &isochrone.to_geojson(graph))?;
+    Ok(())
+}
+
+/// Writes a validation report to a JSON or GeoJSON file.
+///
+/// Files ending with `.geojson` get [`Report::to_geojson`], other files
+/// [`Report::to_json`].
+///
+/// # Errors
+/// Returns an error if file creation or JSON serialization fails.
+pub fn report_json(report: &Report, path: &str) -> Result<(), Error> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let json = if path.ends_with(".geojson") {
+        report.to_geojson()
+    } else {
+        report.to_json()
+    };
+    serde_json::to_writer(file, &json)?;
+    Ok(())
+}
 
+/// Writes the edges of a matched trace to a CSV file, in order.
+///
+/// # Matched edges CSV Format
+/// Columns: `id`, `osm_id`, `direction`, `start`, `end`
+/// - `id`: Unique edge identifier
+/// - `osm_id`: The original OSM way ID
+/// - `direction`: `Forward` or `Backward` relative to the edge geometry
+/// - `start`: Time of the first trace point on the edge (see [`MatchedEdge`](super::mapmatch::MatchedEdge))
+/// - `end`: Time of the last trace point on the edge
+///
+/// # Errors
+/// Returns an error if file creation or CSV serialization fails.
+pub fn matched_csv(graph: &Graph, matched: &MatchedTrace, path: &str) -> Result<(), Error> {
+    let mut matched_csv = csv::Writer::from_path(std::path::Path::new(path))?;
+    matched_csv.serialize(vec!["id", "osm_id", "direction", "start", "end"])?;
+    for matched_edge in &matched.edges {
+        let edge = &graph.edges[matched_edge.edge];
+        matched_csv.serialize((
+            edge.id,
+            edge.osm_id.0,
+            matched_edge.direction,
+            &matched_edge.start,
+            &matched_edge.end,
+        ))?;
+    }
     Ok(())
 }
+
+#[test]
+fn test_csv_header() {
+    let dir = std::env::temp_dir().join(format!("osm4routing_csv_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let nodes_file = dir.join("nodes.csv");
+    let edges_file = dir.join("edges.csv");
+    let (nodes_file, edges_file) = (nodes_file.to_str().unwrap(), edges_file.to_str().unwrap());
+    let header = |path: &str| {
+        let content = std::fs::read_to_string(path).unwrap();
+        content.lines().map(str::to_string).collect::<Vec<_>>()
+    };
+
+    csv(vec![], vec![], nodes_file, edges_file).unwrap();
+    assert_eq!(vec![EDGE_COLUMNS.join(",")], header(edges_file));
+    assert_eq!(vec!["id,lon,lat,ele,component"], header(nodes_file));
+
+    let edge = Edge {
+        geometry: vec![geo_types::Coord { x: 0., y: 0. }; 2],
+        ..Default::default()
+    };
+    csv(vec![], vec![edge], nodes_file, edges_file).unwrap();
+    let mut reader = ::csv::Reader::from_path(edges_file).unwrap();
+    assert_eq!(
+        EDGE_COLUMNS.len(),
+        reader.records().next().unwrap().unwrap().len()
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}